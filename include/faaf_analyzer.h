@@ -0,0 +1,48 @@
+/*
+ * ABI of the `.so` analyzers of faaf (extension = "so").
+ *
+ * The library lib<name>.so exports the three functions below. faaf calls analyzer_main with the JSON
+ * arguments of one file (UTF-8, not NUL terminated) and reads the output from *output. The output belongs
+ * to the library: faaf copies it and hands it back with analyzer_free, so it can be allocated with any
+ * allocator. analyzer_main may be called from several threads at the same time.
+ *
+ * analyzer_main returns FAAF_ANALYZER_OK with the output JSON in *output, or another value with an error
+ * message (or nothing) in *output, which faaf stores in the analyzer_error table. The output must be UTF-8.
+ *
+ * A library without analyzer_abi_version is rejected, unless the analyzer sets legacy_abi = true: it is then
+ * called with the version 0 ABI, where analyzer_main returns the output and faaf frees it with the allocator
+ * of Rust. It only works for Rust libraries and is deprecated.
+ */
+#ifndef FAAF_ANALYZER_H
+#define FAAF_ANALYZER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FAAF_ANALYZER_ABI_VERSION 1
+
+#define FAAF_ANALYZER_OK 0
+
+typedef struct faaf_output {
+    uint8_t *data;
+    size_t len;
+} faaf_output;
+
+/* returns FAAF_ANALYZER_ABI_VERSION */
+uint32_t analyzer_abi_version(void);
+
+/* *output is { NULL, 0 } when called. a NULL output is an error when FAAF_ANALYZER_OK is returned. */
+int32_t analyzer_main(const uint8_t *input, size_t input_len, faaf_output *output);
+
+/* called once for every non-NULL output set by analyzer_main, whatever it returned */
+void analyzer_free(faaf_output output);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,58 @@
+use std::io::{Read, Write};
+
+// Runs one `.so` analyzer outside of the faaf process, so that faaf can enforce limits on it.
+// Usage: faaf-so-host <library>
+// The JSON argument is read from stdin and the analyzer output is written to stdout.
+// Usage: faaf-so-host --serve <library>
+// The library stays loaded and is called for every line of stdin, see faaf::gateway::dispatcher::so_host.
+// --legacy-abi calls a library without `analyzer_abi_version` with the version 0 ABI.
+fn main() {
+    let mut serve: bool = false;
+    let mut legacy_abi: bool = false;
+    let mut paths: Vec<std::path::PathBuf> = Vec::new();
+    for arg in std::env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--serve") => serve = true,
+            Some("--legacy-abi") => legacy_abi = true,
+            _ => paths.push(std::path::PathBuf::from(arg)),
+        }
+    }
+    let lib_path: std::path::PathBuf = match <[std::path::PathBuf; 1]>::try_from(paths) {
+        Ok([path]) => path,
+        Err(_) => {
+            eprintln!("usage: faaf-so-host [--serve] [--legacy-abi] <library>");
+            std::process::exit(2);
+        }
+    };
+
+    if serve {
+        if let Err(err) = faaf::gateway::dispatcher::so_host::serve(&lib_path, legacy_abi) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut input = String::new();
+    if let Err(err) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("failed to read the argument: {}", err);
+        std::process::exit(1);
+    }
+
+    match faaf::gateway::dispatcher::dispatcher::call_shared_object(&lib_path, &input, legacy_abi) {
+        Ok(output) => {
+            let mut stdout = std::io::stdout();
+            if stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush()).is_err() {
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            // the error message returned by the library
+            if let Some(message) = err.stderr() {
+                eprintln!("{}", message);
+            }
+            std::process::exit(1);
+        }
+    }
+}
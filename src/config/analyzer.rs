@@ -1,16 +1,73 @@
 use crate::config::arguments;
 use crate::config::conditions;
+use crate::config::filter;
 use serde::de::Deserializer;
 use serde::Deserialize;
 use crate::config::parser::{arguments_parser, conditions_parser};
 
+// extensions of the analyzers that always run in a child process
+const PROCESS_EXTENSIONS: &[&str] = &["sh", "exe"];
+
 #[derive(Debug)]
 pub struct Analyzer {
     pub name: String,
     pub extension: String,
+    // part of the result cache key. change it when the output of the analyzer changes.
+    pub version: Option<String>,
     pub arguments: Option<Vec<arguments::Argument>>,
     pub dependencies: Option<Vec<String>>,
-    pub conditions: Option<Vec<conditions::Condition>>,
+    pub conditions: Option<conditions::Expression>,
+    pub limits: Limits,
+    pub isolation: Isolation,
+    pub protocol: Protocol,
+    // extension = "exe": the program and its arguments, run instead of the file named after the analyzer
+    pub command: Option<Vec<String>>,
+    // extension = "exe": put in front of the file named after the analyzer, e.g. ["perl"]
+    pub interpreter: Vec<String>,
+    // extension = "so": a library without `analyzer_abi_version` is called with the deprecated version 0 ABI
+    pub legacy_abi: bool,
+    // files this analyzer runs on, on top of the filter of the whole config
+    pub filter: filter::FileFilter,
+    pub scope: Scope,
+}
+
+// how a child process analyzer gets its arguments. the output is always read from stdout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    // the JSON arguments are written to stdin
+    Stdin,
+    // the JSON arguments are the first command line argument, visible in `ps` and limited by ARG_MAX
+    Argv,
+}
+
+// What an analyzer runs on, in the order the scopes are run: an analyzer can only depend on analyzers of its
+// scope or of a scope before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    // once for the whole firmware, before the files
+    ImageBefore,
+    File,
+    // every directory, once every file is done
+    Directory,
+    // once for the whole firmware, once every directory is done
+    ImageAfter,
+}
+
+// limits of one analyzer run. timeout and max_memory can only be enforced on a child process.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    pub timeout: Option<std::time::Duration>,
+    pub max_memory: Option<u64>,
+    pub max_output_bytes: Option<u64>,
+}
+
+// where py and so analyzers run. sh analyzers always run in a child process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Isolation {
+    InProcess,
+    Process,
+    // so analyzers only: a faaf-so-host process kept running from one file to the next
+    Host,
 }
 
 impl<'de> Deserialize<'de> for Analyzer {
@@ -22,9 +79,26 @@ impl<'de> Deserialize<'de> for Analyzer {
         struct InnerAnalyzer {
             name: String,
             extension: String,
+            version: Option<String>,
             arguments: Option<Vec<String>>,
             dependencies: Option<Vec<String>>,
             conditions: Option<String>,
+            // seconds
+            timeout: Option<f64>,
+            max_memory: Option<u64>,
+            max_output_bytes: Option<u64>,
+            isolation: Option<String>,
+            protocol: Option<String>,
+            command: Option<Vec<String>>,
+            interpreter: Option<Vec<String>>,
+            legacy_abi: Option<bool>,
+            include: Option<Vec<String>>,
+            exclude: Option<Vec<String>>,
+            max_file_size: Option<u64>,
+            file_types: Option<Vec<String>>,
+            scope: Option<String>,
+            // when an image analyzer runs
+            stage: Option<String>,
         }
 
         let inner: InnerAnalyzer = InnerAnalyzer::deserialize(deserializer)?;
@@ -48,31 +122,147 @@ impl<'de> Deserialize<'de> for Analyzer {
             None
         };
 
-        let conditions: Option<Vec<conditions::Condition>> = if let Some(condition_statement) = inner.conditions {
-            let mut parsed_conditions = Vec::new();
-            for cond_str in condition_statement.lines() {
-                match conditions_parser::parse_condition(&cond_str) {
-                    Ok(cond) => parsed_conditions.push(cond),
-                    Err(err) => {
-                        return Err(serde::de::Error::custom(format!(
-                            "Failed to parse conditions: {}, error: {:?}",
-                            cond_str, err
-                        )))
-                    }
+        // the statement is parsed as a whole, so one expression can span several lines
+        let conditions: Option<conditions::Expression> = match inner.conditions {
+            Some(condition_statement) => match conditions_parser::parse_condition(&condition_statement) {
+                Ok(expression) => Some(expression),
+                Err(err) => {
+                    return Err(serde::de::Error::custom(format!(
+                        "Failed to parse conditions: {}, error: {:?}",
+                        condition_statement, err
+                    )))
                 }
+            },
+            None => None,
+        };
+
+        let timeout: Option<std::time::Duration> = match inner.timeout {
+            Some(seconds) => Some(std::time::Duration::try_from_secs_f64(seconds).map_err(|err| serde::de::Error::custom(format!(
+                "Invalid timeout: {}, error: {}",
+                seconds, err
+            )))?),
+            None => None,
+        };
+        let limits: Limits = Limits {
+            timeout,
+            max_memory: inner.max_memory,
+            max_output_bytes: inner.max_output_bytes,
+        };
+
+        let isolation: Isolation = match inner.isolation.as_deref() {
+            None | Some("thread") => Isolation::InProcess,
+            Some("process") => Isolation::Process,
+            Some("host") => Isolation::Host,
+            Some(other) => {
+                return Err(serde::de::Error::custom(format!(
+                    "Invalid isolation: {}, expected `thread`, `process` or `host`",
+                    other
+                )))
             }
-            Some(parsed_conditions)
+        };
+        if isolation == Isolation::Host && inner.extension != "so" {
+            return Err(serde::de::Error::custom(format!(
+                "analyzer `{}`: isolation = \"host\" requires extension = \"so\"",
+                inner.name
+            )));
         }
-        else {
-            None
+        let process: bool = PROCESS_EXTENSIONS.contains(&inner.extension.as_str());
+        if isolation == Isolation::InProcess && !process && (limits.timeout.is_some() || limits.max_memory.is_some()) {
+            return Err(serde::de::Error::custom(format!(
+                "analyzer `{}`: timeout and max_memory require isolation = \"process\"",
+                inner.name
+            )));
+        }
+
+        let protocol: Protocol = match inner.protocol.as_deref() {
+            None | Some("stdin") => Protocol::Stdin,
+            Some("argv") => Protocol::Argv,
+            Some(other) => {
+                return Err(serde::de::Error::custom(format!(
+                    "Invalid protocol: {}, expected `stdin` or `argv`",
+                    other
+                )))
+            }
+        };
+        if inner.protocol.is_some() && !process {
+            return Err(serde::de::Error::custom(format!(
+                "analyzer `{}`: protocol only applies to extension = \"sh\" or \"exe\"",
+                inner.name
+            )));
+        }
+
+        match (&inner.command, &inner.interpreter) {
+            (Some(_), Some(_)) => {
+                return Err(serde::de::Error::custom(format!(
+                    "analyzer `{}`: command and interpreter can not be used together",
+                    inner.name
+                )))
+            }
+            (Some(command), None) if command.is_empty() => {
+                return Err(serde::de::Error::custom(format!("analyzer `{}`: command is empty", inner.name)))
+            }
+            (Some(_), None) | (None, Some(_)) if inner.extension != "exe" => {
+                return Err(serde::de::Error::custom(format!(
+                    "analyzer `{}`: command and interpreter require extension = \"exe\"",
+                    inner.name
+                )))
+            }
+            _ => {}
+        }
+        if inner.legacy_abi.is_some() && inner.extension != "so" {
+            return Err(serde::de::Error::custom(format!(
+                "analyzer `{}`: legacy_abi requires extension = \"so\"",
+                inner.name
+            )));
+        }
+
+        let filter: filter::FileFilter = filter::FileFilter::new(
+            &inner.include.unwrap_or_default(),
+            &inner.exclude.unwrap_or_default(),
+            inner.max_file_size,
+            inner.file_types,
+        ).map_err(|err| serde::de::Error::custom(format!("analyzer `{}`: {}", inner.name, err)))?;
+
+        let scope: Scope = match (inner.scope.as_deref(), inner.stage.as_deref()) {
+            (None | Some("file"), None) => Scope::File,
+            (Some("directory"), None) => Scope::Directory,
+            (Some("image"), Some("before")) => Scope::ImageBefore,
+            (Some("image"), None | Some("after")) => Scope::ImageAfter,
+            (Some("image"), Some(other)) => {
+                return Err(serde::de::Error::custom(format!(
+                    "Invalid stage: {}, expected `before` or `after`",
+                    other
+                )))
+            }
+            (None | Some("file") | Some("directory"), Some(_)) => {
+                return Err(serde::de::Error::custom(format!(
+                    "analyzer `{}`: stage requires scope = \"image\"",
+                    inner.name
+                )))
+            }
+            (Some(other), _) => {
+                return Err(serde::de::Error::custom(format!(
+                    "Invalid scope: {}, expected `file`, `directory` or `image`",
+                    other
+                )))
+            }
         };
 
         Ok(Analyzer {
             name: inner.name,
             extension: inner.extension,
+            version: inner.version,
             arguments: arguments,
             dependencies: inner.dependencies,
             conditions: conditions,
+            limits,
+            isolation,
+            protocol,
+            command: inner.command,
+            interpreter: inner.interpreter.unwrap_or_default(),
+            legacy_abi: inner.legacy_abi.unwrap_or(false),
+            filter,
+            scope,
         })
     }
 }
@@ -3,5 +3,5 @@ use crate::config::parser::parser_type;
 
 #[derive(Debug, PartialEq)]
 pub struct Argument {
-    pub value: parser_type::Access,
+    pub value: parser_type::Value,
 }
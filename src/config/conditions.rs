@@ -2,12 +2,23 @@ extern crate nom;
 use serde_json;
 use crate::config::parser::parser_type;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Condition {
     pub left: parser_type::Value,
     pub op: parser_type::Operator,
     pub right: parser_type::Value,
-    pub chain: Option<parser_type::Chain>,
+    // compiled when the config is loaded if `op` takes a pattern and `right` is a string literal
+    pub pattern: Option<parser_type::Pattern>,
+}
+
+// the whole `conditions` statement of an analyzer.
+// `not` binds tighter than `and`, which binds tighter than `or`.
+#[derive(Debug, PartialEq)]
+pub enum Expression {
+    Compare(Condition),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
 }
 
 impl From<&parser_type::LiteralValue> for serde_json::Value {
@@ -18,6 +29,7 @@ impl From<&parser_type::LiteralValue> for serde_json::Value {
             parser_type::LiteralValue::String(s) => serde_json::Value::String(s.clone()),
             parser_type::LiteralValue::Bool(b) => serde_json::Value::Bool(*b),
             parser_type::LiteralValue::Null => serde_json::Value::Null,
+            parser_type::LiteralValue::Array(items) => serde_json::Value::Array(items.iter().map(|item| item.into()).collect()),
         }
     }
 }
@@ -30,6 +42,7 @@ impl Into<serde_json::Value> for parser_type::LiteralValue {
             parser_type::LiteralValue::String(s) => serde_json::Value::String(s),
             parser_type::LiteralValue::Bool(b) => serde_json::Value::Bool(b),
             parser_type::LiteralValue::Null => serde_json::Value::Null,
+            parser_type::LiteralValue::Array(items) => serde_json::Value::Array(items.into_iter().map(|item| item.into()).collect()),
         }
     }
 }
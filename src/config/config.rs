@@ -1,5 +1,9 @@
 use serde::Deserialize;
 use crate::config::analyzer;
+use crate::config::dependency::DependencyGraph;
+use crate::config::filter::FileFilter;
+use crate::extract::extractor::ExternalExtractor;
+use crate::config::identifier;
 use crate::config::error::Error;
 use toml;
 
@@ -7,13 +11,39 @@ use toml;
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub analyzer: Vec<analyzer::Analyzer>,
+    // include, exclude, max_file_size and file_types at the top of the file
+    #[serde(flatten)]
+    pub filter: FileFilter,
+    // [[extractor]] sections: programs unpacking firmware images, tried before the built-in extractors
+    #[serde(default)]
+    pub extractor: Vec<ExternalExtractor>,
+    #[serde(skip)]
+    pub graph: DependencyGraph,
 }
 
 
 impl Config {
     pub fn load(config_file: &std::path::Path) -> Result<Self, Error>{
         let data: String = std::fs::read_to_string(config_file)?;
-        let config: Config = toml::from_str(&data)?;
+        let mut config: Config = toml::from_str(&data)?;
+        config.check_names()?;
+        config.graph = DependencyGraph::build(&config.analyzer)?;
         Ok(config)
     }
+
+    // analyzer names end up in sql and in accesses, see config::identifier
+    fn check_names(&self) -> Result<(), Error> {
+        for analyzer in &self.analyzer {
+            identifier::check_analyzer_name(&analyzer.name)
+                .map_err(|reason| Error::InvalidAnalyzerName(analyzer.name.clone(), reason))?;
+        }
+        for analyzer in &self.analyzer {
+            for name in identifier::accessed_analyzers(analyzer) {
+                if !self.analyzer.iter().any(|other| other.name == name) {
+                    return Err(Error::UnknownAnalyzer(analyzer.name.clone(), name.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
 }
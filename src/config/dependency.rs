@@ -0,0 +1,363 @@
+use crate::config::analyzer::{Analyzer, Scope};
+use crate::config::error::Error;
+use crate::config::identifier;
+
+
+// Dependency graph between analyzers, indexed by the position of the analyzer in `Config::analyzer`.
+// Built once when the config is loaded; unknown dependency names and cycles are rejected there.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    // dependencies[i] = analyzers that analyzer i depends on
+    dependencies: Vec<Vec<usize>>,
+    // analyzer indices in an order where every analyzer comes after its dependencies
+    order: Vec<usize>,
+    // the pass over the files an analyzer of the file scope runs in, see `passes`
+    passes: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+impl DependencyGraph {
+    pub fn build(analyzers: &[Analyzer]) -> Result<Self, Error> {
+        let mut index_of: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+        for (index, analyzer) in analyzers.iter().enumerate() {
+            if index_of.insert(analyzer.name.as_str(), index).is_some() {
+                return Err(Error::DuplicateAnalyzerName(analyzer.name.clone()));
+            }
+        }
+
+        let mut dependencies: Vec<Vec<usize>> = Vec::with_capacity(analyzers.len());
+        for analyzer in analyzers {
+            let mut deps: Vec<usize> = Vec::new();
+            for dep_name in analyzer.dependencies.iter().flatten() {
+                match index_of.get(dep_name.as_str()) {
+                    Some(dep) => {
+                        // the results of a later scope do not exist yet when the analyzer runs
+                        if analyzers[*dep].scope > analyzer.scope {
+                            return Err(Error::DependencyOnLaterScope(analyzer.name.clone(), dep_name.clone()));
+                        }
+                        if !deps.contains(dep) {
+                            deps.push(*dep);
+                        }
+                    }
+                    None => return Err(Error::UnknownDependency(analyzer.name.clone(), dep_name.clone())),
+                }
+            }
+            dependencies.push(deps);
+        }
+
+        // depth first search in config order, so that independent analyzers keep the order of the config file.
+        let mut visit: Vec<Visit> = vec![Visit::Unvisited; analyzers.len()];
+        let mut order: Vec<usize> = Vec::with_capacity(analyzers.len());
+        let mut stack: Vec<usize> = Vec::new();
+        for index in 0..analyzers.len() {
+            visit_node(index, &dependencies, &mut visit, &mut stack, &mut order)
+                .map_err(|cycle| Error::DependencyCycle(cycle.iter().map(|i| analyzers[*i].name.clone()).collect()))?;
+        }
+
+        let passes: Vec<usize> = passes(analyzers, &index_of, &dependencies, &order);
+        Ok(DependencyGraph { dependencies, order, passes })
+    }
+
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    pub fn dependencies(&self, index: usize) -> &[usize] {
+        &self.dependencies[index]
+    }
+
+    pub fn pass(&self, index: usize) -> usize {
+        self.passes[index]
+    }
+
+    pub fn last_pass(&self) -> usize {
+        self.passes.iter().copied().max().unwrap_or(0)
+    }
+
+    // marks the given analyzers and every analyzer depending on them, directly or not
+    pub fn with_dependents(&self, roots: &[usize]) -> Vec<bool> {
+        let mut marked: Vec<bool> = vec![false; self.dependencies.len()];
+        for root in roots {
+            marked[*root] = true;
+        }
+        // dependencies come first in `order`, so one pass is enough
+        for &index in &self.order {
+            if self.dependencies[index].iter().any(|dep| marked[*dep]) {
+                marked[index] = true;
+            }
+        }
+        marked
+    }
+}
+
+// A file analyzer reading other files (path[...], pathlist) runs once every file has been through the pass before
+// its own, so that the results it reads exist whatever the order of the files: one pass after the analyzers it
+// reads of other files, and not before its dependencies. Analyzers reading each other of other files, or
+// themselves, can not be ordered: their pass stops growing at the number of analyzers.
+fn passes(analyzers: &[Analyzer], index_of: &std::collections::HashMap<&str, usize>, dependencies: &[Vec<usize>], order: &[usize]) -> Vec<usize> {
+    let mut passes: Vec<usize> = vec![0; analyzers.len()];
+    let reads: Vec<Option<Vec<usize>>> = analyzers.iter().enumerate().map(|(index, analyzer)| {
+        let names: Vec<&str> = identifier::other_file_accesses(analyzer)?;
+        Some(names.iter().filter_map(|name| index_of.get(name).copied()).filter(|read| *read != index && analyzers[*read].scope == Scope::File).collect())
+    }).collect();
+    for _ in 0..analyzers.len() {
+        let mut changed: bool = false;
+        for &index in order {
+            if analyzers[index].scope != Scope::File {
+                continue;
+            }
+            let mut pass: usize = dependencies[index].iter().map(|dep| passes[*dep]).max().unwrap_or(0);
+            if let Some(read) = &reads[index] {
+                pass = pass.max(1 + read.iter().map(|read| passes[*read]).max().unwrap_or(0));
+            }
+            let pass: usize = pass.min(analyzers.len());
+            if pass != passes[index] {
+                passes[index] = pass;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    passes
+}
+
+// on a cycle, returns the analyzer indices forming it, with the first analyzer repeated at the end.
+fn visit_node(
+    index: usize,
+    dependencies: &[Vec<usize>],
+    visit: &mut [Visit],
+    stack: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), Vec<usize>> {
+    match visit[index] {
+        Visit::Done => return Ok(()),
+        Visit::InProgress => {
+            let start: usize = stack.iter().position(|i| *i == index).unwrap_or(0);
+            let mut cycle: Vec<usize> = stack[start..].to_vec();
+            cycle.push(index);
+            return Err(cycle);
+        }
+        Visit::Unvisited => {}
+    }
+
+    visit[index] = Visit::InProgress;
+    stack.push(index);
+    for dep in &dependencies[index] {
+        visit_node(*dep, dependencies, visit, stack, order)?;
+    }
+    stack.pop();
+    visit[index] = Visit::Done;
+    order.push(index);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::Config;
+
+    fn analyzers(toml_str: &str) -> Vec<Analyzer> {
+        let config: Config = toml::from_str(toml_str).unwrap();
+        config.analyzer
+    }
+
+    #[test]
+    fn test_order_puts_dependencies_first() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "ghidra"
+            extension = "sh"
+            dependencies = ["basic_info", "ldd"]
+
+            [[analyzer]]
+            name = "ldd"
+            extension = "sh"
+            dependencies = ["basic_info"]
+
+            [[analyzer]]
+            name = "basic_info"
+            extension = "py"
+        "#);
+        let graph = DependencyGraph::build(&list).unwrap();
+        assert_eq!(graph.order(), &[2, 1, 0]);
+        assert_eq!(graph.dependencies(0), &[2, 1]);
+    }
+
+    #[test]
+    fn test_order_keeps_config_order_without_dependencies() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "a"
+            extension = "py"
+
+            [[analyzer]]
+            name = "b"
+            extension = "py"
+        "#);
+        let graph = DependencyGraph::build(&list).unwrap();
+        assert_eq!(graph.order(), &[0, 1]);
+    }
+
+    #[test]
+    fn test_with_dependents() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "basic_info"
+            extension = "py"
+
+            [[analyzer]]
+            name = "ldd"
+            extension = "sh"
+            dependencies = ["basic_info"]
+
+            [[analyzer]]
+            name = "ghidra"
+            extension = "sh"
+            dependencies = ["ldd"]
+
+            [[analyzer]]
+            name = "strings"
+            extension = "sh"
+        "#);
+        let graph = DependencyGraph::build(&list).unwrap();
+        assert_eq!(graph.with_dependents(&[1]), vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn test_passes() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "linked"
+            extension = "sh"
+            arguments = ["path[ldd.libs[0]].checksec.relro"]
+
+            [[analyzer]]
+            name = "ldd"
+            extension = "sh"
+
+            [[analyzer]]
+            name = "checksec"
+            extension = "sh"
+
+            [[analyzer]]
+            name = "report"
+            extension = "sh"
+            dependencies = ["linked"]
+
+            [[analyzer]]
+            name = "summary"
+            extension = "sh"
+            arguments = ["path[0].linked", "pathlist"]
+
+            [[analyzer]]
+            name = "recursive"
+            extension = "sh"
+            arguments = ["path[0].recursive"]
+        "#);
+        let graph = DependencyGraph::build(&list).unwrap();
+        assert_eq!((0..list.len()).map(|index| graph.pass(index)).collect::<Vec<usize>>(), vec![1, 0, 0, 1, 2, 1]);
+        assert_eq!(graph.last_pass(), 2);
+    }
+
+    #[test]
+    fn test_unknown_dependency() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "ldd"
+            extension = "sh"
+            dependencies = ["basic_info"]
+        "#);
+        match DependencyGraph::build(&list) {
+            Err(Error::UnknownDependency(analyzer, dep)) => {
+                assert_eq!(analyzer, "ldd");
+                assert_eq!(dep, "basic_info");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cycle_is_named() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "a"
+            extension = "py"
+            dependencies = ["b"]
+
+            [[analyzer]]
+            name = "b"
+            extension = "py"
+            dependencies = ["c"]
+
+            [[analyzer]]
+            name = "c"
+            extension = "py"
+            dependencies = ["a"]
+        "#);
+        match DependencyGraph::build(&list) {
+            Err(err @ Error::DependencyCycle(_)) => {
+                assert_eq!(err.to_string(), "dependency cycle: a -> b -> c -> a");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dependency_on_later_scope() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "os"
+            extension = "sh"
+            scope = "image"
+            stage = "before"
+
+            [[analyzer]]
+            name = "elf"
+            extension = "sh"
+            dependencies = ["os"]
+
+            [[analyzer]]
+            name = "summary"
+            extension = "sh"
+            scope = "image"
+            dependencies = ["elf", "os"]
+        "#);
+        assert!(DependencyGraph::build(&list).is_ok());
+
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "elf"
+            extension = "sh"
+            dependencies = ["libraries"]
+
+            [[analyzer]]
+            name = "libraries"
+            extension = "sh"
+            scope = "directory"
+        "#);
+        assert!(matches!(DependencyGraph::build(&list), Err(Error::DependencyOnLaterScope(_, _))));
+    }
+
+    #[test]
+    fn test_duplicate_name() {
+        let list = analyzers(r#"
+            [[analyzer]]
+            name = "a"
+            extension = "py"
+
+            [[analyzer]]
+            name = "a"
+            extension = "sh"
+        "#);
+        assert!(matches!(DependencyGraph::build(&list), Err(Error::DuplicateAnalyzerName(_))));
+    }
+}
@@ -9,6 +9,12 @@ pub enum Error {
     ParseTomlError(toml::de::Error),
     NoAccessSatement(),
     DatabaseError(database::error::Error),
+    DuplicateAnalyzerName(String),
+    UnknownDependency(String, String),
+    DependencyCycle(Vec<String>),
+    InvalidAnalyzerName(String, String),
+    UnknownAnalyzer(String, String),
+    DependencyOnLaterScope(String, String),
 }
 
 impl fmt::Display for Error {
@@ -18,6 +24,12 @@ impl fmt::Display for Error {
             Error::ParseTomlError(err) => write!(f, "Parse Toml File Error: {}", err),
             Error::NoAccessSatement() => write!(f, "there is access struct and is not access statement"),
             Error::DatabaseError(err) => write!(f, "database error: {}", err),
+            Error::DuplicateAnalyzerName(name) => write!(f, "analyzer name: `{}` is defined more than once", name),
+            Error::UnknownDependency(analyzer, dep) => write!(f, "analyzer `{}` depends on unknown analyzer `{}`", analyzer, dep),
+            Error::DependencyCycle(cycle) => write!(f, "dependency cycle: {}", cycle.join(" -> ")),
+            Error::InvalidAnalyzerName(name, reason) => write!(f, "analyzer name: `{}` {}", name, reason),
+            Error::UnknownAnalyzer(analyzer, name) => write!(f, "analyzer `{}` reads unknown analyzer `{}`", analyzer, name),
+            Error::DependencyOnLaterScope(analyzer, dep) => write!(f, "analyzer `{}` depends on `{}`, whose scope runs after it", analyzer, dep),
         }
     }
 }
@@ -29,6 +41,12 @@ impl std::error::Error for Error {
             Error::ParseTomlError(err) => Some(err),
             Error::NoAccessSatement() => None,
             Error::DatabaseError(err) => Some(err),
+            Error::DuplicateAnalyzerName(_) => None,
+            Error::UnknownDependency(_, _) => None,
+            Error::DependencyCycle(_) => None,
+            Error::InvalidAnalyzerName(_, _) => None,
+            Error::UnknownAnalyzer(_, _) => None,
+            Error::DependencyOnLaterScope(_, _) => None,
         }
     }
 }
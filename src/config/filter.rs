@@ -0,0 +1,145 @@
+use serde::de::Deserializer;
+use serde::Deserialize;
+use crate::database::database::{FileMetadata, FILE_TYPES};
+
+
+// Which files are analyzed, from the `include`, `exclude`, `max_file_size` and `file_types` keys.
+// At the top of the config file it applies to every analyzer, in `[[analyzer]]` to that analyzer only.
+// It is checked before the conditions, without touching the database.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    pub include: Vec<glob::Pattern>,
+    pub exclude: Vec<glob::Pattern>,
+    // bytes
+    pub max_file_size: Option<u64>,
+    // values of the file_type column
+    pub file_types: Option<Vec<String>>,
+}
+
+// `*` does not match `/`, `**` matches any number of directories
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+impl FileFilter {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        max_file_size: Option<u64>,
+        file_types: Option<Vec<String>>,
+    ) -> Result<Self, String>
+    {
+        if let Some(file_type) = file_types.iter().flatten().find(|file_type| !FILE_TYPES.contains(&file_type.as_str())) {
+            return Err(format!("Invalid file type: {}, expected one of {}", file_type, FILE_TYPES.join(", ")));
+        }
+        Ok(FileFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+            max_file_size,
+            file_types,
+        })
+    }
+
+    // `relative_path` is the path recorded in the result table
+    pub fn matches(&self, relative_path: &std::path::Path, metadata: &FileMetadata) -> bool {
+        if !self.include.is_empty() && !self.include.iter().any(|pattern| matches_path(pattern, relative_path)) {
+            return false;
+        }
+        if self.exclude.iter().any(|pattern| matches_path(pattern, relative_path)) {
+            return false;
+        }
+        // the size of a directory or a symlink is not the size of any content, so only regular files are checked
+        if let (Some(max_file_size), Some(size), Some("regular")) = (self.max_file_size, metadata.size, metadata.file_type.as_deref()) {
+            if u64::try_from(size).map_or(true, |size| size > max_file_size) {
+                return false;
+            }
+        }
+        if let Some(file_types) = &self.file_types {
+            match &metadata.file_type {
+                Some(file_type) if file_types.contains(file_type) => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<glob::Pattern>, String> {
+    patterns.iter()
+        .map(|pattern| glob::Pattern::new(pattern).map_err(|err| format!("Invalid glob: {}, error: {}", pattern, err)))
+        .collect()
+}
+
+// A pattern without `/` matches the name of the file (`*.jpg`), any other one the whole relative path (`usr/lib/*.so`).
+// A file also matches when one of its directories matches, so `proc` or `dev/**` cover everything below them.
+fn matches_path(pattern: &glob::Pattern, relative_path: &std::path::Path) -> bool {
+    let by_name: bool = !pattern.as_str().contains('/');
+    relative_path.ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .any(|path| {
+            if by_name {
+                path.file_name().is_some_and(|name| pattern.matches_with(&name.to_string_lossy(), MATCH_OPTIONS))
+            } else {
+                pattern.matches_path_with(path, MATCH_OPTIONS)
+            }
+        })
+}
+
+impl<'de> Deserialize<'de> for FileFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct InnerFileFilter {
+            include: Option<Vec<String>>,
+            exclude: Option<Vec<String>>,
+            max_file_size: Option<u64>,
+            file_types: Option<Vec<String>>,
+        }
+
+        let inner: InnerFileFilter = InnerFileFilter::deserialize(deserializer)?;
+        FileFilter::new(
+            &inner.include.unwrap_or_default(),
+            &inner.exclude.unwrap_or_default(),
+            inner.max_file_size,
+            inner.file_types,
+        ).map_err(serde::de::Error::custom)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_matches() {
+        let filter: FileFilter = FileFilter::new(
+            &["usr/**".to_string(), "bin".to_string()],
+            &["*.jpg".to_string(), "usr/share/doc".to_string()],
+            Some(1024),
+            Some(vec!["regular".to_string()]),
+        ).unwrap();
+        let regular: FileMetadata = FileMetadata { size: Some(10), file_type: Some("regular".to_string()), ..FileMetadata::default() };
+
+        assert!(filter.matches(Path::new("bin/busybox"), &regular));
+        assert!(filter.matches(Path::new("usr/lib/libc.so"), &regular));
+        assert!(!filter.matches(Path::new("sbin/init"), &regular));
+        assert!(!filter.matches(Path::new("usr/share/logo.jpg"), &regular));
+        assert!(!filter.matches(Path::new("usr/share/doc/README"), &regular));
+        assert!(!filter.matches(Path::new("usr/lib/big.so"), &FileMetadata { size: Some(4096), ..regular.clone() }));
+        assert!(!filter.matches(Path::new("usr/lib"), &FileMetadata { file_type: Some("directory".to_string()), ..regular.clone() }));
+
+        let size_only: FileFilter = FileFilter::new(&[], &[], Some(1024), None).unwrap();
+        assert!(size_only.matches(Path::new("usr/lib"), &FileMetadata { size: Some(4096), file_type: Some("directory".to_string()), ..regular.clone() }));
+        assert!(!size_only.matches(Path::new("usr/lib/big.so"), &FileMetadata { size: Some(4096), ..regular.clone() }));
+
+        assert!(FileFilter::default().matches(Path::new("dev/null"), &FileMetadata::default()));
+        assert!(FileFilter::new(&[], &["[".to_string()], None, None).is_err());
+        assert!(FileFilter::new(&[], &[], None, Some(vec!["file".to_string()])).is_err());
+    }
+}
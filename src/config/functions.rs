@@ -0,0 +1,194 @@
+use serde_json;
+
+
+// Built-in functions of the condition and argument expressions, e.g. `len(ldd.libs) > 0`.
+// Calls are checked against this registry when the config is loaded (name, number of arguments and,
+// as far as they are known, argument types), and evaluated by the database statements.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Any,
+    String,
+    Number,
+    Bool,
+    // string, array or object
+    Sized,
+}
+
+impl Type {
+    pub fn accepts(&self, value: &serde_json::Value) -> bool {
+        matches!(
+            (self, value),
+            (Type::Any, _)
+                | (Type::String, serde_json::Value::String(_))
+                | (Type::Number, serde_json::Value::Number(_))
+                | (Type::Bool, serde_json::Value::Bool(_))
+                | (Type::Sized, serde_json::Value::String(_) | serde_json::Value::Array(_) | serde_json::Value::Object(_))
+        )
+    }
+
+    // whether a value of type `other` can be passed where `self` is expected
+    pub fn accepts_type(&self, other: Type) -> bool {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Sized, Type::String) => true,
+            (expected, actual) => *expected == actual,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::String => write!(f, "string"),
+            Type::Number => write!(f, "number"),
+            Type::Bool => write!(f, "bool"),
+            Type::Sized => write!(f, "string, array or object"),
+        }
+    }
+}
+
+pub struct Function {
+    pub name: &'static str,
+    pub params: &'static [Type],
+    pub returns: Type,
+    // false: a null argument (a missing value) makes the result null without calling `eval`
+    pub accepts_null: bool,
+    eval: fn(&[serde_json::Value]) -> Result<serde_json::Value, String>,
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.name, self.params.len())
+    }
+}
+
+impl Function {
+    pub fn call(&self, args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+        if args.len() != self.params.len() {
+            return Err(format!("{}() takes {} argument(s), {} given", self.name, self.params.len(), args.len()));
+        }
+        if !self.accepts_null && args.iter().any(|arg| arg.is_null()) {
+            return Ok(serde_json::Value::Null);
+        }
+        for (index, (param, arg)) in self.params.iter().zip(args).enumerate() {
+            if !param.accepts(arg) {
+                return Err(format!("{}(): argument {} must be {}, got {}", self.name, index + 1, param, arg));
+            }
+        }
+        (self.eval)(args)
+    }
+}
+
+pub static FUNCTIONS: &[Function] = &[
+    Function { name: "len", params: &[Type::Sized], returns: Type::Number, accepts_null: false, eval: len },
+    Function { name: "lower", params: &[Type::String], returns: Type::String, accepts_null: false, eval: lower },
+    Function { name: "upper", params: &[Type::String], returns: Type::String, accepts_null: false, eval: upper },
+    Function { name: "startswith", params: &[Type::String, Type::String], returns: Type::Bool, accepts_null: false, eval: startswith },
+    Function { name: "endswith", params: &[Type::String, Type::String], returns: Type::Bool, accepts_null: false, eval: endswith },
+    Function { name: "exists", params: &[Type::Any], returns: Type::Bool, accepts_null: true, eval: exists },
+    Function { name: "basename", params: &[Type::String], returns: Type::String, accepts_null: false, eval: basename },
+    Function { name: "extension", params: &[Type::String], returns: Type::String, accepts_null: false, eval: extension },
+    Function { name: "int", params: &[Type::Any], returns: Type::Number, accepts_null: false, eval: int },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|function| function.name == name)
+}
+
+fn str_arg(args: &[serde_json::Value], index: usize) -> &str {
+    // the type was checked by `Function::call`
+    args[index].as_str().unwrap_or_default()
+}
+
+fn len(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    let len: usize = match &args[0] {
+        serde_json::Value::String(s) => s.chars().count(),
+        serde_json::Value::Array(array) => array.len(),
+        serde_json::Value::Object(obj) => obj.len(),
+        _ => 0,
+    };
+    Ok(serde_json::json!(len))
+}
+
+fn lower(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(str_arg(args, 0).to_lowercase()))
+}
+
+fn upper(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(str_arg(args, 0).to_uppercase()))
+}
+
+fn startswith(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(str_arg(args, 0).starts_with(str_arg(args, 1))))
+}
+
+fn endswith(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(str_arg(args, 0).ends_with(str_arg(args, 1))))
+}
+
+fn exists(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(!args[0].is_null()))
+}
+
+fn basename(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    let path: &std::path::Path = std::path::Path::new(str_arg(args, 0));
+    let name: String = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(serde_json::json!(name))
+}
+
+// without the dot. "" when there is none
+fn extension(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    let path: &std::path::Path = std::path::Path::new(str_arg(args, 0));
+    let extension: String = path.extension().map(|ext| ext.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(serde_json::json!(extension))
+}
+
+// floats are truncated. strings are parsed as decimal integers
+fn int(args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    match &args[0] {
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Ok(serde_json::json!(i)),
+            None => Ok(serde_json::json!(n.as_f64().unwrap_or_default().trunc() as i64)),
+        },
+        serde_json::Value::String(s) => s.trim().parse::<i64>()
+            .map(|i| serde_json::json!(i))
+            .map_err(|err| format!("int(): can not convert {:?}: {}", s, err)),
+        serde_json::Value::Bool(b) => Ok(serde_json::json!(*b as i64)),
+        other => Err(format!("int(): can not convert {}", other)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+        lookup(name).unwrap().call(args)
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(call("len", &[serde_json::json!(["a", "b"])]), Ok(serde_json::json!(2)));
+        assert_eq!(call("lower", &[serde_json::json!("Application/X-ELF")]), Ok(serde_json::json!("application/x-elf")));
+        assert_eq!(call("startswith", &[serde_json::json!("etc/passwd"), serde_json::json!("etc/")]), Ok(serde_json::json!(true)));
+        assert_eq!(call("basename", &[serde_json::json!("usr/lib/libc.so.6")]), Ok(serde_json::json!("libc.so.6")));
+        assert_eq!(call("extension", &[serde_json::json!("usr/lib/libc.so.6")]), Ok(serde_json::json!("6")));
+        assert_eq!(call("int", &[serde_json::json!(" 42 ")]), Ok(serde_json::json!(42)));
+        assert_eq!(call("int", &[serde_json::json!(3.9)]), Ok(serde_json::json!(3)));
+    }
+
+    #[test]
+    fn test_missing_values() {
+        assert_eq!(call("exists", &[serde_json::Value::Null]), Ok(serde_json::json!(false)));
+        assert_eq!(call("len", &[serde_json::Value::Null]), Ok(serde_json::Value::Null));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert!(call("lower", &[serde_json::json!(1)]).is_err());
+        assert!(call("len", &[serde_json::json!(1), serde_json::json!(2)]).is_err());
+    }
+}
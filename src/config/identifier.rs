@@ -0,0 +1,172 @@
+use crate::config::analyzer::Analyzer;
+use crate::config::conditions;
+use crate::config::functions;
+use crate::config::parser::parser_type;
+use crate::database::database;
+
+
+// An analyzer name is the name of its table and the base of accesses such as `ldd.libs`,
+// so it must be an identifier the access parser reads back, and must not shadow anything else.
+
+// names with a meaning in conditions and arguments
+const RESERVED_WORDS: &[&str] = &[
+    "path", "pathlist", "any", "all",
+    "and", "or", "not", "in", "glob", "iglob",
+    "true", "false", "null",
+];
+
+// tables of the result database
+const RESERVED_TABLES: &[&str] = &["result", "analyzer_error"];
+
+pub fn check_analyzer_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {},
+        Some(_) => return Err("must start with a letter".to_string()),
+        None => return Err("must not be empty".to_string()),
+    }
+    if !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err("may only contain letters, digits and `_`".to_string());
+    }
+
+    let lower: String = name.to_ascii_lowercase();
+    if RESERVED_WORDS.contains(&name) || database::file_column(name).is_some() || functions::lookup(name).is_some() {
+        return Err("is a reserved word".to_string());
+    }
+    // sqlite table names are case-insensitive
+    if RESERVED_TABLES.contains(&lower.as_str()) || lower.starts_with("sqlite_") {
+        return Err("is the name of a table of the database".to_string());
+    }
+    Ok(())
+}
+
+// what an analyzer reads in its arguments and conditions
+#[derive(Default)]
+struct Accesses<'a> {
+    analyzers: Vec<&'a str>,
+    // path[...] and pathlist read other files
+    other_files: bool,
+    // the analyzer names read through path[...]
+    other_file_analyzers: Vec<&'a str>,
+}
+
+// the analyzer names an analyzer reads in its arguments and conditions
+pub fn accessed_analyzers(analyzer: &Analyzer) -> Vec<&str> {
+    analyzer_accesses(analyzer).analyzers
+}
+
+// None if the analyzer only reads the file it runs on, otherwise the analyzer names it reads of other files
+pub fn other_file_accesses(analyzer: &Analyzer) -> Option<Vec<&str>> {
+    let accesses: Accesses = analyzer_accesses(analyzer);
+    Some(accesses.other_file_analyzers).filter(|_| accesses.other_files)
+}
+
+fn analyzer_accesses(analyzer: &Analyzer) -> Accesses<'_> {
+    let mut accesses: Accesses = Accesses::default();
+    for argument in analyzer.arguments.iter().flatten() {
+        value_accesses(&argument.value, &mut accesses);
+    }
+    if let Some(expression) = &analyzer.conditions {
+        expression_accesses(expression, &mut accesses);
+    }
+    accesses
+}
+
+fn expression_accesses<'a>(expression: &'a conditions::Expression, accesses: &mut Accesses<'a>) {
+    match expression {
+        conditions::Expression::Compare(condition) => condition_accesses(condition, accesses),
+        conditions::Expression::Not(expr) => expression_accesses(expr, accesses),
+        conditions::Expression::And(left, right) | conditions::Expression::Or(left, right) => {
+            expression_accesses(left, accesses);
+            expression_accesses(right, accesses);
+        }
+    }
+}
+
+fn condition_accesses<'a>(condition: &'a conditions::Condition, accesses: &mut Accesses<'a>) {
+    value_accesses(&condition.left, accesses);
+    value_accesses(&condition.right, accesses);
+}
+
+fn value_accesses<'a>(value: &'a parser_type::Value, accesses: &mut Accesses<'a>) {
+    match value {
+        parser_type::Value::Literal(_) => {},
+        parser_type::Value::Access(access) => access_accesses(access, accesses),
+        parser_type::Value::Call(call) => {
+            for arg in &call.args {
+                value_accesses(arg, accesses);
+            }
+        }
+        parser_type::Value::Binary(binary) => {
+            value_accesses(&binary.left, accesses);
+            value_accesses(&binary.right, accesses);
+        }
+        parser_type::Value::Predicate(predicate) => condition_accesses(&predicate.condition, accesses),
+    }
+}
+
+// path[...].analyzer_name reads analyzer_name of another file. `path` alone is the path of the file itself.
+fn access_accesses<'a>(access: &'a parser_type::Access, accesses: &mut Accesses<'a>) {
+    let path: &[parser_type::AccessPath] = access.path.as_deref().unwrap_or(&[]);
+    match access.base.as_str() {
+        "path" => {
+            if let [parser_type::AccessPath::Index(_), ..] = path {
+                accesses.other_files = true;
+            }
+            if let [parser_type::AccessPath::Index(_), parser_type::AccessPath::Key(name), ..] = path {
+                if database::file_column(name).is_none() {
+                    accesses.analyzers.push(name);
+                    accesses.other_file_analyzers.push(name);
+                }
+            }
+        }
+        "pathlist" => accesses.other_files = true,
+        name if database::file_column(name).is_some() => {},
+        name => accesses.analyzers.push(name),
+    }
+    for part in path {
+        if let parser_type::AccessPath::Index(parser_type::IndexValue::Access(index)) = part {
+            access_accesses(index, accesses);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_analyzer_name() {
+        assert!(check_analyzer_name("checksec_v2").is_ok());
+        assert!(check_analyzer_name("select").is_ok());
+        assert!(check_analyzer_name("").is_err());
+        assert!(check_analyzer_name("_private").is_err());
+        assert!(check_analyzer_name("ldd; DROP TABLE result").is_err());
+        assert!(check_analyzer_name("Result").is_err());
+        assert!(check_analyzer_name("sqlite_master").is_err());
+        assert!(check_analyzer_name("pathlist").is_err());
+        assert!(check_analyzer_name("len").is_err());
+        assert!(check_analyzer_name("mime").is_err());
+    }
+
+    #[test]
+    fn test_accessed_analyzers() {
+        let analyzer: Analyzer = toml::from_str(r#"
+name = "linked"
+extension = "sh"
+arguments = ["path[ldd.libs[index.first]].checksec.relro", "pathlist"]
+conditions = "any(elf.imports[*].name == \"system\") and len(path) > 0 and size > 0 and path[0].mode > 0"
+"#).unwrap();
+        assert_eq!(accessed_analyzers(&analyzer), vec!["checksec", "ldd", "index", "elf"]);
+        assert_eq!(other_file_accesses(&analyzer), Some(vec!["checksec"]));
+
+        let analyzer: Analyzer = toml::from_str(r#"
+name = "local"
+extension = "sh"
+arguments = ["path", "ldd.libs[0]"]
+conditions = "size > 0"
+"#).unwrap();
+        assert_eq!(other_file_accesses(&analyzer), None);
+    }
+}
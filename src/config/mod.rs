@@ -2,5 +2,9 @@ pub mod parser;
 pub mod config;
 pub mod arguments;
 pub mod conditions;
+pub mod functions;
 pub mod analyzer;
+pub mod dependency;
+pub mod identifier;
+pub mod filter;
 pub mod error;
\ No newline at end of file
@@ -34,7 +34,7 @@ pub fn parse_argument(input: base_parser::ParseInput) -> Result<arguments::Argum
     // [multispace] [value] [multispace]
     let (remaining, space) = base_parser::parse_whitespace(input)?;
     // [value] [multispace]
-    let (remaining, value) = base_parser::parse_access(remaining)?;
+    let (remaining, value) = base_parser::parse_value(remaining)?;
     if remaining.is_empty() && !value.is_none(){
         // 111 EOF
         return Ok(arguments::Argument {
@@ -1,4 +1,7 @@
-use super::parser_type::{AccessPath, IndexValue, Access, Value, LiteralValue, Chain, Operator, };
+use super::parser_type::{AccessPath, IndexValue, Access, Value, LiteralValue, Chain, Operator, Call, Binary, BinaryOperator, Predicate, Quantifier, };
+use super::conditions_parser;
+use crate::config::conditions;
+use crate::config::functions;
 extern crate nom;
 
 
@@ -15,7 +18,10 @@ pub enum ParseError<'a>{
     Str2Digits(std::num::ParseIntError),
     AccessPathDotError(ParseInput<'a>),
     InvalidDataInArray(ParseInput<'a>),
-    UnmatchedClosingBracket(ParseInput<'a>)
+    UnmatchedClosingBracket(ParseInput<'a>),
+    InvalidFunctionCall(ParseInput<'a>, String),
+    InvalidArithmetic(ParseInput<'a>, String),
+    InvalidPredicate(ParseInput<'a>, String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -31,7 +37,10 @@ impl<'a> std::fmt::Display for ParseError<'a> {
             ParseError::Str2Digits(err) => write!(f, "{:?}", err),
             ParseError::AccessPathDotError(err) => write!(f, "error occurred after parsing the `.`: {:?}", err),
             ParseError::InvalidDataInArray(err) => write!(f, "invalid data in array: {:?}", err),
-            ParseError::UnmatchedClosingBracket(err) => write!(f, "unmatched closing bracket {:?}", err)
+            ParseError::UnmatchedClosingBracket(err) => write!(f, "unmatched closing bracket {:?}", err),
+            ParseError::InvalidFunctionCall(input, err) => write!(f, "invalid function call {:?}: {}", input, err),
+            ParseError::InvalidArithmetic(input, err) => write!(f, "invalid arithmetic {:?}: {}", input, err),
+            ParseError::InvalidPredicate(input, err) => write!(f, "invalid predicate {:?}: {}", input, err),
         }
     }
 }
@@ -76,7 +85,7 @@ pub fn parse_literal_value(input: &str) -> Result<(ParseInput, ParseResult<Liter
         );
     }
 
-    let (input, output): (ParseInput, ParseResult<i32>) = parse_int(input)?;
+    let (input, output): (ParseInput, ParseResult<i64>) = parse_int(input)?;
     if output.is_some() {
         return Ok(
             ( 
@@ -116,11 +125,16 @@ pub fn parse_literal_value(input: &str) -> Result<(ParseInput, ParseResult<Liter
         );
     }
 
+    let (input, output): (ParseInput, ParseResult<LiteralValue>) = parse_array(input)?;
+    if output.is_some() {
+        return Ok((input, output));
+    }
+
     return Ok((input, None));
 
 }
 
-pub fn parse_digit(input: ParseInput) -> Result<(ParseInput, ParseResult<i32>), ParseError<'_>> {
+pub fn parse_digit(input: ParseInput<'_>) -> Result<(ParseInput<'_>, ParseResult<i64>), ParseError<'_>> {
     let start: ParseInput = input;
 
     let digits_result: nom::IResult<&str, &str, nom::error::Error<&str>> =
@@ -132,30 +146,41 @@ pub fn parse_digit(input: ParseInput) -> Result<(ParseInput, ParseResult<i32>),
     }
 
     let (input, output) = digits_result.unwrap();
-    let digit_int: i32 = output.parse().map_err(ParseError::Str2Digits)?;
+    let digit_int: i64 = output.parse().map_err(ParseError::Str2Digits)?;
 
     return Ok( (input, Some(digit_int) ));
 
 }
 
-pub fn parse_int(input: ParseInput) -> Result<(ParseInput, ParseResult<i32>), ParseError<'_> >{
+// decimal, or hexadecimal (0x1f), octal (0o4000) and binary (0b101) with an optional sign
+pub fn parse_int(input: ParseInput<'_>) -> Result<(ParseInput<'_>, ParseResult<i64>), ParseError<'_> >{
     let start: ParseInput = input;
 
     let (input, sign) = parse_sign(input)?;
 
-    let (input, digits) = parse_digit(input)?;
-    if digits.is_none() {
+    let (digits_start, radix): (ParseInput, u32) = match input.get(..2) {
+        Some("0x") | Some("0X") => (&input[2..], 16),
+        Some("0o") | Some("0O") => (&input[2..], 8),
+        Some("0b") | Some("0B") => (&input[2..], 2),
+        _ => (input, 10),
+    };
+    let digits_len: usize = digits_start.find(|c: char| !c.is_digit(radix)).unwrap_or(digits_start.len());
+    // `0x` without digits is the decimal 0 followed by `x`
+    let (digits_start, radix, digits_len): (ParseInput, u32, usize) = if digits_len == 0 && radix != 10 {
+        (input, 10, 1)
+    }
+    else {
+        (digits_start, radix, digits_len)
+    };
+    if digits_len == 0 {
         return Ok( (start, None) );
     }
 
-    let output: i32 = match sign {
-        Some('-') => -digits.unwrap(),
-        Some('+') => digits.unwrap(),
-        None => digits.unwrap(),
-        _ => panic!("Unexpected sign character"),
-    };
+    // the sign is parsed together with the digits, so that i64::MIN fits
+    let text: String = format!("{}{}", sign.map(String::from).unwrap_or_default(), &digits_start[..digits_len]);
+    let output: i64 = i64::from_str_radix(&text, radix).map_err(ParseError::Str2Digits)?;
 
-    return Ok( (input, Some( output ) ) );
+    Ok( (&digits_start[digits_len..], Some( output ) ) )
 
 }
 
@@ -199,6 +224,39 @@ pub fn parse_null(input: &str) -> Result<(ParseInput, ParseResult<LiteralValue>)
     return Ok( ( input, Some(LiteralValue::Null) ) );
 }
 
+// ["system", "popen"]. the elements are literals
+pub fn parse_array(input: &str) -> Result<(ParseInput<'_>, ParseResult<LiteralValue>), ParseError<'_> > {
+    let start: ParseInput = input;
+
+    let mut tail: ParseInput = match input.strip_prefix('[') {
+        Some(tail) => tail,
+        None => return Ok((start, None)),
+    };
+    let mut items: Vec<LiteralValue> = Vec::new();
+    let (next_tail, _) = parse_whitespace(tail)?;
+    if let Some(next_tail) = next_tail.strip_prefix(']') {
+        return Ok((next_tail, Some(LiteralValue::Array(items))));
+    }
+
+    loop {
+        let (next_tail, _) = parse_whitespace(tail)?;
+        let (next_tail, item) = parse_literal_value(next_tail)?;
+        match item {
+            Some(item) => items.push(item),
+            None => return Err(ParseError::InvalidDataInArray(next_tail)),
+        }
+        let (next_tail, _) = parse_whitespace(next_tail)?;
+        if let Some(next_tail) = next_tail.strip_prefix(',') {
+            tail = next_tail;
+            continue;
+        }
+        match next_tail.strip_prefix(']') {
+            Some(next_tail) => return Ok((next_tail, Some(LiteralValue::Array(items)))),
+            None => return Err(ParseError::InvalidDataInArray(next_tail)),
+        }
+    }
+}
+
 pub fn handle_fatal_parse_error<'a, T>(
     parse_result: &nom::IResult<&'a str, T, nom::error::Error<&'a str>>, add_info: &'a str) -> Result<bool, ParseError<'a>> {
     if let Err(err) = parse_result {
@@ -258,47 +316,34 @@ pub fn parse_float(input: ParseInput) -> Result<(ParseInput, ParseResult<f64>),
 
     let start: ParseInput = input;
 
-    let (input, integer_part) = parse_int(input)?;
-    if integer_part.is_none() {
+    let (input, _) = parse_sign(input)?;
+
+    let integer_result: nom::IResult<&str, &str, nom::error::Error<&str>> = nom::character::complete::digit1(input);
+    let err_handle: bool = handle_fatal_parse_error(&integer_result, "invalid float")?;
+    if !err_handle {
         return Ok((start, None));
     }
+    let (input, _) = integer_result.unwrap();
 
     let (input, dot) = parse_dot(input)?;
     if dot.is_none() {
         return Ok((start, None));
     }
 
-
-    let (input, fractional_part) = parse_digit(input)?;
-    if fractional_part.is_none() {
+    let fractional_result: nom::IResult<&str, &str, nom::error::Error<&str>> = nom::character::complete::digit1(input);
+    let err_handle: bool = handle_fatal_parse_error(&fractional_result, "invalid float")?;
+    if !err_handle {
         return Ok((start, None));
     }
+    let (input, _) = fractional_result.unwrap();
 
-    let int_part: i32 = integer_part.unwrap();
-    let frac_part: i32 = fractional_part.unwrap();
-    let sign: f64 = int_part.signum() as f64;
-    let scale: usize = power_of_ten_based_on(frac_part);
-    let output:f64 = (int_part.abs() as f64 + frac_part as f64 / scale as f64) * sign;
-
-    return Ok((input, Some(output)));
-
-}
-
-fn power_of_ten_based_on(digits: i32) -> usize {
-
-    if digits == 0 {
-        return 1;
-    }
-
-    let mut pow: usize = 1;
-    let mut digits_abs = digits.abs();
-    while digits_abs > 0 {
-        pow *= 10;
-        digits_abs /= 10;
+    // [sign] digits . digits is always a valid f64
+    let text: &str = &start[..start.len() - input.len()];
+    match text.parse::<f64>() {
+        Ok(output) => Ok((input, Some(output))),
+        Err(_) => Ok((start, None)),
     }
 
-    return pow;
-
 }
 
 pub fn parse_operator(input: &str) -> Result<(ParseInput, ParseResult<Operator>), ParseError<'_> >  {
@@ -311,10 +356,18 @@ pub fn parse_operator(input: &str) -> Result<(ParseInput, ParseResult<Operator>)
     let parse_less_than: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("<")(i);
     let parse_greater_than: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag(">")(i);
     let parse_in_keyword: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("in")(i);
+    let parse_match: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("=~")(i);
+    let parse_not_match: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("!~")(i);
+    let parse_glob_keyword: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("glob")(i);
+    let parse_iglob_keyword: fn(ParseInput) -> nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = |i: ParseInput| nom::bytes::complete::tag("iglob")(i);
     
     let operator_result: nom::IResult<ParseInput, &str, nom::error::Error<ParseInput>> = nom::branch::alt((
         parse_equal,
         parse_not_equal,
+        parse_match,
+        parse_not_match,
+        parse_glob_keyword,
+        parse_iglob_keyword,
         parse_less_than_or_equal,
         parse_greater_than_or_equal,
         parse_less_than,
@@ -337,6 +390,10 @@ pub fn parse_operator(input: &str) -> Result<(ParseInput, ParseResult<Operator>)
         "<" => Operator::LessThan,
         ">" => Operator::GreaterThan,
         "in" => Operator::In,
+        "=~" => Operator::Match,
+        "!~" => Operator::NotMatch,
+        "glob" => Operator::Glob,
+        "iglob" => Operator::IGlob,
         _ => unreachable!(),
     };
 
@@ -402,9 +459,13 @@ fn parse_bracket(input: ParseInput) -> Result<(ParseInput, ParseResult<&str>), P
     let mut stack = Vec::new();
     let mut start_pos: usize = 0;
     let mut end_pos: usize = 0;
+    // brackets in a string index are not counted: path["a[1]"]
+    let mut in_string: bool = false;
 
     for (i, ch) in input.char_indices() {
         match ch {
+            '"' => in_string = !in_string,
+            _ if in_string => continue,
             '[' => {
                 if stack.is_empty() {
                     start_pos = i;
@@ -442,8 +503,12 @@ pub fn parse_access_index(input: &str) -> Result<(ParseInput, ParseResult<Access
     }
     let data_in_bracket = data_in_bracket_opt.unwrap();
 
-    //Access to an array must be done using a number (int) or a variable (access path).
-    let (input, output): (ParseInput, ParseResult<i32>) = parse_int(data_in_bracket)?;
+    //Access to an array must be done using a number (int), a string, `*` or a variable (access path).
+    if data_in_bracket.trim() == "*" {
+        return Ok((tail, Some(AccessPath::Index(IndexValue::Wildcard))));
+    }
+
+    let (_, output): (ParseInput, ParseResult<i64>) = parse_int(data_in_bracket)?;
     if output.is_some(){
         return Ok(
             ( 
@@ -453,6 +518,14 @@ pub fn parse_access_index(input: &str) -> Result<(ParseInput, ParseResult<Access
         );
     }
 
+    let (rest, output): (ParseInput, ParseResult<String>) = parse_string(data_in_bracket)?;
+    if let Some(key) = output {
+        if !rest.is_empty() {
+            return Err(ParseError::InvalidDataInArray(tail));
+        }
+        return Ok((tail, Some(AccessPath::Index(IndexValue::String(key)))));
+    }
+
     let (input, output): (ParseInput, ParseResult<Access>) = parse_access(data_in_bracket)?;
     if output.is_some(){
         return Ok(
@@ -538,8 +611,82 @@ pub fn parse_access(input: &str) -> Result<(ParseInput, ParseResult<Access>), Pa
 
 }
 
-pub fn parse_value(input: &str) -> Result<(ParseInput, ParseResult<Value>), ParseError<'_> > {
+// binary operators from the lowest to the highest precedence, as in python
+const BINARY_OPERATORS: &[&[(&str, BinaryOperator)]] = &[
+    &[("|", BinaryOperator::BitOr)],
+    &[("^", BinaryOperator::BitXor)],
+    &[("&", BinaryOperator::BitAnd)],
+    &[("<<", BinaryOperator::Shl), (">>", BinaryOperator::Shr)],
+    &[("+", BinaryOperator::Add), ("-", BinaryOperator::Sub)],
+    &[("*", BinaryOperator::Mul), ("/", BinaryOperator::Div), ("%", BinaryOperator::Rem)],
+];
+
+// value, or values joined by arithmetic and bitwise operators: basic_info.permissions & 0o4000
+// operations on literals only are computed here.
+pub fn parse_value(input: &str) -> Result<(ParseInput<'_>, ParseResult<Value>), ParseError<'_> > {
+    parse_binary(input, 0)
+}
+
+fn parse_binary(input: &str, level: usize) -> Result<(ParseInput<'_>, ParseResult<Value>), ParseError<'_> > {
+    if level == BINARY_OPERATORS.len() {
+        return parse_operand(input);
+    }
     let start: ParseInput = input;
+
+    let (mut tail, left) = parse_binary(input, level + 1)?;
+    let mut left: Value = match left {
+        Some(left) => left,
+        None => return Ok((start, None)),
+    };
+
+    loop {
+        let (after_space, _) = parse_whitespace(tail)?;
+        let found: Option<&(&str, BinaryOperator)> = BINARY_OPERATORS[level].iter().find(|(token, _)| after_space.starts_with(token));
+        let (token, op) = match found {
+            Some((token, op)) => (*token, *op),
+            // the whitespace before a comparison operator is left to the caller
+            None => return Ok((tail, Some(left))),
+        };
+        let (after_op, _) = parse_whitespace(&after_space[token.len()..])?;
+        let (next_tail, right) = parse_binary(after_op, level + 1)?;
+        let right: Value = match right {
+            Some(right) => right,
+            None => return Err(ParseError::InvalidArithmetic(after_space, format!("missing right operand of `{}`", token))),
+        };
+
+        for operand in [&left, &right] {
+            if !functions::Type::Number.accepts_type(value_type(operand)) {
+                return Err(ParseError::InvalidArithmetic(start, format!("operands of `{}` must be numbers, got {}", token, value_type(operand))));
+            }
+        }
+        left = match (&left, &right) {
+            (Value::Literal(l), Value::Literal(r)) => {
+                let result: serde_json::Value = op.apply(&l.into(), &r.into()).map_err(|err| ParseError::InvalidArithmetic(start, err))?;
+                Value::Literal(match result.as_i64() {
+                    Some(i) => LiteralValue::Int(i),
+                    None => LiteralValue::Float(result.as_f64().unwrap_or_default()),
+                })
+            }
+            _ => Value::Binary(Box::new(Binary { left, op, right })),
+        };
+        tail = next_tail;
+    }
+}
+
+// ( value ), a literal, a function call or an access
+fn parse_operand(input: &str) -> Result<(ParseInput<'_>, ParseResult<Value>), ParseError<'_> > {
+    let start: ParseInput = input;
+
+    if let Some(inner) = input.strip_prefix('(') {
+        let (inner, _) = parse_whitespace(inner)?;
+        let (tail, output) = parse_value(inner)?;
+        let (tail, _) = parse_whitespace(tail)?;
+        return match (output, tail.strip_prefix(')')) {
+            (Some(value), Some(tail)) => Ok((tail, Some(value))),
+            _ => Ok((start, None)),
+        };
+    }
+    
     
     let (tail, output): (ParseInput, ParseResult<LiteralValue>) = parse_literal_value(input)?;
     if output.is_some(){
@@ -551,6 +698,16 @@ pub fn parse_value(input: &str) -> Result<(ParseInput, ParseResult<Value>), Pars
         );
     }
 
+    let (tail, output): (ParseInput, ParseResult<Predicate>) = parse_predicate(input)?;
+    if let Some(predicate) = output {
+        return Ok((tail, Some(Value::Predicate(Box::new(predicate)))));
+    }
+
+    let (tail, output): (ParseInput, ParseResult<Call>) = parse_call(input)?;
+    if let Some(call) = output {
+        return Ok((tail, Some(Value::Call(call))));
+    }
+
     let (tail, output): (ParseInput, ParseResult<Access>) = parse_access(input)?;
     if output.is_some(){
         return Ok(
@@ -564,6 +721,104 @@ pub fn parse_value(input: &str) -> Result<(ParseInput, ParseResult<Value>), Pars
     return Ok((start, None));
 }
 
+// any(comparison) / all(comparison), e.g. any(elf.imports[*].name in ["system", "popen"])
+pub fn parse_predicate(input: &str) -> Result<(ParseInput<'_>, ParseResult<Predicate>), ParseError<'_> > {
+    let start: ParseInput = input;
+
+    let (tail, name): (ParseInput, ParseResult<String>) = parse_access_key(input)?;
+    let quantifier: Quantifier = match name.as_deref() {
+        Some("any") => Quantifier::Any,
+        Some("all") => Quantifier::All,
+        _ => return Ok((start, None)),
+    };
+    let tail: ParseInput = match tail.strip_prefix('(') {
+        Some(tail) => tail,
+        None => return Ok((start, None)),
+    };
+
+    let (tail, _) = parse_whitespace(tail)?;
+    let (tail, condition): (ParseInput, conditions::Condition) = conditions_parser::parse_comparison(tail)
+        .map_err(|err| ParseError::InvalidPredicate(start, err.to_string()))?;
+    let (tail, _) = parse_whitespace(tail)?;
+    match tail.strip_prefix(')') {
+        Some(tail) => Ok((tail, Some(Predicate { quantifier, condition }))),
+        None => Err(ParseError::InvalidPredicate(start, format!("expected `)` after the comparison of {}()", name.unwrap_or_default()))),
+    }
+}
+
+// name(value, value, ...)
+// the function must be in config::functions, and literal arguments and nested calls must have the right type.
+pub fn parse_call(input: &str) -> Result<(ParseInput<'_>, ParseResult<Call>), ParseError<'_> > {
+    let start: ParseInput = input;
+
+    let (tail, name): (ParseInput, ParseResult<String>) = parse_access_key(input)?;
+    let name: String = match name {
+        Some(name) => name,
+        None => return Ok((start, None)),
+    };
+    let mut tail: ParseInput = match tail.strip_prefix('(') {
+        Some(tail) => tail,
+        None => return Ok((start, None)),
+    };
+
+    let mut args: Vec<Value> = Vec::new();
+    let (next_tail, _) = parse_whitespace(tail)?;
+    tail = next_tail;
+    if let Some(next_tail) = tail.strip_prefix(')') {
+        tail = next_tail;
+    }
+    else {
+        loop {
+            let (next_tail, arg) = parse_value(tail)?;
+            match arg {
+                Some(arg) => args.push(arg),
+                None => return Err(ParseError::InvalidFunctionCall(start, format!("invalid argument of {}()", name))),
+            }
+            let (next_tail, _) = parse_whitespace(next_tail)?;
+            if let Some(next_tail) = next_tail.strip_prefix(',') {
+                let (next_tail, _) = parse_whitespace(next_tail)?;
+                tail = next_tail;
+                continue;
+            }
+            match next_tail.strip_prefix(')') {
+                Some(next_tail) => {
+                    tail = next_tail;
+                    break;
+                }
+                None => return Err(ParseError::InvalidFunctionCall(start, format!("expected `,` or `)` in {}()", name))),
+            }
+        }
+    }
+
+    let function: &functions::Function = functions::lookup(&name)
+        .ok_or_else(|| ParseError::InvalidFunctionCall(start, format!("unknown function {}()", name)))?;
+    if function.params.len() != args.len() {
+        return Err(ParseError::InvalidFunctionCall(start, format!("{}() takes {} argument(s), {} given", name, function.params.len(), args.len())));
+    }
+    for (index, (param, arg)) in function.params.iter().zip(&args).enumerate() {
+        let arg_type: functions::Type = value_type(arg);
+        if !param.accepts_type(arg_type) {
+            return Err(ParseError::InvalidFunctionCall(start, format!("{}(): argument {} must be {}, got {}", name, index + 1, param, arg_type)));
+        }
+    }
+
+    Ok((tail, Some(Call { name, args })))
+}
+
+// the type of a value as far as it is known before the analyzers run
+fn value_type(value: &Value) -> functions::Type {
+    match value {
+        Value::Literal(LiteralValue::Int(_)) | Value::Literal(LiteralValue::Float(_)) => functions::Type::Number,
+        Value::Literal(LiteralValue::String(_)) => functions::Type::String,
+        Value::Literal(LiteralValue::Bool(_)) => functions::Type::Bool,
+        Value::Literal(LiteralValue::Array(_)) => functions::Type::Sized,
+        Value::Literal(LiteralValue::Null) | Value::Access(_) => functions::Type::Any,
+        Value::Call(call) => functions::lookup(&call.name).map(|function| function.returns).unwrap_or(functions::Type::Any),
+        Value::Binary(_) => functions::Type::Number,
+        Value::Predicate(_) => functions::Type::Bool,
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -773,6 +1028,43 @@ mod tests {
         );
     }
     
+    #[test]
+    fn test_string_index_access() {
+        let input = "path[\"lib/libc[1].so\"].checksec.relro";
+        let result = parse_access(input).unwrap();
+        assert_eq!(
+            result,
+            (
+                "",
+                Some(
+                    Access{
+                        base: "path".to_string(),
+                        path: Some(vec![
+                            AccessPath::Index(IndexValue::String("lib/libc[1].so".to_string())),
+                            AccessPath::Key("checksec".to_string()),
+                            AccessPath::Key("relro".to_string()),
+                        ])
+                    }
+                )
+            )
+        );
+        assert!(parse_access("path[\"lib\" x]").is_err());
+    }
+
+    #[test]
+    fn test_wildcard_index_access() {
+        let (_, access) = parse_access("elf.sections[*].flags[ * ]").unwrap();
+        assert_eq!(
+            access.unwrap().path,
+            Some(vec![
+                AccessPath::Key("sections".to_string()),
+                AccessPath::Index(IndexValue::Wildcard),
+                AccessPath::Key("flags".to_string()),
+                AccessPath::Index(IndexValue::Wildcard),
+            ])
+        );
+    }
+
     #[test]
     fn test_mixed_dot_and_index_access() {
         let input = "root.key[1]";
@@ -908,5 +1200,65 @@ mod tests {
     }
     */
   
-}
 
+    #[test]
+    fn test_parse_value_call() {
+        let (tail, value) = parse_value("startswith(lower(path), \"etc/\") == true").unwrap();
+        assert_eq!(tail, " == true");
+        assert_eq!(value, Some(Value::Call(Call {
+            name: "startswith".to_string(),
+            args: vec![
+                Value::Call(Call {
+                    name: "lower".to_string(),
+                    args: vec![Value::Access(Access { base: "path".to_string(), path: None })],
+                }),
+                Value::Literal(LiteralValue::String("etc/".to_string())),
+            ],
+        })));
+    }
+
+    #[test]
+    fn test_parse_value_call_errors() {
+        assert!(matches!(parse_value("nosuch(path)"), Err(ParseError::InvalidFunctionCall(_, _))));
+        assert!(matches!(parse_value("len(path, path)"), Err(ParseError::InvalidFunctionCall(_, _))));
+        assert!(matches!(parse_value("lower(len(path))"), Err(ParseError::InvalidFunctionCall(_, _))));
+        assert!(matches!(parse_value("lower(path"), Err(ParseError::InvalidFunctionCall(_, _))));
+    }
+
+    #[test]
+    fn test_parse_int_radix() {
+        assert_eq!(parse_int("0x1F "), Ok((" ", Some(31))));
+        assert_eq!(parse_int("0o4000"), Ok(("", Some(2048))));
+        assert_eq!(parse_int("-0b101"), Ok(("", Some(-5))));
+        assert_eq!(parse_int("4294967296"), Ok(("", Some(4294967296))));
+        assert_eq!(parse_int("0xg"), Ok(("xg", Some(0))));
+    }
+
+    #[test]
+    fn test_parse_float_keeps_leading_zeros() {
+        assert_eq!(parse_float("1.05"), Ok(("", Some(1.05))));
+        assert_eq!(parse_float("-0.5"), Ok(("", Some(-0.5))));
+    }
+
+    #[test]
+    fn test_parse_value_arithmetic_precedence() {
+        // a & (0o4000 | (1 << 2))... is folded where both sides are literals
+        let (tail, value) = parse_value("a & 0o4000 | 1 << 2 != 0").unwrap();
+        assert_eq!(tail, " != 0");
+        let a = Value::Access(Access { base: "a".to_string(), path: None });
+        assert_eq!(value, Some(Value::Binary(Box::new(Binary {
+            left: Value::Binary(Box::new(Binary { left: a, op: BinaryOperator::BitAnd, right: Value::Literal(LiteralValue::Int(2048)) })),
+            op: BinaryOperator::BitOr,
+            right: Value::Literal(LiteralValue::Int(4)),
+        }))));
+
+        assert_eq!(parse_value("(1 + 2) * 3 - 10 / 4"), Ok(("", Some(Value::Literal(LiteralValue::Int(7))))));
+    }
+
+    #[test]
+    fn test_parse_value_arithmetic_errors() {
+        assert!(matches!(parse_value("1 / 0"), Err(ParseError::InvalidArithmetic(_, _))));
+        assert!(matches!(parse_value("\"a\" + 1"), Err(ParseError::InvalidArithmetic(_, _))));
+        assert!(matches!(parse_value("a +"), Err(ParseError::InvalidArithmetic(_, _))));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::config::parser::base_parser;
+use crate::config::parser::parser_type;
 use crate::config::conditions;
+use crate::config::functions;
 
 #[derive(Debug, PartialEq)]
 pub enum ParseConditionError<'a>{
@@ -7,7 +9,8 @@ pub enum ParseConditionError<'a>{
     SyntaxError(base_parser::ParseInput<'a>, &'a str),
     InvalidValue(base_parser::ParseInput<'a>, &'a str),
     InvalidOperator(base_parser::ParseInput<'a>, &'a str),
-    InvalidChain(base_parser::ParseInput<'a>, &'a str)
+    InvalidChain(base_parser::ParseInput<'a>, &'a str),
+    InvalidPattern(base_parser::ParseInput<'a>, String),
 }
 
 impl<'a> From<base_parser::ParseError<'a>> for ParseConditionError<'a> {
@@ -23,7 +26,8 @@ impl<'a> std::fmt::Display for ParseConditionError<'a> {
             ParseConditionError::SyntaxError(input, add_info) => write!(f, "Syntax Error: input: {},  error info :{:?}", input, add_info),
             ParseConditionError::InvalidValue(input, add_info) => write!(f, "Invaild Value: input: {},  error info :{:?}", input, add_info),
             ParseConditionError::InvalidOperator(input, add_info) => write!(f, "Invaild Operator: input: {},  error info :{:?}", input, add_info),
-            ParseConditionError::InvalidChain(input, add_info) => write!(f, "Invaild Chain: input: {},  error info :{:?}", input, add_info)
+            ParseConditionError::InvalidChain(input, add_info) => write!(f, "Invaild Chain: input: {},  error info :{:?}", input, add_info),
+            ParseConditionError::InvalidPattern(input, add_info) => write!(f, "Invaild Pattern: input: {},  error info :{:?}", input, add_info),
         }
     }
 }
@@ -31,172 +35,230 @@ impl<'a> std::fmt::Display for ParseConditionError<'a> {
 impl<'a> std::error::Error for ParseConditionError<'a> {}
 
 
-pub fn parse_condition(input: base_parser::ParseInput) -> Result<conditions::Condition, ParseConditionError<'_>> {
-    let start: base_parser::ParseInput = input;
+// expression := or
+// or         := and ("or" and)*
+// and        := not ("and" not)*
+// not        := "not" not | primary
+// primary    := "(" or ")" | comparison
+pub fn parse_condition(input: base_parser::ParseInput<'_>) -> Result<conditions::Expression, ParseConditionError<'_>> {
+    let (remaining, expression) = parse_or(input)?;
 
-    // [value] [multispace] [operator] [multispace] [value] [multispace] [option<chain>] [\n or EOF]
-    let (remaining, space) = base_parser::parse_whitespace(input)?;
-    let (remaining, left) = base_parser::parse_value(remaining)?;
-    if remaining.is_empty() && !left.is_none(){
-        // 111 EOF
-        return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected operator and right-hand value after left-hand value"));
+    let (remaining, _) = base_parser::parse_whitespace(remaining)?;
+    if remaining.starts_with(')') {
+        // a == b)
+        return Err(ParseConditionError::SyntaxError(remaining, "Unmatched closing parenthesis"));
+    }
+    if !remaining.is_empty() {
+        // a == b andand
+        return Err(ParseConditionError::InvalidChain(remaining, "Invalid chain"));
+    }
+
+    Ok(expression)
+}
+
+fn parse_or(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Expression), ParseConditionError<'_>> {
+    let (mut remaining, mut expression) = parse_and(input)?;
+    loop {
+        let (after_chain, chain) = parse_chain_keyword(remaining)?;
+        if chain != Some(parser_type::Chain::Or) {
+            return Ok((remaining, expression));
+        }
+        let (after_right, right) = parse_and(after_chain)?;
+        expression = conditions::Expression::Or(Box::new(expression), Box::new(right));
+        remaining = after_right;
+    }
+}
+
+fn parse_and(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Expression), ParseConditionError<'_>> {
+    let (mut remaining, mut expression) = parse_not(input)?;
+    loop {
+        let (after_chain, chain) = parse_chain_keyword(remaining)?;
+        if chain != Some(parser_type::Chain::And) {
+            return Ok((remaining, expression));
+        }
+        let (after_right, right) = parse_not(after_chain)?;
+        expression = conditions::Expression::And(Box::new(expression), Box::new(right));
+        remaining = after_right;
+    }
+}
+
+fn parse_not(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Expression), ParseConditionError<'_>> {
+    let (remaining, _) = base_parser::parse_whitespace(input)?;
+    if let Some(after_not) = strip_keyword(remaining, "not") {
+        let (remaining, operand) = parse_not(after_not)?;
+        return Ok((remaining, conditions::Expression::Not(Box::new(operand))));
+    }
+    parse_primary(remaining)
+}
+
+fn parse_primary(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Expression), ParseConditionError<'_>> {
+    let (remaining, _) = base_parser::parse_whitespace(input)?;
+    if remaining.is_empty() {
+        // a == b and EOF
+        return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected condition"));
+    }
+    if let Some(inner) = remaining.strip_prefix('(') {
+        let group = parse_group(inner);
+        if group.is_ok() {
+            return group;
+        }
+        // not a group of conditions but a value: (a + 1) * 2 > 3
+        if let Ok((remaining, condition)) = parse_comparison(remaining) {
+            return Ok((remaining, conditions::Expression::Compare(condition)));
+        }
+        return group;
+    }
+    let (remaining, condition) = parse_comparison(remaining)?;
+    Ok((remaining, conditions::Expression::Compare(condition)))
+}
+
+fn parse_group(inner: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Expression), ParseConditionError<'_>> {
+    let (remaining, expression) = parse_or(inner)?;
+    let (remaining, _) = base_parser::parse_whitespace(remaining)?;
+    match remaining.strip_prefix(')') {
+        Some(remaining) => Ok((remaining, expression)),
+        // (a == b
+        None => Err(ParseConditionError::SyntaxError(remaining, "Missing closing parenthesis")),
+    }
+}
+
+// `and` / `or` only count as a chain when they are a whole word
+fn parse_chain_keyword(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, Option<parser_type::Chain>), ParseConditionError<'_>> {
+    let (remaining, _) = base_parser::parse_whitespace(input)?;
+    let (after_chain, chain) = base_parser::parse_chain(remaining)?;
+    if chain.is_none() || !is_word_end(after_chain) {
+        return Ok((input, None));
+    }
+    Ok((after_chain, chain))
+}
+
+fn strip_keyword<'a>(input: base_parser::ParseInput<'a>, keyword: &str) -> Option<base_parser::ParseInput<'a>> {
+    input.strip_prefix(keyword).filter(|rest| is_word_end(rest))
+}
+
+fn is_word_end(input: base_parser::ParseInput<'_>) -> bool {
+    match input.chars().next() {
+        Some(c) => c.is_whitespace() || c == '(' || c == ')',
+        None => true,
+    }
+}
+
+fn is_bool_call(value: &parser_type::Value) -> bool {
+    match value {
+        parser_type::Value::Call(call) => functions::lookup(&call.name).is_some_and(|function| function.returns == functions::Type::Bool),
+        parser_type::Value::Predicate(_) => true,
+        _ => false,
     }
-    if !remaining.is_empty() && left.is_none(){
-        // aaaa == "aaaa"
-        return Err(ParseConditionError::InvalidValue(remaining, "Invalid left-hand value"));
+}
+
+// [value] [multispace] [operator] [multispace] [value]
+pub fn parse_comparison(input: base_parser::ParseInput<'_>) -> Result<(base_parser::ParseInput<'_>, conditions::Condition), ParseConditionError<'_>> {
+    let (remaining, left) = base_parser::parse_value(input)?;
+    // a function returning a bool is a condition by itself: startswith(path, "etc/") and ...
+    if let Some(left) = left.as_ref().filter(|left| is_bool_call(left)) {
+        let (after_space, _) = base_parser::parse_whitespace(remaining)?;
+        if after_space.is_empty() || after_space.starts_with(')') || strip_keyword(after_space, "and").is_some() || strip_keyword(after_space, "or").is_some() {
+            let right: parser_type::Value = parser_type::Value::Literal(parser_type::LiteralValue::Bool(true));
+            return Ok((remaining, conditions::Condition { left: left.clone(), op: parser_type::Operator::Equal, right, pattern: None }));
+        }
     }
-    if remaining.is_empty() && left.is_none(){
-        return Err(ParseConditionError::SyntaxError(remaining, "Missing left-hand value"));
+    if remaining.is_empty() && left.is_some() {
+        // 111 EOF
+        return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected operator and right-hand value after left-hand value"));
     }
+    let left: parser_type::Value = match left {
+        Some(left) => left,
+        // == "aaaa"
+        None => return Err(ParseConditionError::InvalidValue(remaining, "Invalid left-hand value")),
+    };
 
 
-    // [multispace] [operator] [multispace] [value] [multispace] [option<chain>] [\n or EOF]
+    // [multispace] [operator] [multispace] [value]
     let (remaining, space) = base_parser::parse_whitespace(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !space.is_none() && remaining.is_empty(){
+    if space.is_some() && remaining.is_empty() {
         return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected operator and right-hand value after left-hand value"));
     }
-    if space.is_none() && !remaining.is_empty(){
+    if space.is_none() {
         // for exsample if `111aaa` is parsed as int type, remainig = aaa, result = Value::Int(111).
         return Err(ParseConditionError::InvalidValue(remaining, "Unexpected data exists in left value suffix"));
     }
 
 
-    // [operator] [multispace] [value] [multispace] [option<chain>] [\n or EOF]
+    // [operator] [multispace] [value]
     let (remaining, op) = base_parser::parse_operator(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !op.is_none() && remaining.is_empty(){
+    if op.is_some() && remaining.is_empty() {
         // 1111 == EOF
         return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected operator and right-hand value after left-hand value"));
     }
-    if op.is_none() && !remaining.is_empty(){
+    let op: parser_type::Operator = match op {
+        Some(op) => op,
         // 1111 ffff 1111
-        return Err(ParseConditionError::InvalidOperator(remaining, "Invalid Operator"));
-    }
+        None => return Err(ParseConditionError::InvalidOperator(remaining, "Invalid Operator")),
+    };
 
-    
-    // [multispace] [value] [multispace] [option<chain>] [\n or EOF]
+
+    // [multispace] [value]
     let (remaining, space) = base_parser::parse_whitespace(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !space.is_none() && remaining.is_empty(){
+    if space.is_some() && remaining.is_empty() {
         // 111 == space EOF
         return Err(ParseConditionError::SyntaxError(remaining, "Truncated expression: Expected operator and right-hand value after left-hand value"));
     }
-    if space.is_none() && !remaining.is_empty(){
+    if space.is_none() {
         // 111 ==4444 1111. between operator and value is required space.
         return Err(ParseConditionError::InvalidOperator(remaining, "Unexpected data exists in operator suffix"));
     }
 
 
-    // [value] [multispace] [option<chain>] [\n or EOF]
+    // [value]
     let (remaining, right) = base_parser::parse_value(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !right.is_none() && remaining.is_empty(){
-        // 111 == 111
-        // success
-        return Ok( conditions::Condition { 
-            left:left.unwrap(), 
-            op:op.unwrap(), 
-            right:right.unwrap(), 
-            chain:None }
-        );
-    }
-    if right.is_none() && !remaining.is_empty(){
+    let right: parser_type::Value = match right {
+        Some(right) => right,
         // "aaa" == aaa
-        return Err(ParseConditionError::InvalidValue(remaining, "Invalid right-hand value"));
-    }
-
-
-    //[multispace] [option<chain>] [\n or EOF]
-    let (remaining, space) = base_parser::parse_whitespace(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !space.is_none() && remaining.is_empty(){
-        // 111 == 111 space
-        // success
-        return Ok( conditions::Condition { 
-            left:left.unwrap(), 
-            op:op.unwrap(), 
-            right:right.unwrap(), 
-            chain:None }
-        );
-    }
-    if space.is_none() && !remaining.is_empty(){
+        None => return Err(ParseConditionError::InvalidValue(remaining, "Invalid right-hand value")),
+    };
+    if !is_word_end(remaining) {
         // 111 == 111aaa
         return Err(ParseConditionError::InvalidValue(remaining, "Unexpected data exists in right value suffix"));
     }
 
+    let pattern: Option<parser_type::Pattern> = match &right {
+        parser_type::Value::Literal(parser_type::LiteralValue::String(source)) => {
+            parser_type::Pattern::compile(&op, source).map_err(|err| ParseConditionError::InvalidPattern(input, err))?
+        }
+        _ => None,
+    };
 
-    // [option<chain>] [\n or EOF]
-    let (remaining, chain) = base_parser::parse_chain(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !chain.is_none() && remaining.is_empty(){
-        // 111 == 111 and
-        // success
-        return Ok( conditions::Condition { 
-            left:left.unwrap(), 
-            op:op.unwrap(), 
-            right:right.unwrap(), 
-            chain:chain }
-        );
-    }
-    if chain.is_none() && !remaining.is_empty(){
-        // 111 == 111 tttt
-        return Err(ParseConditionError::InvalidChain(remaining, "Invalid chain"));
-    }
-
-
-    //[\n or EOF]
-    let (remaining, space) = base_parser::parse_whitespace(remaining)?;
-    // I don't need it because I know the strings is not empty in the code above.
-    //if result.is_none() && remaining.is_empty(){
-    //}
-    if !space.is_none() && remaining.is_empty(){
-        // 111 == 111 and space
-        // success
-        return Ok( conditions::Condition { 
-            left:left.unwrap(), 
-            op:op.unwrap(), 
-            right:right.unwrap(), 
-            chain:chain }
-        );
-    }
-    if space.is_none() && !remaining.is_empty(){
-        // 111 == 111 andaaaa
-        return Err(ParseConditionError::InvalidChain(remaining, "Unexpected data exists in right Chain suffix"));
-    }
-
-    // 111 == 111 and faddsaf
-    return Err(ParseConditionError::SyntaxError(remaining, "Data exists at the rear of Chain"));
-
+    Ok((remaining, conditions::Condition { left, op, right, pattern }))
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::parser::parser_type;
+    use crate::config::conditions::Expression;
+
+    fn access(base: &str) -> parser_type::Value {
+        parser_type::Value::Access(parser_type::Access{base: base.to_string(), path: None})
+    }
+
+    fn compare(left: &str, right: i64) -> Expression {
+        Expression::Compare(conditions::Condition {
+            left: access(left),
+            op: parser_type::Operator::Equal,
+            right: parser_type::Value::Literal(parser_type::LiteralValue::Int(right)),
+            pattern: None,
+        })
+    }
 
     #[test]
     fn test_parse_condition_valid_case() {
         let input = "a == b";
-        let expected = Ok(conditions::Condition {
-            left: parser_type::Value::Access(parser_type::Access{base: "a".to_string(), path: None}),
+        let expected = Ok(Expression::Compare(conditions::Condition {
+            left: access("a"),
             op: parser_type::Operator::Equal,
-            right: parser_type::Value::Access(parser_type::Access{base: "b".to_string(), path: None}),
-            chain: None,
-        });
+            right: access("b"),
+            pattern: None,
+        }));
         assert_eq!(parse_condition(input), expected);
     }
 
@@ -210,12 +272,7 @@ mod tests {
     #[test]
     fn test_parse_condition_with_chain() {
         let input = "a == b and";
-        let expected = Ok(conditions::Condition {
-            left: parser_type::Value::Access(parser_type::Access{base: "a".to_string(), path: None}),
-            op: parser_type::Operator::Equal,
-            right: parser_type::Value::Access(parser_type::Access{base: "b".to_string(), path: None}),
-            chain: Some(parser_type::Chain::And),
-        });
+        let expected = Err(ParseConditionError::SyntaxError("", "Truncated expression: Expected condition"));
         assert_eq!(parse_condition(input), expected);
     }
 
@@ -229,7 +286,7 @@ mod tests {
     #[test]
     fn test_parse_condition_invalid_chain() {
         let input = "a == b andand";
-        let expected = Err(ParseConditionError::InvalidChain("and", "Unexpected data exists in right Chain suffix"));
+        let expected = Err(ParseConditionError::InvalidChain("andand", "Invalid chain"));
         assert_eq!(parse_condition(input), expected);
     }
 
@@ -247,6 +304,120 @@ mod tests {
         assert_eq!(parse_condition(input), expected);
     }
 
+    #[test]
+    fn test_parse_condition_precedence() {
+        // a or (b and (not c))
+        let input = "a == 1 or b == 2 and not c == 3";
+        let expected = Ok(Expression::Or(
+            Box::new(compare("a", 1)),
+            Box::new(Expression::And(
+                Box::new(compare("b", 2)),
+                Box::new(Expression::Not(Box::new(compare("c", 3)))),
+            )),
+        ));
+        assert_eq!(parse_condition(input), expected);
+    }
+
+    #[test]
+    fn test_parse_condition_parentheses_across_lines() {
+        let input = "(a == 1 or\n  b == 2)\nand c == 3";
+        let expected = Ok(Expression::And(
+            Box::new(Expression::Or(Box::new(compare("a", 1)), Box::new(compare("b", 2)))),
+            Box::new(compare("c", 3)),
+        ));
+        assert_eq!(parse_condition(input), expected);
+    }
 
+    #[test]
+    fn test_parse_condition_keyword_prefix_is_access() {
+        let input = "not notes == 1 and order == 2";
+        let expected = Ok(Expression::And(
+            Box::new(Expression::Not(Box::new(compare("notes", 1)))),
+            Box::new(compare("order", 2)),
+        ));
+        assert_eq!(parse_condition(input), expected);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_condition_unbalanced_parentheses() {
+        assert_eq!(parse_condition("(a == 1"), Err(ParseConditionError::SyntaxError("", "Missing closing parenthesis")));
+        assert_eq!(parse_condition("a == 1)"), Err(ParseConditionError::SyntaxError(")", "Unmatched closing parenthesis")));
+    }
+
+    #[test]
+    fn test_parse_condition_compiles_pattern() {
+        let expression = parse_condition("path =~ \"^usr/lib/.*\\.so(\\.[0-9]+)*$\"").unwrap();
+        match expression {
+            Expression::Compare(conditions::Condition { op: parser_type::Operator::Match, pattern: Some(pattern), .. }) => {
+                assert!(pattern.is_match("usr/lib/libc.so.6"));
+                assert!(!pattern.is_match("usr/lib/libc.a"));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let expression = parse_condition("basic_info.mime iglob \"Application/x-*executable\"").unwrap();
+        match expression {
+            Expression::Compare(conditions::Condition { op: parser_type::Operator::IGlob, pattern: Some(pattern), .. }) => {
+                assert!(pattern.is_match("application/x-pie-executable"));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_condition_invalid_pattern() {
+        assert!(matches!(parse_condition("path =~ \"(unclosed\""), Err(ParseConditionError::InvalidPattern(_, _))));
+    }
+
+    #[test]
+    fn test_parse_condition_bool_call() {
+        let expression = parse_condition("not exists(checksec.nx) or startswith(path, \"etc/\")").unwrap();
+        match expression {
+            Expression::Or(left, right) => {
+                assert!(matches!(*left, Expression::Not(_)));
+                assert!(matches!(*right, Expression::Compare(conditions::Condition { op: parser_type::Operator::Equal, .. })));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_condition_arithmetic() {
+        let expression = parse_condition("(basic_info.size + 511) / 512 > 8 and basic_info.permissions & 0o4000 != 0").unwrap();
+        match expression {
+            Expression::And(left, right) => {
+                assert!(matches!(*left, Expression::Compare(conditions::Condition { left: parser_type::Value::Binary(_), op: parser_type::Operator::GreaterThan, .. })));
+                assert!(matches!(*right, Expression::Compare(conditions::Condition { left: parser_type::Value::Binary(_), op: parser_type::Operator::NotEqual, .. })));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_condition_predicate() {
+        let expression = parse_condition("any(elf.imports[*].name in [\"system\", \"popen\"]) and not all(elf.symbols[*].size > 0)").unwrap();
+        match expression {
+            Expression::And(left, right) => {
+                match *left {
+                    Expression::Compare(conditions::Condition { left: parser_type::Value::Predicate(predicate), op: parser_type::Operator::Equal, .. }) => {
+                        assert_eq!(predicate.quantifier, parser_type::Quantifier::Any);
+                        assert_eq!(predicate.condition.op, parser_type::Operator::In);
+                        assert_eq!(
+                            predicate.condition.right,
+                            parser_type::Value::Literal(parser_type::LiteralValue::Array(vec![
+                                parser_type::LiteralValue::String("system".to_string()),
+                                parser_type::LiteralValue::String("popen".to_string()),
+                            ]))
+                        );
+                    }
+                    other => panic!("Unexpected result: {:?}", other),
+                }
+                assert!(matches!(*right, Expression::Not(_)));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        assert!(parse_condition("any(elf.imports[*].name)").is_err());
+        assert!(parse_condition("any(elf.imports[*].size > 0").is_err());
+    }
+}
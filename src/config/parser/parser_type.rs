@@ -1,4 +1,5 @@
 extern crate nom;
+use crate::config::conditions;
 
 
 #[derive(Debug, PartialEq, Clone)]
@@ -10,7 +11,11 @@ pub enum AccessPath {
 #[derive(Debug, PartialEq, Clone)]
 pub enum IndexValue{
     Access(Access),
-    Int(i32),
+    Int(i64),
+    // an object key, or the path of a file: path["lib/libc.so.6"]
+    String(String),
+    // every element: elf.imports[*].name
+    Wildcard,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -23,15 +28,119 @@ pub struct Access{
 pub enum Value {
     Literal(LiteralValue),
     Access(Access),
+    Call(Call),
+    Binary(Box<Binary>),
+    Predicate(Box<Predicate>),
+}
+
+// any(comparison) / all(comparison): the comparison is checked for every element of the [*] projections in it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Predicate {
+    pub quantifier: Quantifier,
+    pub condition: conditions::Condition,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Quantifier {
+    Any,
+    All,
+}
+
+// arithmetic or bitwise operation, e.g. `basic_info.permissions & 0o4000`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Binary {
+    pub left: Value,
+    pub op: BinaryOperator,
+    pub right: Value,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    // integer division truncates
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+impl BinaryOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Rem => "%",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::BitXor => "^",
+            BinaryOperator::Shl => "<<",
+            BinaryOperator::Shr => ">>",
+        }
+    }
+
+    // integers stay i64 and fail on overflow. a null operand (a missing value) gives null
+    pub fn apply(&self, left: &serde_json::Value, right: &serde_json::Value) -> Result<serde_json::Value, String> {
+        let (left_num, right_num) = match (left, right) {
+            (serde_json::Value::Null, _) | (_, serde_json::Value::Null) => return Ok(serde_json::Value::Null),
+            (serde_json::Value::Number(l), serde_json::Value::Number(r)) => (l, r),
+            _ => return Err(format!("operands of `{}` must be numbers, got {} and {}", self.as_str(), left, right)),
+        };
+
+        if let (Some(l), Some(r)) = (left_num.as_i64(), right_num.as_i64()) {
+            let result: Option<i64> = match self {
+                BinaryOperator::Add => l.checked_add(r),
+                BinaryOperator::Sub => l.checked_sub(r),
+                BinaryOperator::Mul => l.checked_mul(r),
+                BinaryOperator::Div => l.checked_div(r),
+                BinaryOperator::Rem => l.checked_rem(r),
+                BinaryOperator::BitAnd => Some(l & r),
+                BinaryOperator::BitOr => Some(l | r),
+                BinaryOperator::BitXor => Some(l ^ r),
+                BinaryOperator::Shl => u32::try_from(r).ok().and_then(|r| l.checked_shl(r)),
+                BinaryOperator::Shr => u32::try_from(r).ok().and_then(|r| l.checked_shr(r)),
+            };
+            return result
+                .map(|value| serde_json::Value::Number(value.into()))
+                .ok_or_else(|| format!("{} {} {}: overflow or division by zero", l, self.as_str(), r));
+        }
+
+        let (l, r): (f64, f64) = (left_num.as_f64().unwrap_or_default(), right_num.as_f64().unwrap_or_default());
+        let result: f64 = match self {
+            BinaryOperator::Add => l + r,
+            BinaryOperator::Sub => l - r,
+            BinaryOperator::Mul => l * r,
+            BinaryOperator::Div => l / r,
+            BinaryOperator::Rem => l % r,
+            _ => return Err(format!("operands of `{}` must be integers, got {} and {}", self.as_str(), left, right)),
+        };
+        serde_json::Number::from_f64(result)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| format!("{} {} {}: not a finite number", l, self.as_str(), r))
+    }
+}
+
+// call of a built-in function (config::functions), e.g. `len(ldd.libs)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Value>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum LiteralValue {
-    Int(i32),
+    Int(i64),
     Float(f64),
     String(String),
     Bool(bool),
     Null,
+    Array(Vec<LiteralValue>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -40,7 +149,7 @@ pub enum Chain {
     Or,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Operator {
     Equal,
     NotEqual,
@@ -49,5 +158,70 @@ pub enum Operator {
     GreaterThan,
     GreaterThanEqual,
     In,
+    // regex search, `=~` and `!~`
+    Match,
+    NotMatch,
+    // whole string glob match, `glob` and the case-insensitive `iglob`
+    Glob,
+    IGlob,
+}
+
+impl Operator {
+    pub fn is_pattern(&self) -> bool {
+        matches!(self, Operator::Match | Operator::NotMatch | Operator::Glob | Operator::IGlob)
+    }
+}
+
+// the compiled right-hand side of a pattern operator
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Regex(regex::Regex),
+    Glob(glob::Pattern, glob::MatchOptions),
+}
+
+impl Pattern {
+    // None if `op` does not take a pattern
+    pub fn compile(op: &Operator, source: &str) -> Result<Option<Pattern>, String> {
+        match op {
+            Operator::Match | Operator::NotMatch => {
+                let regex: regex::Regex = regex::Regex::new(source).map_err(|err| err.to_string())?;
+                Ok(Some(Pattern::Regex(regex)))
+            }
+            Operator::Glob | Operator::IGlob => {
+                let glob: glob::Pattern = glob::Pattern::new(source).map_err(|err| err.to_string())?;
+                let options: glob::MatchOptions = glob::MatchOptions {
+                    case_sensitive: *op == Operator::Glob,
+                    require_literal_separator: false,
+                    require_literal_leading_dot: false,
+                };
+                Ok(Some(Pattern::Glob(glob, options)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Regex(regex) => regex.is_match(text),
+            Pattern::Glob(glob, options) => glob.matches_with(text, *options),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Pattern::Regex(regex) => regex.as_str(),
+            Pattern::Glob(glob, _) => glob.as_str(),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        match (self, other) {
+            (Pattern::Regex(left), Pattern::Regex(right)) => left.as_str() == right.as_str(),
+            (Pattern::Glob(left, left_options), Pattern::Glob(right, right_options)) => left == right && left_options == right_options,
+            _ => false,
+        }
+    }
 }
 
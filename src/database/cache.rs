@@ -0,0 +1,61 @@
+use rusqlite;
+use rusqlite::OptionalExtension;
+use std::path::Path;
+use crate::database::error::Error;
+use crate::database::result::Result;
+
+
+// Results shared between runs, e.g. between two versions of the same firmware.
+// An analyzer run is identified by the content of the file, the analyzer, its version and its arguments,
+// so an identical file at any path reuses the previous output instead of running the analyzer again.
+pub struct Cache {
+    conn: rusqlite::Connection,
+}
+
+pub struct CacheKey {
+    pub file_sha256: String,
+    pub analyzer: String,
+    pub version: String,
+    pub arguments_sha256: String,
+}
+
+impl Cache {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn: rusqlite::Connection = rusqlite::Connection::open(path)?;
+        conn.execute("CREATE TABLE IF NOT EXISTS cache (
+                            file_sha256 TEXT,
+                            analyzer TEXT,
+                            version TEXT,
+                            arguments_sha256 TEXT,
+                            value JSON,
+                            PRIMARY KEY (file_sha256, analyzer, version, arguments_sha256)
+                        )",
+                    [])?;
+        // new entries are committed together when the run ends
+        conn.execute_batch("BEGIN")?;
+        Ok(Cache { conn })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Result<Option<serde_json::Value>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT value FROM cache WHERE file_sha256 = ?1 AND analyzer = ?2 AND version = ?3 AND arguments_sha256 = ?4"
+        )?;
+        let value: Option<serde_json::Value> = stmt
+            .query_row(rusqlite::params![key.file_sha256, key.analyzer, key.version, key.arguments_sha256], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn put(&self, key: &CacheKey, value: &serde_json::Value) -> Result<()> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT OR REPLACE INTO cache (file_sha256, analyzer, version, arguments_sha256, value) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        stmt.execute(rusqlite::params![key.file_sha256, key.analyzer, key.version, key.arguments_sha256, value])?;
+        Ok(())
+    }
+
+    pub fn commit(self) -> Result<(), Error> {
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+}
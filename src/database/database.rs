@@ -1,4 +1,5 @@
 use rusqlite;
+use rusqlite::OptionalExtension;
 use rusqlite::Params;
 use std::path::Path;
 use crate::config::parser::parser_type::Access;
@@ -10,6 +11,7 @@ use crate::database::result::Result;
 use crate::config::config::Config;
 use crate::config::conditions;
 use crate::config::arguments;
+use crate::config::functions;
 use crate::config::parser::parser_type;
 use crate::database::error::Error;
 use base64::Engine;
@@ -31,10 +33,102 @@ pub struct StatementT<'a>{
 
 pub struct InsertAnalyerStatement<'a, 'b>{
     result: Statement<'a>,
+    error: Statement<'a>,
+    image: Statement<'a>,
+    find_image: Statement<'a>,
     // Hashmap<analyzer name, insert stmt>
     analyzer: std::collections::HashMap<&'b str, Statement<'a>>,
 }
 
+// lookups of a previous run, used when resuming on an existing database
+pub struct ResumeStatement<'a, 'b>{
+    path: Statement<'a>,
+    // the files whose path starts with a prefix
+    emitted: Statement<'a>,
+    // Hashmap<analyzer name, select stmt>
+    analyzer: std::collections::HashMap<&'b str, Statement<'a>>,
+    // Hashmap<analyzer name, delete stmt>
+    forget: std::collections::HashMap<&'b str, Statement<'a>>,
+}
+
+// what the core records about every file in the result table
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileMetadata {
+    pub size: Option<i64>,
+    // permission bits, with setuid, setgid and sticky
+    pub mode: Option<i64>,
+    pub uid: Option<i64>,
+    pub gid: Option<i64>,
+    // one of FILE_TYPES
+    pub file_type: Option<String>,
+    pub link_target: Option<String>,
+    // seconds since the unix epoch
+    pub mtime: Option<i64>,
+    // only for regular files
+    pub sha256: Option<String>,
+    pub mime: Option<String>,
+}
+
+// where a file comes from, when it was extracted from an image
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FileOrigin {
+    // the image the file was extracted from
+    pub image_id: Option<i64>,
+    // the result id of the archive the file was found in, when the archive is itself a file of the firmware
+    pub parent_id: Option<i64>,
+}
+
+// a row of the image table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageRecord<'r> {
+    // the image this image was extracted from
+    pub parent_id: Option<i64>,
+    // the result id of the image, when it is a file of the firmware
+    pub result_id: Option<i64>,
+    pub path: &'r str,
+    // the extractor that unpacked it
+    pub origin: &'r str,
+    pub sha256: Option<&'r str>,
+    // why it could not be unpacked
+    pub error: Option<&'r str>,
+}
+
+// a row of the analyzer_error table
+pub struct AnalyzerErrorRecord<'r> {
+    pub result_id: i64,
+    pub analyzer: &'r str,
+    pub kind: &'r str,
+    pub message: &'r str,
+    pub stderr: Option<&'r str>,
+    // the exit status of an analyzer run in a child process, or the return code of a so analyzer
+    pub exit_code: Option<i32>,
+    // the signal that killed it
+    pub signal: Option<i32>,
+}
+
+// the result id of the results of image analyzers (scope = "image"). no file has it, and every file reads them.
+pub const IMAGE_RESULT_ID: i64 = 0;
+
+pub const FILE_TYPES: &[&str] = &["regular", "directory", "symlink", "block_device", "char_device", "fifo", "socket"];
+
+// columns of the result table after `path`. each one is a built-in name of conditions and arguments,
+// like `path`: size > 1024, mime glob "application/x-*"
+pub const FILE_COLUMNS: &[(&str, &str)] = &[
+    ("size", "INTEGER"),
+    ("mode", "INTEGER"),
+    ("uid", "INTEGER"),
+    ("gid", "INTEGER"),
+    ("file_type", "TEXT"),
+    ("link_target", "TEXT"),
+    ("mtime", "INTEGER"),
+    ("sha256", "TEXT"),
+    ("mime", "TEXT"),
+];
+
+pub fn file_column(name: &str) -> Option<&'static str> {
+    FILE_COLUMNS.iter().map(|(column, _)| *column).find(|column| *column == name)
+}
+
 pub struct SelectAnalyzerStatement<'a, 'b>{
     stmt: std::collections::HashMap<&'b str, (ArgumentStatement<'a>, Option<ConditionStatement<'a>>)>
 }
@@ -42,6 +136,8 @@ pub struct SelectAnalyzerStatement<'a, 'b>{
 #[derive(Clone)]
 pub enum BindType {
     ResultId(i64),
+    // not replaced by set_placeholder
+    JsonPath(String),
 }
 
 pub enum BindRequirement {
@@ -51,7 +147,44 @@ pub enum BindRequirement {
 
 pub enum AccessStatement<'a>{
     Stmt(Statement<'a>, BindRequirement),
-    Format(String)
+    Format(Box<FormatStatement<'a>>)
+}
+
+// an access whose file or json path is only known when it runs: ldd.libs[i], path["lib/libc.so.6"].checksec.relro
+// ?1 is the result id or the path of the file, ?2 the json path.
+pub struct FormatStatement<'a>{
+    stmt: Statement<'a>,
+    file: FileKey<'a>,
+    // None when only the path of the file is selected
+    json_path: Option<Vec<JsonPathPart<'a>>>,
+}
+
+pub enum FileKey<'a>{
+    // the file being analyzed, set by set_placeholder
+    Current(Option<i64>),
+    Id(i64),
+    Path(String),
+    // an integer is a result id, a string a path
+    Dynamic(AccessStatement<'a>),
+    // path[*]: every file, in result id order
+    All(Statement<'a>),
+}
+
+pub enum JsonPathPart<'a>{
+    Key(String),
+    Index(i64),
+    // an integer indexes an array, a string is an object key
+    Dynamic(AccessStatement<'a>),
+    // [*]: the value is the array of what the rest of the path selects in every element
+    Wildcard,
+}
+
+// a JsonPathPart once its dynamic index is known
+#[derive(PartialEq)]
+enum JsonPathStep {
+    Key(String),
+    Index(i64),
+    Wildcard,
 }
 
 //pub enum AccessStatement<'a>{
@@ -64,13 +197,35 @@ pub enum AccessStatement<'a>{
 //    Format(String)
 //}
 
+// a side of a condition or an argument
+pub enum ValueStatement<'a>{
+    Literal(serde_json::Value),
+    Access(AccessStatement<'a>),
+    Call(&'static functions::Function, Vec<ValueStatement<'a>>),
+    Binary(parser_type::BinaryOperator, Box<ValueStatement<'a>>, Box<ValueStatement<'a>>),
+    Predicate(Box<PredicateStatement<'a>>),
+}
+
+// any(comparison) / all(comparison)
+pub struct PredicateStatement<'a>{
+    quantifier: parser_type::Quantifier,
+    op: parser_type::Operator,
+    pattern: Option<parser_type::Pattern>,
+    left: ValueStatement<'a>,
+    right: ValueStatement<'a>,
+}
+
 pub struct ArgumentStatement<'a>{
     // object key, statement
-    arg_stmt_list: Vec<(String, AccessStatement<'a>)>
+    arg_stmt_list: Vec<(String, ValueStatement<'a>)>
 }
 
-pub struct ConditionStatement<'a>{
-    pub cond_stmt_list: Vec<(Option<AccessStatement<'a>>, Option<AccessStatement<'a>>)>,
+// statements of a condition expression, in the same shape as the expression
+pub enum ConditionStatement<'a>{
+    Compare((Option<ValueStatement<'a>>, Option<ValueStatement<'a>>)),
+    Not(Box<ConditionStatement<'a>>),
+    And(Box<ConditionStatement<'a>>, Box<ConditionStatement<'a>>),
+    Or(Box<ConditionStatement<'a>>, Box<ConditionStatement<'a>>),
 }
 
 impl<'a> Statement<'a>{
@@ -86,7 +241,11 @@ impl<'a> Statement<'a>{
         //}
         let mut rows = self.stmt.query_map(param, |row| {
             match row.get_ref(0)? {
-                rusqlite::types::ValueRef::Text(text) => Ok(serde_json::Value::String(String::from_utf8_lossy(text).into_owned())),
+                // access statements select json text (`->`, json_quote, json_group_array)
+                rusqlite::types::ValueRef::Text(text) => {
+                    let text: std::borrow::Cow<str> = String::from_utf8_lossy(text);
+                    Ok(serde_json::from_str(&text).unwrap_or_else(|_| serde_json::Value::String(text.into_owned())))
+                },
                 rusqlite::types::ValueRef::Integer(int) => Ok(serde_json::Value::Number(int.into())),
                 rusqlite::types::ValueRef::Real(float) => Ok(serde_json::Value::Number(serde_json::Number::from_f64(float).unwrap())),
                 rusqlite::types::ValueRef::Blob(blob) => {
@@ -109,6 +268,24 @@ impl<'a> Statement<'a>{
         self.stmt.execute(param)?;
         Ok(())
     }
+
+    fn query_exists<P: Params>(&mut self, param: P) -> Result<bool, Error> {
+        Ok(self.stmt.exists(param)?)
+    }
+
+    fn query_id<P: Params>(&mut self, param: P) -> Result<Option<i64>, Error> {
+        Ok(self.stmt.query_row(param, |row| row.get(0)).optional()?)
+    }
+
+    fn query_ids<P: Params>(&mut self, param: P) -> Result<Vec<i64>, Error> {
+        let ids = self.stmt.query_map(param, |row| row.get(0))?;
+        Ok(ids.collect::<rusqlite::Result<Vec<i64>>>()?)
+    }
+
+    // returns the rowid of the inserted row
+    fn insert_row<P: Params>(&mut self, param: P) -> Result<i64, Error> {
+        Ok(self.stmt.insert(param)?)
+    }
 }
 
 impl<'a, 'b> SelectAnalyzerStatement<'a, 'b>{
@@ -117,17 +294,11 @@ impl<'a, 'b> SelectAnalyzerStatement<'a, 'b>{
     }
 
 
-    pub fn process_analyzer_stmt(&mut self, analyzer_name: &str, conditions: Option<&Vec<conditions::Condition>>) -> Result<()> {
+    pub fn process_analyzer_stmt(&mut self, analyzer_name: &str, conditions: Option<&conditions::Expression>) -> Result<()> {
         let (argument_stmt, cond_stmt_opt) = self.stmt.get_mut(analyzer_name).ok_or_else(|| Error::NotAnalyzerNameInDataBase(analyzer_name.to_string()))?;
         match (conditions, cond_stmt_opt) {
             (Some(conditions), Some(cond_stmt)) => {
-                if conditions.len() != cond_stmt.cond_stmt_list.len() {
-                    return Err(Error::DiffCondAndCondStmt());
-                }
-                for (cond, stmt) in conditions.iter().zip(&mut cond_stmt.cond_stmt_list) {
-                    //cond.is_match_condition(stmt)?; 
-                    stmt.is_match_condition(cond)?;
-                }
+                cond_stmt.is_match_condition(conditions)?;
             }
             (None, None) => {}
             _ => return Err(Error::DiffCondAndCondStmt()),
@@ -141,7 +312,7 @@ pub trait IsMatchCodition{
     fn is_match_condition(&mut self, config: &Condition) -> Result<bool, Error>;
 }
 
-impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatement<'a>>) {
+impl<'a> IsMatchCodition for (Option<ValueStatement<'a>>, Option<ValueStatement<'a>>) {
     fn is_match_condition(&mut self, cond: &Condition) -> Result<bool, Error>{
     //pub fn is_match_condition<'a>(&self, select_stmt: &'a mut (Option<AccessStatement<'a>>, Option<AccessStatement<'a>>)) -> Result<bool, Error>{
         let mut left_json: serde_json::Value;
@@ -149,9 +320,8 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
         match cond {
             Condition { 
                 left: Value::Literal(left_lit), 
-                op, 
                 right: Value::Literal(right_lit), 
-                chain 
+                ..
             } => 
             {
                 if self.0.is_some() || self.1.is_some(){
@@ -159,13 +329,12 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                 }
                 left_json = left_lit.into();
                 right_json = right_lit.into();
-                Ok( json_compare(&left_json, &op, &right_json)? )
+                Ok( condition_compare(cond, &left_json, &right_json)? )
             },
             Condition { 
                 left: parser_type::Value::Literal(left_lit), 
-                op, 
-                right: parser_type::Value::Access(right_acc), 
-                chain 
+                right: parser_type::Value::Access(_) | parser_type::Value::Call(_) | parser_type::Value::Binary(_) | parser_type::Value::Predicate(_), 
+                ..
             } => 
             {
                 if self.0.is_some() || self.1.is_none(){
@@ -181,7 +350,7 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                         match stmt.to_json()?{
                             Some(json) => {
                                 right_json = json;
-                                Ok( json_compare(&left_json, &op, &right_json)? )
+                                Ok( condition_compare(cond, &left_json, &right_json)? )
                             }
                             None => {
                                 return Ok(false);
@@ -194,10 +363,9 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                 }
             },
             Condition { 
-                left: parser_type::Value::Access(left_acc), 
-                op, 
+                left: parser_type::Value::Access(_) | parser_type::Value::Call(_) | parser_type::Value::Binary(_) | parser_type::Value::Predicate(_), 
                 right: parser_type::Value::Literal(right_lit), 
-                chain 
+                ..
             } => 
             {
                 right_json = right_lit.into();
@@ -206,7 +374,7 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                         match stmt.to_json()?{
                             Some(json) => {
                                 left_json = json;
-                                Ok( json_compare(&left_json, &op, &right_json)? )
+                                Ok( condition_compare(cond, &left_json, &right_json)? )
                             }
                             None => {
                                 return Ok(false);
@@ -219,10 +387,9 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                 }
             },
             Condition { 
-                left: parser_type::Value::Access(left_acc), 
-                op, 
-                right: parser_type::Value::Access(right_acc), 
-                chain 
+                left: parser_type::Value::Access(_) | parser_type::Value::Call(_) | parser_type::Value::Binary(_) | parser_type::Value::Predicate(_), 
+                right: parser_type::Value::Access(_) | parser_type::Value::Call(_) | parser_type::Value::Binary(_) | parser_type::Value::Predicate(_), 
+                ..
             } => 
             {
                 if self.0.is_none() || self.1.is_none(){
@@ -234,7 +401,7 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
                             (Some(json_left), Some(json_right)) => {
                                 left_json = json_left;
                                 right_json = json_right;
-                                Ok( json_compare(&left_json, &op, &right_json)? )
+                                Ok( condition_compare(cond, &left_json, &right_json)? )
                             }
                             _ => {
                                 return Ok(false);
@@ -251,24 +418,36 @@ impl<'a> IsMatchCodition for (Option<AccessStatement<'a>>, Option<AccessStatemen
 }
 
 impl<'a> ConditionStatement<'a> {
-    pub fn is_match_condition(&mut self, conditions: &Vec<conditions::Condition>) -> Result<bool, Error> {
-        if conditions.len() != self.cond_stmt_list.len() {
-            return Err(Error::DiffCondAndCondStmt());
-        }
-
-        for (cond, mut stmt) in conditions.iter().zip(self.cond_stmt_list.iter_mut()) {
-            if !stmt.is_match_condition(&cond)? {
-                return Ok(false);
+    // `and` and `or` short-circuit: the right side is not queried when the left side decides the result
+    pub fn is_match_condition(&mut self, expression: &conditions::Expression) -> Result<bool, Error> {
+        match (self, expression) {
+            (ConditionStatement::Compare(stmt), conditions::Expression::Compare(cond)) => {
+                stmt.is_match_condition(cond)
+            }
+            (ConditionStatement::Not(stmt), conditions::Expression::Not(expr)) => {
+                Ok(!stmt.is_match_condition(expr)?)
+            }
+            (ConditionStatement::And(left_stmt, right_stmt), conditions::Expression::And(left_expr, right_expr)) => {
+                Ok(left_stmt.is_match_condition(left_expr)? && right_stmt.is_match_condition(right_expr)?)
             }
+            (ConditionStatement::Or(left_stmt, right_stmt), conditions::Expression::Or(left_expr, right_expr)) => {
+                Ok(left_stmt.is_match_condition(left_expr)? || right_stmt.is_match_condition(right_expr)?)
+            }
+            _ => Err(Error::DiffCondAndCondStmt()),
         }
-
-        Ok(true)
     }
 
     pub fn set_placeholder(&mut self, result_id_for_place_holder: i64) -> Result<()>{
-        for (left_opt, right_opt) in self.cond_stmt_list.iter_mut() {
-            left_opt.as_mut().map(|left| left.set_placeholder(result_id_for_place_holder)).transpose()?;
-            right_opt.as_mut().map(|right| right.set_placeholder(result_id_for_place_holder)).transpose()?;
+        match self {
+            ConditionStatement::Compare((left_opt, right_opt)) => {
+                left_opt.as_mut().map(|left| left.set_placeholder(result_id_for_place_holder)).transpose()?;
+                right_opt.as_mut().map(|right| right.set_placeholder(result_id_for_place_holder)).transpose()?;
+            }
+            ConditionStatement::Not(stmt) => stmt.set_placeholder(result_id_for_place_holder)?,
+            ConditionStatement::And(left, right) | ConditionStatement::Or(left, right) => {
+                left.set_placeholder(result_id_for_place_holder)?;
+                right.set_placeholder(result_id_for_place_holder)?;
+            }
         }
         Ok(())
     }
@@ -307,8 +486,35 @@ impl<'a> ArgumentStatement<'a> {
 
 
 impl<'a, 'b> InsertAnalyerStatement<'a, 'b> {
-    pub fn insert_path(&mut self, path: &std::path::Path) -> Result<bool, Error>{
-        self.result.execute_insert([path.to_string_lossy().as_ref()])?;
+    // returns the result id of the inserted path
+    pub fn insert_path(&mut self, path: &std::path::Path, metadata: &FileMetadata, origin: &FileOrigin) -> Result<i64, Error>{
+        self.result.insert_row(rusqlite::params![
+            path.to_string_lossy().as_ref(),
+            metadata.size,
+            metadata.mode,
+            metadata.uid,
+            metadata.gid,
+            metadata.file_type,
+            metadata.link_target,
+            metadata.mtime,
+            metadata.sha256,
+            metadata.mime,
+            origin.image_id,
+            origin.parent_id,
+        ])
+    }
+
+    // Returns the id of the image. The same image of a previous run is reused, so resuming does not record it twice.
+    pub fn insert_image(&mut self, image: &ImageRecord) -> Result<i64, Error>{
+        let params = rusqlite::params![image.parent_id, image.result_id, image.path, image.origin, image.sha256, image.error];
+        if let Some(id) = self.find_image.query_id(params)? {
+            return Ok(id);
+        }
+        self.image.insert_row(params)
+    }
+
+    pub fn insert_error(&mut self, error: &AnalyzerErrorRecord) -> Result<bool, Error>{
+        self.error.execute_insert(rusqlite::params![error.result_id, error.analyzer, error.kind, error.message, error.stderr, error.exit_code, error.signal])?;
         Ok(true)
     }
 
@@ -328,6 +534,55 @@ impl<'a, 'b> InsertAnalyerStatement<'a, 'b> {
 
 
 
+impl<'a, 'b> ResumeStatement<'a, 'b> {
+    // the result id of the file and the metadata recorded with it
+    pub fn find_path(&mut self, path: &std::path::Path) -> Result<Option<(i64, FileMetadata)>, Error>{
+        let row = self.path.stmt.query_row([path.to_string_lossy().as_ref()], |row| Ok((row.get(0)?, file_metadata(row, 1)?)));
+        Ok(row.optional()?)
+    }
+
+    // the files emitted from a file by an analyzer, `file!analyzer/`, and the files emitted from them in turn
+    pub fn find_emitted(&mut self, prefix: &str) -> Result<Vec<(i64, std::path::PathBuf, FileMetadata)>, Error>{
+        let rows = self.emitted.stmt.query_map([prefix], |row| {
+            Ok((row.get(0)?, std::path::PathBuf::from(row.get::<_, String>(1)?), file_metadata(row, 2)?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<(i64, std::path::PathBuf, FileMetadata)>>>()?)
+    }
+
+    pub fn has_result(&mut self, analyzer_name: &str, result_id: i64) -> Result<bool, Error>{
+        match self.analyzer.get_mut(analyzer_name) {
+            Some(stmt) => stmt.query_exists([result_id]),
+            None => Err(Error::NoAnalyzerName()),
+        }
+    }
+
+    // removes the result of an analyzer for a file, before it is computed again
+    pub fn forget_result(&mut self, analyzer_name: &str, result_id: i64) -> Result<(), Error>{
+        match self.forget.get_mut(analyzer_name) {
+            Some(stmt) => {
+                stmt.stmt.execute([result_id])?;
+                Ok(())
+            }
+            None => Err(Error::NoAnalyzerName()),
+        }
+    }
+}
+
+// the FILE_COLUMNS of a row, from the column `first`
+fn file_metadata(row: &rusqlite::Row, first: usize) -> rusqlite::Result<FileMetadata> {
+    Ok(FileMetadata {
+        size: row.get(first)?,
+        mode: row.get(first + 1)?,
+        uid: row.get(first + 2)?,
+        gid: row.get(first + 3)?,
+        file_type: row.get(first + 4)?,
+        link_target: row.get(first + 5)?,
+        mtime: row.get(first + 6)?,
+        sha256: row.get(first + 7)?,
+        mime: row.get(first + 8)?,
+    })
+}
+
 impl<'a> Transaction<'a> {
 
 
@@ -348,7 +603,12 @@ impl<'a> Transaction<'a> {
     }
 
     pub fn create_insert_result_stmt(&'a self) -> Result<Statement<'a>> {
-        return self.prepare(&"INSERT INTO result (path) VALUES (?1)");
+        self.prepare("INSERT INTO result (path, size, mode, uid, gid, file_type, link_target, mtime, sha256, mime, image_id, parent_id)
+                      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)")
+    }
+
+    pub fn create_insert_error_stmt(&'a self) -> Result<Statement<'a>> {
+        self.prepare("INSERT INTO analyzer_error (result_id, analyzer, kind, message, stderr, exit_code, signal) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")
     }
 
     pub fn create_insert_analyzer_stmt<'b>(&'a self, config: &'b Config) -> Result<std::collections::HashMap<&'b str, Statement<'a>>> {
@@ -356,7 +616,7 @@ impl<'a> Transaction<'a> {
         for analyzer in &config.analyzer {
             analyzer_list.insert(
                 analyzer.name.as_str(),
-                self.prepare(format!("INSERT INTO {} (result_id, value) VALUES (?1, ?2)", analyzer.name).as_str())?
+                self.prepare(format!("INSERT INTO {} (result_id, value) VALUES (?1, ?2)", quote_identifier(&analyzer.name)).as_str())?
             );
         }
         Ok(analyzer_list)
@@ -365,10 +625,48 @@ impl<'a> Transaction<'a> {
     pub fn insert_stmt<'b>(&'a self, config: &'b Config) -> Result<InsertAnalyerStatement<'a, 'b>>{
         Ok(InsertAnalyerStatement{
             result: self.create_insert_result_stmt()?,
+            error: self.create_insert_error_stmt()?,
+            image: self.prepare("INSERT INTO image (parent_id, result_id, path, origin, sha256, error) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?,
+            find_image: self.prepare("SELECT id FROM image
+                                      WHERE parent_id IS ?1 AND result_id IS ?2 AND path = ?3 AND origin = ?4 AND sha256 IS ?5 AND error IS ?6
+                                      ORDER BY id LIMIT 1")?,
             analyzer: self.create_insert_analyzer_stmt(config)?,
         })
     }
 
+    pub fn resume_stmt<'b>(&'a self, config: &'b Config) -> Result<ResumeStatement<'a, 'b>>{
+        let mut analyzer_list = std::collections::HashMap::new();
+        let mut forget_list = std::collections::HashMap::new();
+        for analyzer in &config.analyzer {
+            analyzer_list.insert(
+                analyzer.name.as_str(),
+                self.prepare(format!("SELECT 1 FROM {} WHERE result_id = ?1", quote_identifier(&analyzer.name)).as_str())?
+            );
+            forget_list.insert(
+                analyzer.name.as_str(),
+                self.prepare(format!("DELETE FROM {} WHERE result_id = ?1", quote_identifier(&analyzer.name)).as_str())?
+            );
+        }
+        Ok(ResumeStatement{
+            path: self.prepare("SELECT id, size, mode, uid, gid, file_type, link_target, mtime, sha256, mime FROM result WHERE path = ?1 ORDER BY id LIMIT 1")?,
+            emitted: self.prepare("SELECT id, path, size, mode, uid, gid, file_type, link_target, mtime, sha256, mime FROM result
+                                   WHERE substr(path, 1, length(?1)) = ?1 ORDER BY id")?,
+            analyzer: analyzer_list,
+            forget: forget_list,
+        })
+    }
+
+    // forgets the results of an analyzer, so that it is run again on every file
+    pub fn delete_analyzer_results(&self, analyzer_name: &str) -> Result<()> {
+        self.tx.execute(&format!("DELETE FROM {}", quote_identifier(analyzer_name)), [])?;
+        Ok(())
+    }
+
+    pub fn delete_analyzer_errors(&self, analyzer_name: &str) -> Result<()> {
+        self.tx.execute("DELETE FROM analyzer_error WHERE analyzer = ?1", [analyzer_name])?;
+        Ok(())
+    }
+
     pub fn select_stmt<'b>(&'a self, config: &'b Config) -> Result<SelectAnalyzerStatement<'a, 'b>>{
         let mut rst: std::collections::HashMap<&'b str, (ArgumentStatement<'a>, Option<ConditionStatement<'a>>)>
             = std::collections::HashMap::new();
@@ -379,14 +677,14 @@ impl<'a> Transaction<'a> {
     }
 
     pub fn argument_stmt<'b>(&'a self, opt_arg_list: &'b Option<Vec<arguments::Argument>>) -> Result<ArgumentStatement<'a>>{
-        let mut arg_stmt: Vec<(String, AccessStatement)> = Vec::new();
+        let mut arg_stmt: Vec<(String, ValueStatement)> = Vec::new();
         let value_stmt: AccessStatement = Access{base: "path".to_string(), path: None}.generate_stmt(self)?;
-        arg_stmt.push(("filename".to_string() , value_stmt));
+        arg_stmt.push(("filename".to_string() , ValueStatement::Access(value_stmt)));
         match opt_arg_list{
             Some(arg_list) =>  {
                 let mut cnt: u64 = 1;
                 for arg in arg_list {
-                    let value_stmt: AccessStatement = arg.generate_stmt(self)?;
+                    let value_stmt: ValueStatement = arg.value.generate_value_stmt(self)?;
                     arg_stmt.push((format!("argument{}", cnt) , value_stmt));
                     cnt += 1;
                 }
@@ -397,20 +695,26 @@ impl<'a> Transaction<'a> {
         Ok(ArgumentStatement{arg_stmt_list: arg_stmt})
     }
 
-    pub fn condition_stmt<'b>(&'a self, opt_vec_cond: &'b Option<Vec<conditions::Condition>>) -> Result<Option<ConditionStatement<'a>>>{
-        let mut cond_stmt: Vec<(Option<AccessStatement>, Option<AccessStatement>)> = Vec::new();
-        match opt_vec_cond{
-            Some(vec_cond) =>  {
-                for cond in vec_cond {
-                    let value_stmt: (Option<AccessStatement>, Option<AccessStatement>) = cond.generate_stmt_condition(self)?;
-                    cond_stmt.push(value_stmt);
-                }
-            }
-            None => {
-                return Ok(None);
-            }
+    pub fn condition_stmt<'b>(&'a self, opt_expression: &'b Option<conditions::Expression>) -> Result<Option<ConditionStatement<'a>>>{
+        match opt_expression{
+            Some(expression) => Ok(Some(self.expression_stmt(expression)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn expression_stmt(&'a self, expression: &conditions::Expression) -> Result<ConditionStatement<'a>>{
+        match expression {
+            conditions::Expression::Compare(cond) => Ok(ConditionStatement::Compare(cond.generate_stmt_condition(self)?)),
+            conditions::Expression::Not(expr) => Ok(ConditionStatement::Not(Box::new(self.expression_stmt(expr)?))),
+            conditions::Expression::And(left, right) => Ok(ConditionStatement::And(
+                Box::new(self.expression_stmt(left)?),
+                Box::new(self.expression_stmt(right)?),
+            )),
+            conditions::Expression::Or(left, right) => Ok(ConditionStatement::Or(
+                Box::new(self.expression_stmt(left)?),
+                Box::new(self.expression_stmt(right)?),
+            )),
         }
-        Ok(Some(ConditionStatement{cond_stmt_list: cond_stmt}))
     }
 
 }
@@ -431,22 +735,83 @@ impl DatabaseT{
     }
 
     pub fn create_result_table(&self) -> Result<()> {
-        self.conn.execute( &"CREATE TABLE result (
+        self.conn.execute( "CREATE TABLE IF NOT EXISTS result (
                                 id INTEGER PRIMARY KEY AUTOINCREMENT,
-                                path TEXT
+                                path TEXT,
+                                sha256 TEXT
                             )", 
                         [] )?;
+        // databases created by an older version are resumed with the new columns added
+        for (column, column_type) in FILE_COLUMNS {
+            self.ensure_column("result", column, column_type)?;
+        }
+        self.ensure_column("result", "image_id", "INTEGER")?;
+        self.ensure_column("result", "parent_id", "INTEGER")?;
+        Ok(())
+    }
+
+    // images unpacked before or during the analysis, see extract. origin is the extractor that unpacked the image.
+    pub fn create_image_table(&self) -> Result<()> {
+        self.conn.execute( "CREATE TABLE IF NOT EXISTS image (
+                                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                                parent_id INTEGER,
+                                path TEXT,
+                                origin TEXT,
+                                sha256 TEXT
+                            )",
+                        [] )?;
+        self.ensure_column("image", "result_id", "INTEGER")?;
+        self.ensure_column("image", "error", "TEXT")?;
+        Ok(())
+    }
+
+    fn ensure_column(&self, table: &str, column: &str, column_type: &str) -> Result<()> {
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+            [table, column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            self.conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", quote_identifier(table), quote_identifier(column), column_type), [])?;
+        }
         Ok(())
     }
     
+    pub fn has_results(&self) -> Result<bool> {
+        Ok(self.conn.query_row("SELECT EXISTS (SELECT 1 FROM result)", [], |row| row.get(0))?)
+    }
+
+    // whether an analyzer emitted files in a previous run: they are named `file!analyzer/name`
+    pub fn has_emitted(&self, analyzer_name: &str) -> Result<bool> {
+        Ok(self.conn.query_row("SELECT EXISTS (SELECT 1 FROM result WHERE instr(path, ?1) > 0)", [format!("!{}/", analyzer_name)], |row| row.get(0))?)
+    }
+
+    // analyzers that failed on a file. the run goes on and the failure is recorded here.
+    pub fn create_analyzer_error_table(&self) -> Result<()> {
+        self.conn.execute( "CREATE TABLE IF NOT EXISTS analyzer_error (
+                                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                                result_id INTEGER,
+                                analyzer TEXT,
+                                kind TEXT,
+                                message TEXT,
+                                stderr TEXT
+                            )", 
+                        [] )?;
+        // the exit status of an analyzer run in a child process
+        self.ensure_column("analyzer_error", "exit_code", "INTEGER")?;
+        // the signal that killed it
+        self.ensure_column("analyzer_error", "signal", "INTEGER")?;
+        Ok(())
+    }
+
     pub fn create_analyzer_table(&self, config: &Config) -> Result<()> {
         for analyzer in &config.analyzer {
-            self.conn.execute( &format!("CREATE TABLE {} (
+            self.conn.execute( &format!("CREATE TABLE IF NOT EXISTS {} (
                                             id INTEGER PRIMARY KEY AUTOINCREMENT,
                                             result_id INTEGER,
                                             value JSON
                                             )",
-                                    &analyzer.name
+                                    quote_identifier(&analyzer.name)
                                 ), 
                                 [])?;
         }
@@ -455,7 +820,154 @@ impl DatabaseT{
     
 }
 
+impl<'a> ValueStatement<'a> {
+    pub fn to_json(&mut self) -> Result<Option<serde_json::Value>, Error> {
+        match self {
+            ValueStatement::Literal(value) => Ok(Some(value.clone())),
+            ValueStatement::Access(stmt) => stmt.to_json(),
+            // a missing argument is passed as null, and a null result is handled like a missing value. so is
+            // the result of a function failing on the data of the file, e.g. int("abc") or lower(1)
+            ValueStatement::Call(function, args) => {
+                let mut values: Vec<serde_json::Value> = Vec::with_capacity(args.len());
+                for arg in args.iter_mut() {
+                    values.push(arg.to_json()?.unwrap_or(serde_json::Value::Null));
+                }
+                let value: serde_json::Value = function.call(&values).unwrap_or(serde_json::Value::Null);
+                if value.is_null() {
+                    Ok(None)
+                }
+                else {
+                    Ok(Some(value))
+                }
+            }
+            // division by zero, an overflow or an operand other than a number gives a missing value too
+            ValueStatement::Binary(op, left, right) => {
+                let left: serde_json::Value = left.to_json()?.unwrap_or(serde_json::Value::Null);
+                let right: serde_json::Value = right.to_json()?.unwrap_or(serde_json::Value::Null);
+                let value: serde_json::Value = op.apply(&left, &right).unwrap_or(serde_json::Value::Null);
+                if value.is_null() {
+                    Ok(None)
+                }
+                else {
+                    Ok(Some(value))
+                }
+            }
+            ValueStatement::Predicate(predicate) => Ok(Some(serde_json::Value::Bool(predicate.is_match()?))),
+        }
+    }
+
+    // whether the value contains a [*] projection, whose elements any() / all() compare one by one
+    fn is_projection(&self) -> bool {
+        match self {
+            ValueStatement::Literal(_) | ValueStatement::Predicate(_) => false,
+            ValueStatement::Access(stmt) => stmt.is_projection(),
+            ValueStatement::Call(_, args) => args.iter().any(|arg| arg.is_projection()),
+            ValueStatement::Binary(_, left, right) => left.is_projection() || right.is_projection(),
+        }
+    }
+
+    // one value per element of the projections in the value. a function or an operator is applied
+    // to every combination of the elements of its operands
+    fn each_json(&mut self) -> Result<Vec<Option<serde_json::Value>>, Error> {
+        if !self.is_projection() {
+            return Ok(vec![self.to_json()?]);
+        }
+        match self {
+            ValueStatement::Access(stmt) => match stmt.to_json()? {
+                Some(serde_json::Value::Array(items)) => Ok(items.into_iter().map(Some).collect()),
+                other => Ok(vec![other]),
+            },
+            ValueStatement::Call(function, args) => {
+                let mut combinations: Vec<Vec<serde_json::Value>> = vec![Vec::new()];
+                for arg in args.iter_mut() {
+                    let values: Vec<Option<serde_json::Value>> = arg.each_json()?;
+                    let mut next: Vec<Vec<serde_json::Value>> = Vec::with_capacity(combinations.len() * values.len());
+                    for combination in &combinations {
+                        for value in &values {
+                            let mut combination: Vec<serde_json::Value> = combination.clone();
+                            combination.push(value.clone().unwrap_or(serde_json::Value::Null));
+                            next.push(combination);
+                        }
+                    }
+                    combinations = next;
+                }
+                let mut results: Vec<Option<serde_json::Value>> = Vec::with_capacity(combinations.len());
+                for combination in &combinations {
+                    let value: serde_json::Value = function.call(combination).unwrap_or(serde_json::Value::Null);
+                    results.push(Some(value).filter(|value| !value.is_null()));
+                }
+                Ok(results)
+            }
+            ValueStatement::Binary(op, left, right) => {
+                let lefts: Vec<Option<serde_json::Value>> = left.each_json()?;
+                let rights: Vec<Option<serde_json::Value>> = right.each_json()?;
+                let mut results: Vec<Option<serde_json::Value>> = Vec::with_capacity(lefts.len() * rights.len());
+                for left in &lefts {
+                    for right in &rights {
+                        let left: &serde_json::Value = left.as_ref().unwrap_or(&serde_json::Value::Null);
+                        let right: &serde_json::Value = right.as_ref().unwrap_or(&serde_json::Value::Null);
+                        let value: serde_json::Value = op.apply(left, right).unwrap_or(serde_json::Value::Null);
+                        results.push(Some(value).filter(|value| !value.is_null()));
+                    }
+                }
+                Ok(results)
+            }
+            ValueStatement::Literal(_) | ValueStatement::Predicate(_) => Ok(vec![self.to_json()?]),
+        }
+    }
+
+    pub fn set_placeholder(&mut self, result_id_for_placeholder: i64) -> Result<(), Error> {
+        match self {
+            ValueStatement::Literal(_) => Ok(()),
+            ValueStatement::Access(stmt) => stmt.set_placeholder(result_id_for_placeholder),
+            ValueStatement::Call(_, args) => {
+                for arg in args.iter_mut() {
+                    arg.set_placeholder(result_id_for_placeholder)?;
+                }
+                Ok(())
+            }
+            ValueStatement::Binary(_, left, right) => {
+                left.set_placeholder(result_id_for_placeholder)?;
+                right.set_placeholder(result_id_for_placeholder)
+            }
+            ValueStatement::Predicate(predicate) => {
+                predicate.left.set_placeholder(result_id_for_placeholder)?;
+                predicate.right.set_placeholder(result_id_for_placeholder)
+            }
+        }
+    }
+}
+
+impl<'a> PredicateStatement<'a> {
+    // any() is false and all() is true when there is no element. a missing value never matches
+    fn is_match(&mut self) -> Result<bool, Error> {
+        let lefts: Vec<Option<serde_json::Value>> = self.left.each_json()?;
+        let rights: Vec<Option<serde_json::Value>> = self.right.each_json()?;
+        for left in &lefts {
+            for right in &rights {
+                let matched: bool = match (left, right) {
+                    (Some(left), Some(right)) => compare(&self.op, self.pattern.as_ref(), left, right)?,
+                    _ => false,
+                };
+                match self.quantifier {
+                    parser_type::Quantifier::Any if matched => return Ok(true),
+                    parser_type::Quantifier::All if !matched => return Ok(false),
+                    _ => {},
+                }
+            }
+        }
+        Ok(self.quantifier == parser_type::Quantifier::All)
+    }
+}
+
 impl<'a> AccessStatement<'a> {
+    fn is_projection(&self) -> bool {
+        match self {
+            AccessStatement::Stmt(_, _) => false,
+            AccessStatement::Format(format) => format.is_projection(),
+        }
+    }
+
     pub fn to_json(&mut self) -> Result<Option<serde_json::Value>, Error> {
         match self {
             AccessStatement::Stmt(stmt, bind_req) => {
@@ -466,19 +978,21 @@ impl<'a> AccessStatement<'a> {
                     BindRequirement::Provided(values) => {
                         values.iter().map(|val| match val {
                             BindType::ResultId(i) => i as &dyn rusqlite::ToSql,
+                            BindType::JsonPath(path) => path as &dyn rusqlite::ToSql,
                         }).collect()
                     }
                 };
 
                 stmt.query_map_json(&bind_values[..])
             },
-            AccessStatement::Format(_) => {
-                Err(Error::UnimplementedError())
-            }
+            AccessStatement::Format(format) => format.query(),
         }
     }
 
     pub fn set_placeholder(&mut self, result_id_for_placeholder: i64) -> Result<(), Error> {
+        if let AccessStatement::Format(format) = self {
+            return format.set_placeholder(result_id_for_placeholder);
+        }
         if let AccessStatement::Stmt(_, bind_req) = self {
             match bind_req {
                 BindRequirement::Required(types) => {
@@ -487,6 +1001,7 @@ impl<'a> AccessStatement<'a> {
                             BindType::ResultId(_) => {
                                 *btype = BindType::ResultId(result_id_for_placeholder);
                             },
+                            BindType::JsonPath(_) => {},
                         }
                     }
                     *bind_req = BindRequirement::Provided(types.clone());
@@ -497,6 +1012,7 @@ impl<'a> AccessStatement<'a> {
                             BindType::ResultId(_) => {
                                 *value = BindType::ResultId(result_id_for_placeholder);
                             },
+                            BindType::JsonPath(_) => {},
                         }
                     }
                 }
@@ -512,63 +1028,100 @@ trait GenerateStmt {
     fn generate_stmt<'a>(&self, tx: &'a Transaction<'a>) -> Result<AccessStatement<'a>>;
 }
 
-impl GenerateStmt for arguments::Argument {
-    fn generate_stmt<'a>(&self, tx: &'a Transaction<'a>) -> Result<AccessStatement<'a>>{
-        return self.value.generate_stmt(tx);
-    }
-}
-
 trait GetStatementCondition {
-    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<(Option<AccessStatement<'a>>, Option<AccessStatement<'a>>)>;
+    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<(Option<ValueStatement<'a>>, Option<ValueStatement<'a>>)>;
 }
 
 impl GetStatementCondition for conditions::Condition{
-    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<(Option<AccessStatement<'a>>, Option<AccessStatement<'a>>)> {
+    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<(Option<ValueStatement<'a>>, Option<ValueStatement<'a>>)> {
         return Ok((self.left.generate_stmt_condition(tx)?, self.right.generate_stmt_condition(tx)?));
     }
 }
 
 trait GetStatementValue {
-    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<Option<AccessStatement<'a>>>;
+    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<Option<ValueStatement<'a>>>;
+    fn generate_value_stmt<'a>(&self, tx: &'a Transaction<'a>) -> Result<ValueStatement<'a>>;
 }
 
 
 impl GetStatementValue for parser_type::Value{
-    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<Option<AccessStatement<'a>>> {
+    // literal sides of a condition are compared directly and have no statement
+    fn generate_stmt_condition<'a>(&self, tx: &'a Transaction<'a>) -> Result<Option<ValueStatement<'a>>> {
         match self{
             Value::Literal(_) => Ok(None),
-            Value::Access(ac) => Ok(Some(ac.generate_stmt(tx)?))
+            _ => Ok(Some(self.generate_value_stmt(tx)?)),
+        }
+    }
+
+    fn generate_value_stmt<'a>(&self, tx: &'a Transaction<'a>) -> Result<ValueStatement<'a>> {
+        match self{
+            Value::Literal(lit) => Ok(ValueStatement::Literal(lit.into())),
+            Value::Access(ac) => Ok(ValueStatement::Access(ac.generate_stmt(tx)?)),
+            Value::Call(call) => {
+                let function: &'static functions::Function = functions::lookup(&call.name)
+                    .ok_or_else(|| Error::FunctionError(format!("unknown function {}()", call.name)))?;
+                let mut args: Vec<ValueStatement> = Vec::with_capacity(call.args.len());
+                for arg in &call.args {
+                    args.push(arg.generate_value_stmt(tx)?);
+                }
+                Ok(ValueStatement::Call(function, args))
+            }
+            Value::Binary(binary) => Ok(ValueStatement::Binary(
+                binary.op,
+                Box::new(binary.left.generate_value_stmt(tx)?),
+                Box::new(binary.right.generate_value_stmt(tx)?),
+            )),
+            Value::Predicate(predicate) => Ok(ValueStatement::Predicate(Box::new(PredicateStatement{
+                quantifier: predicate.quantifier,
+                op: predicate.condition.op,
+                pattern: predicate.condition.pattern.clone(),
+                left: predicate.condition.left.generate_value_stmt(tx)?,
+                right: predicate.condition.right.generate_value_stmt(tx)?,
+            }))),
         }
     }
 }
 
 // path -> get current path
 // path[integer] -> select path where id = ?
-// path["aaaa"] -> error
-// feature -> path["aaaaaa"].analyzer_name -> select value->'$' from analyzer_name where path = "aaaaaa"
-// analyzer_name.aaa.bbb. -> select value->'$aaa.bbb.' from analyzer_name where id = current
+// path["aaaa"] -> select path where path = "aaaa"
+// size, mime, ... -> select size from result where id = current
+// path["aaaaaa"].analyzer_name.aaa -> select value->'$.aaa' from analyzer_name where result_id = (select id from result where path = "aaaaaa")
+// path[*].analyzer_name.aaa -> the array of path[id].analyzer_name.aaa for every file (FormatStatement)
+// analyzer_name.aaa.bbb. -> select value->'$."aaa"."bbb"' from "analyzer_name" where id = current
+// the result of an image analyzer has the result id IMAGE_RESULT_ID and is read from every file
+// analyzer_name.aaa[bbb.ccc] -> the json path is built from bbb.ccc when the statement runs (FormatStatement)
+// analyzer_name.aaa[*].bbb -> select value->'$.aaa', then [*].bbb is taken from the array (FormatStatement)
 // analyzer_name[] -> error
 
-//-------------------------------------
-// there is a sql injection!!
-// take measures against it when parsing configuration strings (e.g., parsing Access variables).
-//------------------------------------
+// analyzer names are checked when the config is loaded and are quoted, and json paths are bound,
+// so nothing of the configuration is interpolated into sql as is.
 impl GenerateStmt for parser_type::Access {
     fn generate_stmt<'a>(&self, tx: &'a Transaction<'a>) -> Result<AccessStatement<'a>>{
         match self {
             // path
             parser_type::Access{ base: base , path: None} if base == "path" => {
-                let sql = "SELECT path FROM result WHERE id = ?1";
+                let sql = "SELECT json_quote(path) FROM result WHERE id = ?1";
                 let latest_path_stmt: Statement = tx.prepare(&sql)?;
                 return Ok(AccessStatement::Stmt(latest_path_stmt, BindRequirement::Required(vec!(BindType::ResultId(0)))));
             },
 
-            parser_type::Access{base: base, path: path} if base == "path" => {
-                return Err(Error::UnimplementedError());
+            // path[...], path[...].analyzer_name...
+            parser_type::Access{base, path: Some(_)} if base == "path" => {
+                return Ok(AccessStatement::Format(Box::new(FormatStatement::generate(self, tx)?)));
+            }
+
+            // size, mime, ...
+            parser_type::Access{base, path} if file_column(base).is_some() => {
+                if path.is_some() {
+                    return Err(Error::FileColumnDoesNotHaveAccess(base.clone()));
+                }
+                return Ok(AccessStatement::Stmt(tx.prepare(&file_column_sql(base, "?1"))?, BindRequirement::Required(vec!(BindType::ResultId(0)))));
             }
 
             parser_type::Access{ base: base , path: None} if base == "pathlist" => {
-                let sql = "SELECT path FROM result;";
+                // every path as one array
+                let sql = "SELECT json_group_array(path) FROM (SELECT path FROM result ORDER BY id);";
                 let path_list_stmt: Statement = tx.prepare(&sql)?;
                 return Ok(AccessStatement::Stmt(path_list_stmt, BindRequirement::Required(vec!())));
             },
@@ -576,12 +1129,16 @@ impl GenerateStmt for parser_type::Access {
             parser_type::Access{ base: base , path: path} if base == "pathlist" => {
                 return Err(Error::PathListDoesNotHaveAcess());
             },
-            
+
+            // analyzer_name.aaa[bbb.ccc], analyzer_name.aaa["key"]
+            parser_type::Access{base: _, path: Some(json_path)} if json_path.iter().any(|p| matches!(p, AccessPath::Index(IndexValue::Access(_) | IndexValue::String(_) | IndexValue::Wildcard))) => {
+                return Ok(AccessStatement::Format(Box::new(FormatStatement::generate(self, tx)?)));
+            }
 
             // base = analyzer_name
             parser_type::Access{base: analyzer_name, path: opt_json_path} => {
                 // json data in an analyzer_name table is stored in `value` column
-                let mut db_json_operator: String = format!("");
+                let mut db_json_operator: String = "$".to_string();
 
                 // first, check if the first path is not contained accesspath::index 
                 // analyzer_name dose not have array
@@ -592,22 +1149,16 @@ impl GenerateStmt for parser_type::Access {
                             match path {
                                 AccessPath::Index(_) => return Err(Error::AnalyzerNameDoesNotHaveAnArray()),
                                 AccessPath::Key(key) => {
-                                    // the json access operator for sqlite is written like `culumn_name->>$.access_key` at the beginning.
-                                    db_json_operator = db_json_operator + &format!(".{}", key);
+                                    // the json access operator for sqlite is written like `culumn_name->$."access_key"` at the beginning.
+                                    // keys of the config are identifiers, so they never contain `"`
+                                    push_json_path_key(&mut db_json_operator, key);
                                 },
                             }
                         }
                         db_json_operator
                     },
                     // if only analyzer_name. get latest json data in analyzer_name table
-                    None => {
-                        let sql = &format!("SELECT analyzer.value->>'$'
-                                                                FROM {} AS analyzer 
-                                                                JOIN result ON analyzer.result_id = result.id
-                                                                WHERE result.id = ?1;", analyzer_name);
-                        let latest_analyzer_stmt: Statement = tx.prepare(sql)?;
-                        return Ok(AccessStatement::Stmt(latest_analyzer_stmt, BindRequirement::Required(vec!(BindType::ResultId(0)))));
-                        }
+                    None => db_json_operator,
                 };
 
                 db_json_operator = match opt_json_path {
@@ -615,17 +1166,16 @@ impl GenerateStmt for parser_type::Access {
                         for p in json_path.iter().skip(1) {
                             match p {
                                 AccessPath::Key(key) => {
-                                    db_json_operator = db_json_operator + &format!(".{}", key);
+                                    push_json_path_key(&mut db_json_operator, key);
                                 },
                                 AccessPath::Index(idx) => {
                                     match idx {
-                                        IndexValue::Access(_) => {
-                                            // in the featuer, IndexValue::Access will be not error. allow object in json array: test1[test2], test1[test2[test3]]
+                                        // handled by FormatStatement
+                                        IndexValue::Access(_) | IndexValue::String(_) | IndexValue::Wildcard => {
                                             return Err(Error::JsonArrayDoesNotHaveOtherThanInt());
                                         },
-                                            // currently, access for json array is only integer.
                                         IndexValue::Int(i) => {
-                                            db_json_operator = db_json_operator + &format!("[{}]", i);
+                                            push_json_path_index(&mut db_json_operator, *i);
                                         },
                                     }
                                 },
@@ -633,20 +1183,282 @@ impl GenerateStmt for parser_type::Access {
                         }
                         db_json_operator
                     }
-                    None => panic!("expected_error!")
+                    None => db_json_operator,
                 };
 
-                let sql = &format!("SELECT analyzer.value->>'${}'
-                                                        FROM {} AS analyzer 
-                                                        JOIN result ON analyzer.result_id = result.id
-                                                        WHERE result.id = ?1", db_json_operator, analyzer_name);
+                let sql = &format!("SELECT analyzer.value->?2
+                                                        FROM {} AS analyzer
+                                                        WHERE analyzer.result_id IN (?1, {})", quote_identifier(analyzer_name), IMAGE_RESULT_ID);
                 let latest_analyzer_stmt: Statement = tx.prepare(sql)?;
-                return Ok(AccessStatement::Stmt(latest_analyzer_stmt, BindRequirement::Required(vec!(BindType::ResultId(0)))));
+                Ok(AccessStatement::Stmt(latest_analyzer_stmt, BindRequirement::Required(vec!(BindType::ResultId(0), BindType::JsonPath(db_json_operator)))))
             }
         }
     }
 }
 
+// a null column is a missing value, like a missing analyzer result
+fn file_column_sql(name: &str, result_id: &str) -> String {
+    let column: String = quote_identifier(name);
+    format!("SELECT json_quote({}) FROM result WHERE id = {} AND {} IS NOT NULL", column, result_id, column)
+}
+
+// ?1 is a result id or the path of a file
+const FORMAT_RESULT_ID: &str = "(CASE WHEN typeof(?1) = 'integer' THEN ?1 ELSE (SELECT id FROM result WHERE path = ?1) END)";
+
+impl<'a> FormatStatement<'a> {
+    fn generate(access: &parser_type::Access, tx: &'a Transaction<'a>) -> Result<FormatStatement<'a>> {
+        let access_path: &[AccessPath] = access.path.as_deref().unwrap_or(&[]);
+
+        // path[file] is followed by nothing or by an analyzer name
+        let (file, analyzer_name, json_path): (FileKey<'a>, Option<&String>, &[AccessPath]) = if access.base == "path" {
+            let (file, rest): (FileKey<'a>, &[AccessPath]) = match access_path.split_first() {
+                Some((AccessPath::Index(index), rest)) => (FileKey::generate(index, tx)?, rest),
+                _ => return Err(Error::InvalidPathAccess()),
+            };
+            match rest.split_first() {
+                None => (file, None, rest),
+                Some((AccessPath::Key(analyzer_name), json_path)) => (file, Some(analyzer_name), json_path),
+                Some((AccessPath::Index(_), _)) => return Err(Error::AnalyzerNameDoesNotHaveAnArray()),
+            }
+        }
+        else {
+            if let Some(AccessPath::Index(_)) = access_path.first() {
+                return Err(Error::AnalyzerNameDoesNotHaveAnArray());
+            }
+            (FileKey::Current(None), Some(&access.base), access_path)
+        };
+
+        match analyzer_name {
+            // path["lib/libc.so.6"].size
+            Some(name) if file_column(name).is_some() => {
+                if !json_path.is_empty() {
+                    return Err(Error::FileColumnDoesNotHaveAccess(name.clone()));
+                }
+                Ok(FormatStatement{ stmt: tx.prepare(&file_column_sql(name, FORMAT_RESULT_ID))?, file, json_path: None })
+            },
+            None => {
+                let sql: String = format!("SELECT json_quote(path) FROM result WHERE id = {}", FORMAT_RESULT_ID);
+                Ok(FormatStatement{ stmt: tx.prepare(&sql)?, file, json_path: None })
+            },
+            Some(analyzer_name) => {
+                let mut parts: Vec<JsonPathPart<'a>> = Vec::with_capacity(json_path.len());
+                for p in json_path {
+                    parts.push(match p {
+                        AccessPath::Key(key) => JsonPathPart::Key(key.clone()),
+                        AccessPath::Index(IndexValue::Int(i)) => JsonPathPart::Index(*i),
+                        AccessPath::Index(IndexValue::String(key)) => JsonPathPart::Key(key.clone()),
+                        AccessPath::Index(IndexValue::Access(index)) => JsonPathPart::Dynamic(index.generate_stmt(tx)?),
+                        AccessPath::Index(IndexValue::Wildcard) => JsonPathPart::Wildcard,
+                    });
+                }
+                let sql: String = format!("SELECT analyzer.value->?2 FROM {} AS analyzer WHERE analyzer.result_id IN ({}, {})", quote_identifier(analyzer_name), FORMAT_RESULT_ID, IMAGE_RESULT_ID);
+                Ok(FormatStatement{ stmt: tx.prepare(&sql)?, file, json_path: Some(parts) })
+            },
+        }
+    }
+
+    // a file or an index that is missing, or of a type other than integer and string, makes the value missing
+    fn query(&mut self) -> Result<Option<serde_json::Value>, Error> {
+        let file: rusqlite::types::Value = match &mut self.file {
+            // files without the value are left out, and projections are flattened like nested [*]
+            FileKey::All(stmt) => {
+                let ids: Vec<i64> = stmt.query_ids([])?;
+                let projection: bool = self.json_path.as_ref().is_some_and(|parts| parts.iter().any(|part| matches!(part, JsonPathPart::Wildcard)));
+                let mut items: Vec<serde_json::Value> = Vec::new();
+                for id in ids {
+                    match self.query_file(rusqlite::types::Value::Integer(id))? {
+                        Some(serde_json::Value::Array(elements)) if projection => items.extend(elements),
+                        Some(value) => items.push(value),
+                        None => {},
+                    }
+                }
+                return Ok(Some(serde_json::Value::Array(items)));
+            },
+            FileKey::Current(Some(id)) | FileKey::Id(id) => rusqlite::types::Value::Integer(*id),
+            FileKey::Current(None) => return Err(Error::BindRequired()),
+            FileKey::Path(path) => rusqlite::types::Value::Text(path.clone()),
+            FileKey::Dynamic(stmt) => match stmt.to_json()? {
+                Some(serde_json::Value::Number(n)) if n.is_i64() => rusqlite::types::Value::Integer(n.as_i64().unwrap_or_default()),
+                Some(serde_json::Value::String(path)) => rusqlite::types::Value::Text(normalize_result_path(&path)),
+                _ => return Ok(None),
+            },
+        };
+        self.query_file(file)
+    }
+
+    fn query_file(&mut self, file: rusqlite::types::Value) -> Result<Option<serde_json::Value>, Error> {
+        let parts: &mut Vec<JsonPathPart<'a>> = match &mut self.json_path {
+            Some(parts) => parts,
+            None => return self.stmt.query_map_json([file]),
+        };
+
+        let mut steps: Vec<JsonPathStep> = Vec::with_capacity(parts.len());
+        for part in parts.iter_mut() {
+            steps.push(match part {
+                JsonPathPart::Key(key) => JsonPathStep::Key(key.clone()),
+                JsonPathPart::Index(i) => JsonPathStep::Index(*i),
+                JsonPathPart::Wildcard => JsonPathStep::Wildcard,
+                JsonPathPart::Dynamic(stmt) => match stmt.to_json()? {
+                    Some(serde_json::Value::Number(n)) if n.is_i64() => JsonPathStep::Index(n.as_i64().unwrap_or_default()),
+                    Some(serde_json::Value::String(key)) => JsonPathStep::Key(key),
+                    _ => return Ok(None),
+                },
+            });
+        }
+
+        // the steps before the first [*] are queried by sqlite, the rest is applied to every element here
+        let split: usize = steps.iter().position(|step| *step == JsonPathStep::Wildcard).unwrap_or(steps.len());
+        let mut json_path: String = "$".to_string();
+        for step in &steps[..split] {
+            match step {
+                JsonPathStep::Key(key) => {
+                    if !push_json_path_key(&mut json_path, key) {
+                        return Ok(None);
+                    }
+                },
+                JsonPathStep::Index(i) => push_json_path_index(&mut json_path, *i),
+                JsonPathStep::Wildcard => {},
+            }
+        }
+        let value: Option<serde_json::Value> = self.stmt.query_map_json(rusqlite::params![file, json_path])?;
+        if split == steps.len() {
+            return Ok(value);
+        }
+        Ok(value.map(|value| {
+            let mut items: Vec<serde_json::Value> = Vec::new();
+            project(value, &steps[split..], &mut items);
+            serde_json::Value::Array(items)
+        }))
+    }
+
+    fn is_projection(&self) -> bool {
+        matches!(self.file, FileKey::All(_)) || self.json_path.as_ref().is_some_and(|parts| parts.iter().any(|part| matches!(part, JsonPathPart::Wildcard)))
+    }
+
+    fn set_placeholder(&mut self, result_id_for_placeholder: i64) -> Result<(), Error> {
+        match &mut self.file {
+            FileKey::Current(id) => *id = Some(result_id_for_placeholder),
+            FileKey::Dynamic(stmt) => stmt.set_placeholder(result_id_for_placeholder)?,
+            FileKey::Id(_) | FileKey::Path(_) | FileKey::All(_) => {},
+        }
+        if let Some(parts) = &mut self.json_path {
+            for part in parts.iter_mut() {
+                if let JsonPathPart::Dynamic(stmt) = part {
+                    stmt.set_placeholder(result_id_for_placeholder)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FileKey<'a> {
+    fn generate(index: &IndexValue, tx: &'a Transaction<'a>) -> Result<FileKey<'a>> {
+        match index {
+            IndexValue::Int(id) => Ok(FileKey::Id(*id)),
+            IndexValue::String(path) => Ok(FileKey::Path(normalize_result_path(path))),
+            IndexValue::Access(access) => Ok(FileKey::Dynamic(access.generate_stmt(tx)?)),
+            IndexValue::Wildcard => Ok(FileKey::All(tx.prepare("SELECT id FROM result ORDER BY id")?)),
+        }
+    }
+}
+
+// [*] takes every element of an array or every value of an object, and nested [*] are flattened.
+// elements where the rest of the path does not exist are left out.
+fn project(value: serde_json::Value, steps: &[JsonPathStep], items: &mut Vec<serde_json::Value>) {
+    let (step, rest): (&JsonPathStep, &[JsonPathStep]) = match steps.split_first() {
+        Some(split) => split,
+        None => {
+            items.push(value);
+            return;
+        }
+    };
+    match (step, value) {
+        (JsonPathStep::Wildcard, serde_json::Value::Array(elements)) => {
+            for element in elements {
+                project(element, rest, items);
+            }
+        },
+        (JsonPathStep::Wildcard, serde_json::Value::Object(members)) => {
+            for (_, member) in members {
+                project(member, rest, items);
+            }
+        },
+        (JsonPathStep::Key(key), serde_json::Value::Object(mut members)) => {
+            if let Some(member) = members.remove(key) {
+                project(member, rest, items);
+            }
+        },
+        (JsonPathStep::Index(i), serde_json::Value::Array(elements)) => {
+            let index: Option<usize> = if *i < 0 {
+                elements.len().checked_sub(i.unsigned_abs() as usize)
+            }
+            else {
+                Some(*i as usize)
+            };
+            if let Some(element) = index.and_then(|index| elements.into_iter().nth(index)) {
+                project(element, rest, items);
+            }
+        },
+        _ => {},
+    }
+}
+
+// analyzer names are checked by Config::load, the quotes keep sql keywords such as `select` usable
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// paths are stored relative to the analyzed directory: "/lib/libc.so.6" and "./lib/libc.so.6" are "lib/libc.so.6"
+fn normalize_result_path(path: &str) -> String {
+    let path: &str = path.strip_prefix("./").unwrap_or(path);
+    path.trim_start_matches('/').to_string()
+}
+
+// sqlite has no escape for `"` in a quoted key, such a key is never found
+fn push_json_path_key(json_path: &mut String, key: &str) -> bool {
+    if key.contains('"') {
+        return false;
+    }
+    json_path.push_str(&format!(".\"{}\"", key));
+    true
+}
+
+// a negative index counts from the end of the array
+fn push_json_path_index(json_path: &mut String, index: i64) {
+    if index < 0 {
+        json_path.push_str(&format!("[#{}]", index));
+    }
+    else {
+        json_path.push_str(&format!("[{}]", index));
+    }
+}
+
+fn condition_compare(cond: &Condition, left: &serde_json::Value, right: &serde_json::Value) -> Result<bool>{
+    compare(&cond.op, cond.pattern.as_ref(), left, right)
+}
+
+// uses the pattern compiled with the config when there is one. values of types that cannot be compared,
+// e.g. a string and a number with `>`, and a pattern from another analyzer that does not compile, do not match
+fn compare(op: &parser_type::Operator, pattern: Option<&parser_type::Pattern>, left: &serde_json::Value, right: &serde_json::Value) -> Result<bool>{
+    match pattern {
+        Some(pattern) => Ok(pattern_compare(left, op, pattern)),
+        None => match json_compare(left, op, right) {
+            Err(Error::ComparisonErrorTypeMismatch()) | Err(Error::InvalidPattern(_)) => Ok(false),
+            result => result,
+        },
+    }
+}
+
+// a missing value or a value other than a string never matches a pattern, with `=~` or `!~`
+fn pattern_compare(left: &serde_json::Value, op: &parser_type::Operator, pattern: &parser_type::Pattern) -> bool {
+    match left.as_str() {
+        Some(text) if *op == parser_type::Operator::NotMatch => !pattern.is_match(text),
+        Some(text) => pattern.is_match(text),
+        None => false,
+    }
+}
+
 pub fn json_compare(left: &serde_json::Value, op: &parser_type::Operator, right: &serde_json::Value) -> Result<bool>{
     match op {
         parser_type::Operator::Equal => Ok(left == right),
@@ -683,6 +1495,20 @@ pub fn json_compare(left: &serde_json::Value, op: &parser_type::Operator, right:
                 (serde_json::Value::String(l_str), serde_json::Value::String(r_str)) => Ok(l_str.contains(r_str)),
                 (serde_json::Value::Object(l_obj), serde_json::Value::String(r_key)) => Ok(l_obj.contains_key(r_key)),
                 (serde_json::Value::Array(l_arr), r_val) => Ok(l_arr.contains(r_val)),
+                // "system" in ["system", "popen"]
+                (l_val, serde_json::Value::Array(r_arr)) => Ok(r_arr.contains(l_val)),
+                _ => Err(Error::ComparisonErrorTypeMismatch()),
+            }
+        }
+        // the pattern comes from another analyzer, so it is compiled here
+        parser_type::Operator::Match | parser_type::Operator::NotMatch | parser_type::Operator::Glob | parser_type::Operator::IGlob => {
+            match right {
+                serde_json::Value::String(source) => {
+                    match parser_type::Pattern::compile(op, source).map_err(Error::InvalidPattern)? {
+                        Some(pattern) => Ok(pattern_compare(left, op, &pattern)),
+                        None => Err(Error::ComparisonErrorTypeMismatch()),
+                    }
+                },
                 _ => Err(Error::ComparisonErrorTypeMismatch()),
             }
         }
@@ -690,3 +1516,371 @@ pub fn json_compare(left: &serde_json::Value, op: &parser_type::Operator, right:
 }
 
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[analyzer]]
+name = "ldd"
+extension = "sh"
+
+[[analyzer]]
+name = "checksec"
+extension = "sh"
+
+[[analyzer]]
+name = "linked"
+extension = "sh"
+arguments = [
+    "ldd.libs[ldd.index]",
+    "ldd.libs[-1]",
+    "path[\"/lib/libc.so.6\"].checksec.relro",
+    "path[ldd.libs[0]].checksec.relro",
+    "path[2]",
+    "path[\"lib/missing.so\"].checksec.relro",
+    "ldd.libs[ldd.missing]",
+]
+"#;
+
+    #[test]
+    fn test_dynamic_access() -> Result<()> {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let busybox: i64 = insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata::default(), &FileOrigin::default())?;
+        let libc: i64 = insert_stmt.insert_path(Path::new("lib/libc.so.6"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("ldd", busybox, serde_json::json!({"libs": ["lib/libc.so.6", "lib/libm.so.6"], "index": 1}))?;
+        insert_stmt.insert_analyzer("checksec", libc, serde_json::json!({"relro": "full"}))?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, _) = select_stmt.get_stmt("linked")?;
+        arg_stmt.set_placeholder(busybox)?;
+        assert_eq!(
+            arg_stmt.get_argument()?,
+            serde_json::json!({
+                "filename": "bin/busybox",
+                "argument1": "lib/libm.so.6",
+                "argument2": "lib/libm.so.6",
+                "argument3": "full",
+                "argument4": "full",
+                "argument5": "lib/libc.so.6",
+                "argument6": null,
+                "argument7": null,
+            })
+        );
+        Ok(())
+    }
+
+    const PROJECTION_CONFIG: &str = r#"
+[[analyzer]]
+name = "elf"
+extension = "sh"
+
+[[analyzer]]
+name = "dangerous"
+extension = "sh"
+arguments = [
+    "elf.imports[*].name",
+    "elf.sections[*].flags[*]",
+    "pathlist",
+    "elf.imports",
+]
+conditions = '''
+any(elf.imports[*].name in ["system", "popen"]) and all(elf.imports[*].size > 0)
+and not any(upper(elf.imports[*].name) == "system") and len(elf.imports) == 3
+'''
+"#;
+
+    #[test]
+    fn test_projection() -> Result<()> {
+        let config: Config = toml::from_str(PROJECTION_CONFIG).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let busybox: i64 = insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_path(Path::new("bin/sh"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("elf", busybox, serde_json::json!({
+            "imports": [{"name": "printf", "size": 1}, {"name": "system", "size": 2}, {"size": 3}],
+            "sections": [{"flags": ["A", "X"]}, {"flags": []}, {"flags": ["W"]}],
+        }))?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, cond_stmt) = select_stmt.get_stmt("dangerous")?;
+        arg_stmt.set_placeholder(busybox)?;
+        assert_eq!(
+            arg_stmt.get_argument()?,
+            serde_json::json!({
+                "filename": "bin/busybox",
+                "argument1": ["printf", "system"],
+                "argument2": ["A", "X", "W"],
+                "argument3": ["bin/busybox", "bin/sh"],
+                "argument4": [{"name": "printf", "size": 1}, {"name": "system", "size": 2}, {"size": 3}],
+            })
+        );
+
+        let cond_stmt: &mut ConditionStatement = cond_stmt.as_mut().unwrap();
+        cond_stmt.set_placeholder(busybox)?;
+        assert!(cond_stmt.is_match_condition(config.analyzer[1].conditions.as_ref().unwrap())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pattern_missing_value() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[analyzer]]
+name = "elf"
+extension = "sh"
+
+[[analyzer]]
+name = "not_lib"
+extension = "sh"
+conditions = 'elf.soname !~ "^lib"'
+
+[[analyzer]]
+name = "lib"
+extension = "sh"
+conditions = 'elf.soname =~ "^lib"'
+
+[[analyzer]]
+name = "not_number"
+extension = "sh"
+conditions = 'elf.size !~ "^1"'
+"#).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let busybox: i64 = insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata::default(), &FileOrigin::default())?;
+        let script: i64 = insert_stmt.insert_path(Path::new("bin/script.sh"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("elf", busybox, serde_json::json!({"soname": "busybox", "size": 20}))?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let mut matches = |name: &str, result_id: i64| -> Result<bool> {
+            let index: usize = config.analyzer.iter().position(|analyzer| analyzer.name == name).unwrap();
+            let (_, cond_stmt) = select_stmt.get_stmt(name)?;
+            let cond_stmt: &mut ConditionStatement = cond_stmt.as_mut().unwrap();
+            cond_stmt.set_placeholder(result_id)?;
+            cond_stmt.is_match_condition(config.analyzer[index].conditions.as_ref().unwrap())
+        };
+        assert!(matches("not_lib", busybox)?);
+        assert!(!matches("lib", busybox)?);
+        // the script has no elf result: null matches neither
+        assert!(!matches("not_lib", script)?);
+        assert!(!matches("lib", script)?);
+        assert!(!matches("not_number", busybox)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_error() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[analyzer]]
+name = "version"
+extension = "sh"
+
+[[analyzer]]
+name = "new"
+extension = "sh"
+arguments = ["int(version.major)", "lower(version.major)"]
+conditions = 'int(version.major) >= 2 or not exists(int(version.major))'
+"#).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let busybox: i64 = insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata::default(), &FileOrigin::default())?;
+        let dropbear: i64 = insert_stmt.insert_path(Path::new("bin/dropbear"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("version", busybox, serde_json::json!({"major": "unknown"}))?;
+        insert_stmt.insert_analyzer("version", dropbear, serde_json::json!({"major": 1}))?;
+
+        // a function failing on the data of one file gives a missing value instead of an error
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, cond_stmt) = select_stmt.get_stmt("new")?;
+        arg_stmt.set_placeholder(busybox)?;
+        assert_eq!(arg_stmt.get_argument()?, serde_json::json!({"filename": "bin/busybox", "argument1": null, "argument2": "unknown"}));
+        arg_stmt.set_placeholder(dropbear)?;
+        assert_eq!(arg_stmt.get_argument()?, serde_json::json!({"filename": "bin/dropbear", "argument1": 1, "argument2": null}));
+
+        let cond_stmt: &mut ConditionStatement = cond_stmt.as_mut().unwrap();
+        cond_stmt.set_placeholder(busybox)?;
+        assert!(cond_stmt.is_match_condition(config.analyzer[1].conditions.as_ref().unwrap())?);
+        cond_stmt.set_placeholder(dropbear)?;
+        assert!(!cond_stmt.is_match_condition(config.analyzer[1].conditions.as_ref().unwrap())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyword_analyzer_name() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[analyzer]]
+name = "select"
+extension = "sh"
+
+[[analyzer]]
+name = "order"
+extension = "sh"
+arguments = ["select.from", "select"]
+"#).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let result_id: i64 = insert_stmt.insert_path(Path::new("bin/sh"), &FileMetadata::default(), &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("select", result_id, serde_json::json!({"from": "where"}))?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, _) = select_stmt.get_stmt("order")?;
+        arg_stmt.set_placeholder(result_id)?;
+        assert_eq!(
+            arg_stmt.get_argument()?,
+            serde_json::json!({"filename": "bin/sh", "argument1": "where", "argument2": {"from": "where"}})
+        );
+        tx.delete_analyzer_results("select")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_columns() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[analyzer]]
+name = "info"
+extension = "sh"
+arguments = ["size", "mime", "link_target", 'path["bin/busybox"].mode']
+"#).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata {
+            size: Some(1024),
+            mode: Some(0o4755),
+            file_type: Some("regular".to_string()),
+            mime: Some("application/x-pie-executable".to_string()),
+            ..FileMetadata::default()
+        }, &FileOrigin::default())?;
+        let sh: i64 = insert_stmt.insert_path(Path::new("bin/sh"), &FileMetadata {
+            size: Some(7),
+            file_type: Some("symlink".to_string()),
+            link_target: Some("busybox".to_string()),
+            ..FileMetadata::default()
+        }, &FileOrigin::default())?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, _) = select_stmt.get_stmt("info")?;
+        arg_stmt.set_placeholder(sh)?;
+        assert_eq!(
+            arg_stmt.get_argument()?,
+            serde_json::json!({"filename": "bin/sh", "argument1": 7, "argument2": null, "argument3": "busybox", "argument4": 0o4755})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_files() -> Result<()> {
+        let config: Config = toml::from_str("analyzer = []").unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        let archive: i64 = insert_stmt.insert_path(Path::new("update.tar"), &FileMetadata::default(), &FileOrigin::default())?;
+        let image: ImageRecord = ImageRecord { parent_id: None, result_id: Some(archive), path: "update.tar", origin: "tar", sha256: None, error: None };
+        let image_id: i64 = insert_stmt.insert_image(&image)?;
+        // resuming finds the same image
+        assert_eq!(insert_stmt.insert_image(&image)?, image_id);
+        let origin: FileOrigin = FileOrigin { image_id: Some(image_id), parent_id: Some(archive) };
+        let busybox: i64 = insert_stmt.insert_path(Path::new("update.tar!/bin/busybox"), &FileMetadata::default(), &origin)?;
+
+        let parent: String = tx.tx.query_row(
+            "SELECT parent.path FROM result JOIN result AS parent ON parent.id = result.parent_id WHERE result.id = ?1",
+            [busybox],
+            |row| row.get(0),
+        )?;
+        assert_eq!(parent, "update.tar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_scope() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[analyzer]]
+name = "os"
+extension = "sh"
+scope = "image"
+stage = "before"
+
+[[analyzer]]
+name = "elf"
+extension = "sh"
+arguments = ["os.name"]
+
+[[analyzer]]
+name = "summary"
+extension = "sh"
+scope = "image"
+arguments = ["path[*]", "path[*].elf.imports[*]", "path[*].size", "os.name"]
+"#).unwrap();
+        let mut db: Database = Database::open(Path::new(":memory:"))?;
+        db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
+        db.create_analyzer_table(&config)?;
+        let tx: Transaction = db.start_transaction()?;
+
+        let mut insert_stmt: InsertAnalyerStatement = tx.insert_stmt(&config)?;
+        insert_stmt.insert_analyzer("os", IMAGE_RESULT_ID, serde_json::json!({"name": "openwrt"}))?;
+        let busybox: i64 = insert_stmt.insert_path(Path::new("bin/busybox"), &FileMetadata { size: Some(10), ..FileMetadata::default() }, &FileOrigin::default())?;
+        insert_stmt.insert_path(Path::new("etc"), &FileMetadata::default(), &FileOrigin::default())?;
+        let sh: i64 = insert_stmt.insert_path(Path::new("bin/sh"), &FileMetadata { size: Some(5), ..FileMetadata::default() }, &FileOrigin::default())?;
+        insert_stmt.insert_analyzer("elf", busybox, serde_json::json!({"imports": ["printf", "system"]}))?;
+        insert_stmt.insert_analyzer("elf", sh, serde_json::json!({"imports": ["execve"]}))?;
+
+        let mut select_stmt: SelectAnalyzerStatement = tx.select_stmt(&config)?;
+        let (arg_stmt, _) = select_stmt.get_stmt("elf")?;
+        arg_stmt.set_placeholder(sh)?;
+        assert_eq!(arg_stmt.get_argument()?, serde_json::json!({"filename": "bin/sh", "argument1": "openwrt"}));
+
+        let (arg_stmt, _) = select_stmt.get_stmt("summary")?;
+        arg_stmt.set_placeholder(IMAGE_RESULT_ID)?;
+        assert_eq!(arg_stmt.get_argument()?, serde_json::json!({
+            "filename": null,
+            "argument1": ["bin/busybox", "etc", "bin/sh"],
+            "argument2": ["printf", "system", "execve"],
+            "argument3": [10, 5],
+            "argument4": "openwrt",
+        }));
+        Ok(())
+    }
+}
@@ -22,6 +22,10 @@ pub enum Error {
     BindRequired(),
     MismatchedBindType(),
     BindAlreadyProvided(),
+    InvalidPattern(String),
+    FunctionError(String),
+    InvalidPathAccess(),
+    FileColumnDoesNotHaveAccess(String),
 }
 
 impl fmt::Display for Error {
@@ -42,7 +46,11 @@ impl fmt::Display for Error {
             Error::NoAnalyzerName() => write!(f, "no analyzer name"),
             Error::BindRequired() => write!(f, "bind required"),
             Error::MismatchedBindType() => write!(f, "missmatch bind type"),
-            Error::BindAlreadyProvided() => write!(f, "bind already provided")
+            Error::BindAlreadyProvided() => write!(f, "bind already provided"),
+            Error::InvalidPattern(err) => write!(f, "invalid pattern: {}", err),
+            Error::FunctionError(err) => write!(f, "function error: {}", err),
+            Error::InvalidPathAccess() => write!(f, "path must be followed by [result id or path] and an optional analyzer name"),
+            Error::FileColumnDoesNotHaveAccess(name) => write!(f, "`{}` is a value of the file and has no keys or indices", name),
         }
     }
 }
@@ -67,6 +75,10 @@ impl std::error::Error for Error {
             Error::BindRequired() => None,
             Error::MismatchedBindType() => None,
             Error::BindAlreadyProvided() => None,
+            Error::InvalidPattern(_) => None,
+            Error::FunctionError(_) => None,
+            Error::InvalidPathAccess() => None,
+            Error::FileColumnDoesNotHaveAccess(_) => None,
         }
     }
 }
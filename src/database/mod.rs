@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod database;
 pub mod error;
 pub mod result;
\ No newline at end of file
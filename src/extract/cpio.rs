@@ -0,0 +1,218 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::extract::error::Error;
+use crate::extract::extractor;
+use crate::extract::extractor::Budget;
+
+
+// file type bits of the mode
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+const TRAILER: &str = "TRAILER!!!";
+
+// the fields of a header this reader uses
+struct Header {
+    mode: u32,
+    mtime: u64,
+    name_size: usize,
+    file_size: u64,
+    // newc aligns the name and the data on 4 bytes, odc does not align
+    aligned: bool,
+}
+
+// Unpacks a cpio archive in the newc ("070701"), crc ("070702") or odc ("070707") format, as used by initramfs.
+// Regular files, directories and symlinks are created; device nodes, fifos and sockets can not be created without
+// privileges and are left out.
+pub fn unpack<R: Read>(mut reader: R, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+    let mut offset: u64 = 0;
+    loop {
+        let header: Header = match read_header(&mut reader, &mut offset)? {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+
+        // the name size comes from the archive, and is checked before it is allocated
+        if header.name_size > libc::PATH_MAX as usize {
+            return Err(invalid(&format!("name of {} bytes at offset {}", header.name_size, offset)));
+        }
+        let mut name: Vec<u8> = vec![0u8; header.name_size];
+        read_exact(&mut reader, &mut name, &mut offset)?;
+        if header.aligned {
+            skip_padding(&mut reader, &mut offset)?;
+        }
+        // the name ends with a NUL
+        let name: String = String::from_utf8_lossy(name.split(|byte| *byte == 0).next().unwrap_or_default()).into_owned();
+        if name == TRAILER {
+            return Ok(());
+        }
+
+        budget.take(header.file_size)?;
+        let mut data: Vec<u8> = Vec::new();
+        (&mut reader).take(header.file_size).read_to_end(&mut data)?;
+        if (data.len() as u64) < header.file_size {
+            return Err(invalid("unexpected end of the archive"));
+        }
+        offset += header.file_size;
+        if header.aligned {
+            skip_padding(&mut reader, &mut offset)?;
+        }
+
+        // `.` is the destination itself, and names leaving the destination are ignored
+        let path: PathBuf = match extractor::safe_join(destination, Path::new(&name)) {
+            Some(path) if path != destination => path,
+            _ => continue,
+        };
+        match header.mode & S_IFMT {
+            S_IFDIR => std::fs::create_dir_all(&path)?,
+            S_IFREG => extractor::write_file(&path, &data, header.mode, Some(header.mtime))?,
+            S_IFLNK => extractor::create_symlink(&path, Path::new(&String::from_utf8_lossy(&data).into_owned()))?,
+            _ => {},
+        }
+    }
+}
+
+// None at the end of the input
+fn read_header<R: Read>(reader: &mut R, offset: &mut u64) -> Result<Option<Header>, Error> {
+    let mut magic: [u8; 6] = [0u8; 6];
+    let n: usize = reader.read(&mut magic)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n < magic.len() {
+        reader.read_exact(&mut magic[n..]).map_err(|_| invalid("unexpected end of the archive"))?;
+    }
+    *offset += magic.len() as u64;
+
+    match &magic {
+        b"070701" | b"070702" => {
+            // ino mode uid gid nlink mtime filesize devmajor devminor rdevmajor rdevminor namesize check
+            let mut fields: [u8; 13 * 8] = [0u8; 13 * 8];
+            read_exact(reader, &mut fields, offset)?;
+            let field = |index: usize| -> Result<u64, Error> { parse_number(&fields[index * 8..(index + 1) * 8], 16) };
+            Ok(Some(Header {
+                mode: field(1)? as u32,
+                mtime: field(5)?,
+                file_size: field(6)?,
+                name_size: field(11)? as usize,
+                aligned: true,
+            }))
+        }
+        b"070707" => {
+            // dev ino mode uid gid nlink rdev (6 digits each) mtime (11) namesize (6) filesize (11)
+            let mut fields: [u8; 7 * 6 + 11 + 6 + 11] = [0u8; 7 * 6 + 11 + 6 + 11];
+            read_exact(reader, &mut fields, offset)?;
+            Ok(Some(Header {
+                mode: parse_number(&fields[12..18], 8)? as u32,
+                mtime: parse_number(&fields[42..53], 8)?,
+                name_size: parse_number(&fields[53..59], 8)? as usize,
+                file_size: parse_number(&fields[59..70], 8)?,
+                aligned: false,
+            }))
+        }
+        // the zero padding some tools add after the trailer
+        _ if magic.iter().all(|byte| *byte == 0) => Ok(None),
+        _ => Err(invalid(&format!("bad magic {:?} at offset {}", String::from_utf8_lossy(&magic), *offset - 6))),
+    }
+}
+
+fn read_exact<R: Read>(reader: &mut R, buffer: &mut [u8], offset: &mut u64) -> Result<(), Error> {
+    reader.read_exact(buffer).map_err(|_| invalid("unexpected end of the archive"))?;
+    *offset += buffer.len() as u64;
+    Ok(())
+}
+
+fn skip_padding<R: Read>(reader: &mut R, offset: &mut u64) -> Result<(), Error> {
+    let padding: usize = ((4 - *offset % 4) % 4) as usize;
+    let mut buffer: [u8; 3] = [0u8; 3];
+    read_exact(reader, &mut buffer[..padding], offset)
+}
+
+fn parse_number(field: &[u8], radix: u32) -> Result<u64, Error> {
+    let text: &str = std::str::from_utf8(field).map_err(|_| invalid("header field is not ascii"))?;
+    u64::from_str_radix(text, radix).map_err(|_| invalid(&format!("bad header field {:?}", text)))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidArchive("cpio".to_string(), reason.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn newc_entry(archive: &mut Vec<u8>, name: &str, mode: u32, data: &[u8]) {
+        let header: String = format!(
+            "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+            0, mode, 0, 0, 1, 1700000000, data.len(), 0, 0, 0, 0, name.len() + 1, 0
+        );
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.push(0);
+        while !archive.len().is_multiple_of(4) {
+            archive.push(0);
+        }
+        archive.extend_from_slice(data);
+        while !archive.len().is_multiple_of(4) {
+            archive.push(0);
+        }
+    }
+
+    #[test]
+    fn test_unpack_newc() {
+        let mut archive: Vec<u8> = Vec::new();
+        newc_entry(&mut archive, ".", S_IFDIR | 0o755, b"");
+        newc_entry(&mut archive, "bin", S_IFDIR | 0o755, b"");
+        newc_entry(&mut archive, "bin/busybox", S_IFREG | 0o4755, b"\x7fELF");
+        newc_entry(&mut archive, "bin/sh", S_IFLNK | 0o777, b"busybox");
+        newc_entry(&mut archive, "dev/console", 0o020600, b"");
+        newc_entry(&mut archive, "../escape", S_IFREG | 0o644, b"x");
+        newc_entry(&mut archive, TRAILER, 0, b"");
+
+        let dir = tempfile::tempdir().unwrap();
+        unpack(archive.as_slice(), dir.path(), &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("bin/busybox")).unwrap(), b"\x7fELF");
+        let metadata: std::fs::Metadata = std::fs::metadata(dir.path().join("bin/busybox")).unwrap();
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777, 0o4755);
+        assert_eq!(std::os::unix::fs::MetadataExt::mtime(&metadata), 1700000000);
+        assert_eq!(std::fs::read_link(dir.path().join("bin/sh")).unwrap(), Path::new("busybox"));
+        assert!(!dir.path().join("dev/console").exists());
+        assert!(!dir.path().join("../escape").exists());
+    }
+
+    #[test]
+    fn test_unpack_odc() {
+        let mut archive: Vec<u8> = Vec::new();
+        let name: &str = "init";
+        archive.extend_from_slice(format!(
+            "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+            0, 1, S_IFREG | 0o755, 0, 0, 1, 0, 0, name.len() + 1, 2
+        ).as_bytes());
+        archive.extend_from_slice(b"init\0#!");
+
+        let dir = tempfile::tempdir().unwrap();
+        unpack(archive.as_slice(), dir.path(), &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("init")).unwrap(), b"#!");
+        assert!(unpack(&archive[..archive.len() - 1], dir.path(), &mut Budget::new(4096)).is_err());
+        assert!(unpack(&b"not a cpio archive"[..], dir.path(), &mut Budget::new(4096)).is_err());
+    }
+
+    #[test]
+    fn test_unpack_name_too_long() {
+        let archive: String = format!(
+            "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}init",
+            0, S_IFREG | 0o755, 0, 0, 1, 0, 0, 0, 0, 0, 0, u32::MAX, 0
+        );
+        let dir = tempfile::tempdir().unwrap();
+        match unpack(archive.as_bytes(), dir.path(), &mut Budget::new(4096)) {
+            Err(Error::InvalidArchive(format, reason)) => {
+                assert_eq!(format, "cpio");
+                assert!(reason.starts_with("name of 4294967295 bytes"), "{}", reason);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    ZipError(zip::result::ZipError),
+    // no extractor recognizes the image
+    UnknownFormat(std::path::PathBuf),
+    InvalidArchive(String, String),
+    // the program of an external extractor is not installed
+    ToolNotFound(String, String),
+    ToolFailed(String, Option<i32>, String),
+    // more than this many bytes would be extracted
+    LimitExceeded(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IoError(err) => write!(f, "IO error: {}", err),
+            Error::ZipError(err) => write!(f, "zip error: {}", err),
+            Error::UnknownFormat(path) => write!(f, "no extractor recognizes the image `{}`", path.display()),
+            Error::InvalidArchive(extractor, reason) => write!(f, "invalid {} archive: {}", extractor, reason),
+            Error::ToolNotFound(extractor, program) => write!(f, "extractor `{}` requires `{}`, which is not installed", extractor, program),
+            Error::ToolFailed(extractor, code, stderr) => match code {
+                Some(code) => write!(f, "extractor `{}` exited with status {}: {}", extractor, code, stderr),
+                None => write!(f, "extractor `{}` was killed by a signal: {}", extractor, stderr),
+            },
+            Error::LimitExceeded(limit) => write!(f, "extraction stopped: more than {} bytes would be written", limit),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(err) => Some(err),
+            Error::ZipError(err) => Some(err),
+            Error::UnknownFormat(_) => None,
+            Error::InvalidArchive(_, _) => None,
+            Error::ToolNotFound(_, _) => None,
+            Error::ToolFailed(_, _, _) => None,
+            Error::LimitExceeded(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        Error::ZipError(err)
+    }
+}
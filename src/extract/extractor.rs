@@ -0,0 +1,593 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use serde::de::Deserializer;
+use serde::Deserialize;
+use crate::extract::cpio;
+use crate::extract::error::Error;
+
+
+// bytes read from the start of a file to recognize its format. the tar magic is at offset 257.
+const HEADER_SIZE: usize = 512;
+// how often the output of an external extractor is counted while it runs
+const TOOL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Unpacks one kind of image. The name is recorded in the database as the origin of the extracted files.
+pub trait Extractor: Send + Sync {
+    fn name(&self) -> &str;
+    // `header` is the start of the file, at most HEADER_SIZE bytes
+    fn detect(&self, path: &Path, header: &[u8]) -> bool;
+    // `destination` is an empty directory. nothing may be written outside of it,
+    // and no more than the budget: the extraction stops with Error::LimitExceeded when it runs out.
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error>;
+}
+
+// Bytes an extraction may still write, shared by every extraction of a run against zip bombs.
+#[derive(Debug)]
+pub struct Budget {
+    remaining: u64,
+    limit: u64,
+}
+
+impl Budget {
+    pub fn new(limit: u64) -> Self {
+        Budget { remaining: limit, limit }
+    }
+
+    pub fn take(&mut self, bytes: u64) -> Result<(), Error> {
+        if bytes > self.remaining {
+            self.remaining = 0;
+            return Err(Error::LimitExceeded(self.limit));
+        }
+        self.remaining -= bytes;
+        Ok(())
+    }
+
+    // reads at most the remaining budget
+    pub fn read<R: Read>(&mut self, reader: R) -> Result<Vec<u8>, Error> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.take(self.remaining.saturating_add(1)).read_to_end(&mut data)?;
+        self.take(data.len() as u64)?;
+        Ok(data)
+    }
+
+    // the error of an extraction stopped past the budget, which is used up
+    fn exhaust(&mut self) -> Error {
+        self.remaining = 0;
+        Error::LimitExceeded(self.limit)
+    }
+
+    // copies at most the remaining budget
+    pub fn copy<R: Read, W: std::io::Write>(&mut self, reader: R, writer: &mut W) -> Result<(), Error> {
+        let copied: u64 = std::io::copy(&mut reader.take(self.remaining.saturating_add(1)), writer)?;
+        self.take(copied)
+    }
+}
+
+// the extractors tried on an image, in order
+pub struct Extractors {
+    extractors: Vec<Box<dyn Extractor>>,
+}
+
+impl Extractors {
+    // the extractors of the config come first, so they can replace a built-in one
+    pub fn new(external: &[ExternalExtractor]) -> Self {
+        let mut extractors: Extractors = Extractors { extractors: Vec::new() };
+        for extractor in external {
+            extractors.register(Box::new(extractor.clone()));
+        }
+        extractors.register(Box::new(Gzip));
+        extractors.register(Box::new(Xz));
+        extractors.register(Box::new(Zip));
+        extractors.register(Box::new(Cpio));
+        extractors.register(Box::new(Tar));
+        for extractor in ExternalExtractor::defaults() {
+            extractors.register(Box::new(extractor));
+        }
+        extractors
+    }
+
+    pub fn register(&mut self, extractor: Box<dyn Extractor>) {
+        self.extractors.push(extractor);
+    }
+
+    // None when the file is not an image any extractor knows
+    pub fn detect(&self, path: &Path) -> Result<Option<&dyn Extractor>, Error> {
+        let mut header: Vec<u8> = Vec::with_capacity(HEADER_SIZE);
+        std::fs::File::open(path)?.take(HEADER_SIZE as u64).read_to_end(&mut header)?;
+        Ok(self.extractors.iter().find(|extractor| extractor.detect(path, &header)).map(|extractor| extractor.as_ref()))
+    }
+}
+
+pub struct Gzip;
+
+impl Extractor for Gzip {
+    fn name(&self) -> &str {
+        "gzip"
+    }
+
+    fn detect(&self, _path: &Path, header: &[u8]) -> bool {
+        header.starts_with(b"\x1f\x8b")
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        let decoder = flate2::read::MultiGzDecoder::new(std::io::BufReader::new(std::fs::File::open(image)?));
+        decompress(decoder, image, &["gz", "tgz"], destination, budget)
+    }
+}
+
+pub struct Xz;
+
+impl Extractor for Xz {
+    fn name(&self) -> &str {
+        "xz"
+    }
+
+    fn detect(&self, _path: &Path, header: &[u8]) -> bool {
+        header.starts_with(b"\xfd7zXZ\x00")
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        let decoder = xz2::read::XzDecoder::new_multi_decoder(std::io::BufReader::new(std::fs::File::open(image)?));
+        decompress(decoder, image, &["xz", "txz"], destination, budget)
+    }
+}
+
+pub struct Tar;
+
+impl Extractor for Tar {
+    fn name(&self) -> &str {
+        "tar"
+    }
+
+    // the magic of ustar and gnu tar, or the extension for the older formats without one
+    fn detect(&self, path: &Path, header: &[u8]) -> bool {
+        header.get(257..262) == Some(b"ustar") || path.extension().is_some_and(|extension| extension == "tar")
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        let mut archive = tar::Archive::new(std::io::BufReader::new(std::fs::File::open(image)?));
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_mtime(true);
+        archive.set_unpack_xattrs(false);
+        // the permissions of a directory are applied after its files, so that a read-only directory can still be
+        // filled, and to children before their parent (a parent sorts before its children)
+        let mut directories = Vec::new();
+        // entries leaving the destination, also through a symlink, are skipped by the tar crate
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            match entry.header().entry_type() {
+                // device nodes and fifos can not be created without privileges
+                tar::EntryType::Block | tar::EntryType::Char | tar::EntryType::Fifo => continue,
+                tar::EntryType::Directory => directories.push(entry),
+                _ => {
+                    budget.take(entry.size())?;
+                    entry.unpack_in(destination)?;
+                }
+            }
+        }
+        directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+        for mut directory in directories {
+            directory.unpack_in(destination)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Zip;
+
+impl Extractor for Zip {
+    fn name(&self) -> &str {
+        "zip"
+    }
+
+    fn detect(&self, _path: &Path, header: &[u8]) -> bool {
+        header.starts_with(b"PK\x03\x04")
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(std::fs::File::open(image)?))?;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let path: PathBuf = match file.enclosed_name().and_then(|name| safe_join(destination, name)) {
+                Some(path) if path != destination => path,
+                _ => continue,
+            };
+            let mode: Option<u32> = file.unix_mode();
+            if file.is_dir() {
+                std::fs::create_dir_all(&path)?;
+                continue;
+            }
+            // the size in the header of a zip may be a lie
+            let data: Vec<u8> = budget.read(&mut file)?;
+            // S_IFLNK: the content is the target
+            if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+                create_symlink(&path, Path::new(&String::from_utf8_lossy(&data).into_owned()))?;
+            }
+            else {
+                write_file(&path, &data, mode.unwrap_or(0o644), None)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Cpio;
+
+impl Extractor for Cpio {
+    fn name(&self) -> &str {
+        "cpio"
+    }
+
+    fn detect(&self, _path: &Path, header: &[u8]) -> bool {
+        header.starts_with(b"070701") || header.starts_with(b"070702") || header.starts_with(b"070707")
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        cpio::unpack(std::io::BufReader::new(std::fs::File::open(image)?), destination, budget)
+    }
+}
+
+// An extractor run as a program, for the formats without a built-in one (squashfs, jffs2, ubi, ...).
+// `{image}` and `{output}` in the command are replaced by the image and the destination directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalExtractor {
+    pub name: String,
+    pub command: Vec<String>,
+    // the bytes the image starts with, after `offset` bytes
+    pub signature: Vec<u8>,
+    pub offset: usize,
+}
+
+impl ExternalExtractor {
+    // squashfs, jffs2 and ubi with their usual tools. extracting fails with ToolNotFound when the tool is not installed.
+    pub fn defaults() -> Vec<ExternalExtractor> {
+        let external = |name: &str, command: &[&str], signature: &[u8]| ExternalExtractor {
+            name: name.to_string(),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            signature: signature.to_vec(),
+            offset: 0,
+        };
+        vec![
+            external("squashfs", &["unsquashfs", "-f", "-no-xattrs", "-d", "{output}", "{image}"], b"hsqs"),
+            external("squashfs", &["unsquashfs", "-f", "-no-xattrs", "-d", "{output}", "{image}"], b"sqsh"),
+            // the magic of a node followed by the type of the first node, a cleanmarker or a directory entry,
+            // in little and big endian
+            external("jffs2", &["jefferson", "-f", "-d", "{output}", "{image}"], b"\x85\x19\x03\x20"),
+            external("jffs2", &["jefferson", "-f", "-d", "{output}", "{image}"], b"\x85\x19\x01\xe0"),
+            external("jffs2", &["jefferson", "-f", "-d", "{output}", "{image}"], b"\x19\x85\x20\x03"),
+            external("jffs2", &["jefferson", "-f", "-d", "{output}", "{image}"], b"\x19\x85\xe0\x01"),
+            external("ubi", &["ubireader_extract_files", "-o", "{output}", "{image}"], b"UBI#"),
+        ]
+    }
+}
+
+impl Extractor for ExternalExtractor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, _path: &Path, header: &[u8]) -> bool {
+        header.get(self.offset..).is_some_and(|header| header.starts_with(&self.signature))
+    }
+
+    fn extract(&self, image: &Path, destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+        let args: Vec<String> = self.command.iter()
+            .map(|arg| arg.replace("{image}", &image.to_string_lossy()).replace("{output}", &destination.to_string_lossy()))
+            .collect();
+        let (program, args) = match args.split_first() {
+            Some(split) => split,
+            None => return Err(Error::ToolNotFound(self.name.clone(), String::new())),
+        };
+        let mut command: std::process::Command = std::process::Command::new(program);
+        command.args(args).stdin(std::process::Stdio::null()).stdout(std::process::Stdio::null()).stderr(std::process::Stdio::piped());
+        // a file larger than the budget stops the program with SIGXFSZ. the program gets its own process group,
+        // so that what it started is killed with it.
+        let max_file_size: libc::rlim_t = budget.remaining.saturating_add(1) as libc::rlim_t;
+        // only async-signal-safe calls are made between fork and exec
+        unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, move || {
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let limit = libc::rlimit { rlim_cur: max_file_size, rlim_max: max_file_size };
+                if libc::setrlimit(libc::RLIMIT_FSIZE, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child: std::process::Child = match command.spawn() {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Err(Error::ToolNotFound(self.name.clone(), program.clone())),
+            Err(err) => return Err(err.into()),
+        };
+        let pid: libc::pid_t = child.id() as libc::pid_t;
+        let stderr_reader = child.stderr.take().map(|mut pipe| std::thread::spawn(move || {
+            let mut stderr: Vec<u8> = Vec::new();
+            let _ = pipe.read_to_end(&mut stderr);
+            stderr
+        }));
+
+        // the files written so far are counted while the program runs, and it is killed past the budget
+        let status: Option<std::process::ExitStatus> = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if written_size(destination) > budget.remaining {
+                break None;
+            }
+            std::thread::sleep(TOOL_POLL_INTERVAL);
+        };
+        // also what the program left running
+        unsafe {
+            libc::killpg(pid, libc::SIGKILL);
+        }
+        let status: std::process::ExitStatus = match status {
+            Some(status) => status,
+            None => {
+                child.wait()?;
+                return Err(budget.exhaust());
+            }
+        };
+        let stderr: Vec<u8> = stderr_reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+        if std::os::unix::process::ExitStatusExt::signal(&status) == Some(libc::SIGXFSZ) {
+            return Err(budget.exhaust());
+        }
+        if !status.success() {
+            return Err(Error::ToolFailed(self.name.clone(), status.code(), String::from_utf8_lossy(&stderr).trim().to_string()));
+        }
+        // the files written between the last check and the exit
+        let mut size: u64 = 0;
+        for dir_entry in walkdir::WalkDir::new(destination).follow_links(false) {
+            let dir_entry: walkdir::DirEntry = dir_entry.map_err(std::io::Error::from)?;
+            if dir_entry.file_type().is_file() {
+                size += dir_entry.metadata().map_err(std::io::Error::from)?.len();
+            }
+        }
+        budget.take(size)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExternalExtractor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct InnerExternalExtractor {
+            name: String,
+            command: Vec<String>,
+            // hex
+            signature: String,
+            offset: Option<usize>,
+        }
+
+        let inner: InnerExternalExtractor = InnerExternalExtractor::deserialize(deserializer)?;
+        if inner.command.is_empty() {
+            return Err(serde::de::Error::custom(format!("extractor `{}`: command is empty", inner.name)));
+        }
+        let signature: Vec<u8> = hex::decode(&inner.signature).map_err(|err| serde::de::Error::custom(format!(
+            "extractor `{}`: invalid signature: {}, error: {}",
+            inner.name, inner.signature, err
+        )))?;
+        Ok(ExternalExtractor {
+            name: inner.name,
+            command: inner.command,
+            signature,
+            offset: inner.offset.unwrap_or(0),
+        })
+    }
+}
+
+// the size of the files under `destination`, skipping those an external extractor removes while they are counted
+fn written_size(destination: &Path) -> u64 {
+    walkdir::WalkDir::new(destination).follow_links(false).into_iter()
+        .filter_map(|dir_entry| dir_entry.ok())
+        .filter(|dir_entry| dir_entry.file_type().is_file())
+        .filter_map(|dir_entry| dir_entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// a compressed file holds one file, named after the image without the compression extension
+fn decompress<R: Read>(decoder: R, image: &Path, extensions: &[&str], destination: &Path, budget: &mut Budget) -> Result<(), Error> {
+    let name: String = image.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let name: String = match name.rsplit_once('.') {
+        // fw.tgz -> fw.tar
+        Some((stem, extension)) if extension.len() == 3 && extension.starts_with('t') && extensions.contains(&extension) => format!("{}.tar", stem),
+        Some((stem, extension)) if extensions.contains(&extension) && !stem.is_empty() => stem.to_string(),
+        _ => format!("{}.out", if name.is_empty() { "image" } else { &name }),
+    };
+    let mut file = std::fs::File::create(destination.join(name))?;
+    budget.copy(decoder, &mut file)
+}
+
+// `destination` joined with the path of an archive entry. None when the entry would leave the destination,
+// by `..` or through a symlink created by an earlier entry. A leading `/` is ignored.
+pub fn safe_join(destination: &Path, name: &Path) -> Option<PathBuf> {
+    let mut path: PathBuf = destination.to_path_buf();
+    let mut components = name.components().peekable();
+    while let Some(component) = components.next() {
+        match component {
+            Component::Normal(part) => {
+                path.push(part);
+                // the entry itself may replace a symlink, a directory on the way may not be one
+                if components.peek().is_some() && std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {},
+            Component::ParentDir => return None,
+        }
+    }
+    Some(path)
+}
+
+// writes a regular file of an archive, replacing what an earlier entry put there
+pub fn write_file(path: &Path, data: &[u8], mode: u32, mtime: Option<u64>) -> Result<(), Error> {
+    prepare(path)?;
+    std::fs::write(path, data)?;
+    std::fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(mode & 0o7777))?;
+    if let Some(mtime) = mtime {
+        let file = std::fs::File::options().write(true).open(path)?;
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(mtime))?;
+    }
+    Ok(())
+}
+
+pub fn create_symlink(path: &Path, target: &Path) -> Result<(), Error> {
+    prepare(path)?;
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+// the parent directories exist, and an existing file or symlink is removed so it is never written through
+fn prepare(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir()) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_detect_and_extract() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_size(3);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, "bin/busybox", &b"elf"[..]).unwrap();
+        let tar_data: Vec<u8> = builder.into_inner().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar_data).unwrap();
+        let image: PathBuf = dir.path().join("rootfs.tgz");
+        std::fs::write(&image, encoder.finish().unwrap()).unwrap();
+
+        let extractors: Extractors = Extractors::new(&[]);
+        let gzip: &dyn Extractor = extractors.detect(&image).unwrap().unwrap();
+        assert_eq!(gzip.name(), "gzip");
+        let output: PathBuf = dir.path().join("gzip");
+        std::fs::create_dir(&output).unwrap();
+        gzip.extract(&image, &output, &mut Budget::new(4096)).unwrap();
+
+        let tar_image: PathBuf = output.join("rootfs.tar");
+        let tar: &dyn Extractor = extractors.detect(&tar_image).unwrap().unwrap();
+        assert_eq!(tar.name(), "tar");
+        let output: PathBuf = dir.path().join("tar");
+        std::fs::create_dir(&output).unwrap();
+        tar.extract(&tar_image, &output, &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(output.join("bin/busybox")).unwrap(), b"elf");
+
+        std::fs::write(dir.path().join("kernel"), b"\x00\x01").unwrap();
+        assert!(extractors.detect(&dir.path().join("kernel")).unwrap().is_none());
+    }
+
+    // the permissions of a read-only directory are applied after its files are written
+    #[test]
+    fn test_tar_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, mode) in [("etc", 0o555), ("etc/ssl", 0o500)] {
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_size(0);
+            header.set_mode(mode);
+            header.set_cksum();
+            builder.append_data(&mut header, path, &b""[..]).unwrap();
+        }
+        for path in ["etc/passwd", "etc/ssl/cert.pem"] {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, &b"data"[..]).unwrap();
+        }
+        let image: PathBuf = dir.path().join("rootfs.tar");
+        std::fs::write(&image, builder.into_inner().unwrap()).unwrap();
+
+        let output: PathBuf = dir.path().join("output");
+        std::fs::create_dir(&output).unwrap();
+        Tar.extract(&image, &output, &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(output.join("etc/ssl/cert.pem")).unwrap(), b"data");
+        for (path, mode) in [("etc", 0o555), ("etc/ssl", 0o500)] {
+            let metadata: std::fs::Metadata = std::fs::metadata(output.join(path)).unwrap();
+            assert_eq!(std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777, mode);
+        }
+        // so that the temporary directory can be removed
+        for path in ["etc", "etc/ssl"] {
+            std::fs::set_permissions(output.join(path), std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let image: PathBuf = dir.path().join("fw.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&image).unwrap());
+        let options = zip::write::FileOptions::default().unix_permissions(0o755);
+        writer.start_file("bin/busybox", options).unwrap();
+        writer.write_all(b"elf").unwrap();
+        writer.add_symlink("bin/sh", "busybox", options).unwrap();
+        writer.start_file("../escape", options).unwrap();
+        writer.finish().unwrap();
+
+        let output: PathBuf = dir.path().join("output");
+        std::fs::create_dir(&output).unwrap();
+        Zip.extract(&image, &output, &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(output.join("bin/busybox")).unwrap(), b"elf");
+        assert_eq!(std::fs::read_link(output.join("bin/sh")).unwrap(), Path::new("busybox"));
+        assert!(!dir.path().join("escape").exists());
+    }
+
+    #[test]
+    fn test_safe_join() {
+        let dir = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink("/etc", dir.path().join("etc")).unwrap();
+        assert_eq!(safe_join(dir.path(), Path::new("/bin/sh")), Some(dir.path().join("bin/sh")));
+        assert_eq!(safe_join(dir.path(), Path::new("etc")), Some(dir.path().join("etc")));
+        assert_eq!(safe_join(dir.path(), Path::new("etc/passwd")), None);
+        assert_eq!(safe_join(dir.path(), Path::new("bin/../../x")), None);
+    }
+
+    #[test]
+    fn test_external_extractor() {
+        let dir = tempfile::tempdir().unwrap();
+        let image: PathBuf = dir.path().join("fw.bin");
+        std::fs::write(&image, b"FWIMG").unwrap();
+        let extractor: ExternalExtractor = toml::from_str(r#"
+name = "fwimg"
+command = ["cp", "{image}", "{output}/copy"]
+signature = "4657494d47"
+"#).unwrap();
+        let extractors: Extractors = Extractors::new(&[extractor]);
+        let found: &dyn Extractor = extractors.detect(&image).unwrap().unwrap();
+        assert_eq!(found.name(), "fwimg");
+        found.extract(&image, dir.path(), &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("copy")).unwrap(), b"FWIMG");
+
+        // a program writing past the budget is stopped, whether it writes one large file or many small ones
+        for script in ["head -c 100000 /dev/zero > {output}/big; sleep 10", "i=0; while :; do i=$((i+1)); head -c 1024 /dev/zero > {output}/f$i; done"] {
+            let output: PathBuf = dir.path().join("bomb");
+            std::fs::create_dir(&output).unwrap();
+            let bomb: ExternalExtractor = ExternalExtractor { command: vec!["sh".to_string(), "-c".to_string(), script.to_string()], name: "bomb".to_string(), signature: Vec::new(), offset: 0 };
+            let started: std::time::Instant = std::time::Instant::now();
+            assert!(matches!(bomb.extract(&image, &output, &mut Budget::new(65536)), Err(Error::LimitExceeded(65536))), "{}", script);
+            assert!(started.elapsed() < std::time::Duration::from_secs(5), "{}", script);
+            std::fs::remove_dir_all(&output).unwrap();
+        }
+
+        let missing: ExternalExtractor = ExternalExtractor { command: vec!["faaf-no-such-tool".to_string()], ..ExternalExtractor::defaults()[0].clone() };
+        assert!(matches!(missing.extract(&image, dir.path(), &mut Budget::new(4096)), Err(Error::ToolNotFound(_, _))));
+    }
+}
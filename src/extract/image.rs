@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use crate::extract::error::Error;
+use crate::extract::extractor::{Budget, Extractor, Extractors};
+use crate::gateway::metadata;
+
+
+// compressed files unwrapped one after the other, e.g. rootfs.tar.gz -> rootfs.tar -> files
+const MAX_LAYERS: usize = 16;
+
+// an image unpacked on the way to the firmware root directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    // the path given for the first layer, the name of the file in the previous layer for the others
+    pub path: String,
+    pub sha256: Option<String>,
+    // name of the extractor that unpacked it
+    pub origin: String,
+}
+
+// The firmware root directory made from an image. It lives in a temporary directory removed on drop.
+pub struct ExtractedImage {
+    pub root: PathBuf,
+    // from the image given to faaf to the one the files of `root` come from
+    pub layers: Vec<Layer>,
+    _directory: tempfile::TempDir,
+}
+
+// Unpacks `image` into a temporary directory. When the output is a single file that is an image again
+// (the tar of a .tar.gz), it is unpacked too, and the files of the last layer are the firmware root directory.
+pub fn extract_image(extractors: &Extractors, image: &Path, budget: &mut Budget) -> Result<ExtractedImage, Error> {
+    let directory: tempfile::TempDir = tempfile::Builder::new().prefix("faaf-").tempdir()?;
+    let mut layers: Vec<Layer> = Vec::new();
+    let mut current: PathBuf = image.to_path_buf();
+    let mut path: String = image.display().to_string();
+    let mut root: PathBuf;
+
+    loop {
+        let extractor: &dyn Extractor = match extractors.detect(&current)? {
+            Some(extractor) => extractor,
+            None => return Err(Error::UnknownFormat(current)),
+        };
+        root = directory.path().join(layers.len().to_string());
+        std::fs::create_dir(&root)?;
+        extractor.extract(&current, &root, budget)?;
+        layers.push(Layer { path, sha256: metadata::sha256_file(&current).ok(), origin: extractor.name().to_string() });
+        // the inner layers are not needed once unpacked
+        if layers.len() > 1 {
+            std::fs::remove_file(&current)?;
+        }
+
+        let inner: PathBuf = match single_file(&root)? {
+            Some(inner) if layers.len() < MAX_LAYERS && extractors.detect(&inner)?.is_some() => inner,
+            _ => break,
+        };
+        path = inner.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        current = inner;
+    }
+    Ok(ExtractedImage { root, layers, _directory: directory })
+}
+
+// the only entry of `directory` when it is a regular file
+fn single_file(directory: &Path) -> Result<Option<PathBuf>, Error> {
+    let mut entries = std::fs::read_dir(directory)?;
+    let (first, second) = (entries.next().transpose()?, entries.next().transpose()?);
+    match (first, second) {
+        (Some(entry), None) if entry.file_type()?.is_file() => Ok(Some(entry.path())),
+        _ => Ok(None),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_extract_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_size(2);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "etc/passwd", &b"ok"[..]).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        let image: PathBuf = dir.path().join("rootfs.tar.xz");
+        std::fs::write(&image, encoder.finish().unwrap()).unwrap();
+
+        let extracted: ExtractedImage = extract_image(&Extractors::new(&[]), &image, &mut Budget::new(4096)).unwrap();
+        assert_eq!(std::fs::read(extracted.root.join("etc/passwd")).unwrap(), b"ok");
+        let origins: Vec<(&str, &str)> = extracted.layers.iter().map(|layer| (layer.path.as_str(), layer.origin.as_str())).collect();
+        assert_eq!(origins, vec![(image.to_str().unwrap(), "xz"), ("rootfs.tar", "tar")]);
+        assert_eq!(extracted.layers[0].sha256, metadata::sha256_file(&image).ok());
+
+        let root: PathBuf = extracted.root.clone();
+        drop(extracted);
+        assert!(!root.exists());
+
+        std::fs::write(dir.path().join("blob"), b"\x00").unwrap();
+        assert!(matches!(extract_image(&Extractors::new(&[]), &dir.path().join("blob"), &mut Budget::new(4096)), Err(Error::UnknownFormat(_))));
+        // the tar is larger than 1000 bytes
+        assert!(matches!(extract_image(&Extractors::new(&[]), &image, &mut Budget::new(1000)), Err(Error::LimitExceeded(1000))));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod extractor;
+pub mod cpio;
+pub mod image;
+pub mod error;
\ No newline at end of file
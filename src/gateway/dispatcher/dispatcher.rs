@@ -1,52 +1,134 @@
 use pyo3::prelude::*;
 use pyo3::types::PyTuple;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
 use libloading::{Library, Symbol};
+use crate::config::analyzer::{Analyzer, Isolation, Limits, Protocol};
 use crate::gateway::dispatcher::error::Error;
+use crate::gateway::dispatcher::process;
+use crate::gateway::dispatcher::runner::{AnalyzerRunner, Runners};
+use crate::gateway::dispatcher::so_host::Host;
 extern crate libc;
 
+// Analyzers run on several worker threads. dlopen itself is thread safe, but the initializers of a plugin
+// are not guaranteed to be, so libraries are loaded one at a time.
+static LIBRARY_LOAD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 #[repr(C)]
 pub struct OutputData {
     data: *mut u8,
     len: usize,
 }
 
-//fn execute_shared_object(script_dir: &str, script_name: &str, args: &Value) -> Result<String, Error> {
-//    let lib_path = format!("{}/lib{}.so", script_dir, script_name);
-//    let data_string = args.to_string();
-//    let handler = std::thread::spawn(move || {
-//        let lib = unsafe { Library::new(&lib_path).unwrap() };
-//        type AnalyzerMainFunc = unsafe extern "C" fn(*const u8, usize) -> *mut u8;
-//        let func: Symbol<AnalyzerMainFunc> = unsafe { lib.get(b"analyzer_main").unwrap() };
-//        let data_bytes = data_string.as_bytes();
-//        let result_ptr = unsafe {func(data_bytes.as_ptr(), data_bytes.len()) };
-//        unsafe {String::from_raw_parts(result_ptr, data_bytes.len(), data_bytes.len())}
-//    });
-//
-//    handler.join().map_err(|_| Error::SoPanicError())
-//}
+// the version of the `.so` ABI in include/faaf_analyzer.h, returned by `analyzer_abi_version`
+pub const SO_ABI_VERSION: u32 = 1;
 
+// A loaded `.so` analyzer. With `legacy_abi`, a library without `analyzer_abi_version` uses the version 0
+// ABI, where `analyzer_main` returns the output allocated by rust.
+pub struct SharedObject {
+    library: Library,
+    abi_version: u32,
+}
 
-fn execute_shared_object(script_dir: &str, script_name: &str, args: &Value) -> Result<String, Error> {
-    std::panic::catch_unwind(|| {
-        let lib_path = format!("{}/lib{}.so", script_dir, script_name);
-        let lib = unsafe { Library::new(&lib_path) }?;
-        type AnalyzerMainFunc = unsafe extern "C" fn(*const u8, usize) -> OutputData;
-        let func: Symbol<AnalyzerMainFunc> = unsafe { lib.get(b"analyzer_main")? };
-
-        let json_string = unsafe {
-            let data_string = args.to_string();
-            let data_bytes = data_string.as_bytes();
-            let output_data = func(data_bytes.as_ptr(), data_bytes.len());
-            let result_ptr = output_data.data;
-            let result_len = output_data.len;
-            let result_string = String::from_raw_parts(result_ptr, result_len, result_len);
-            //libc::free(result_ptr as *mut libc::c_void);
-            result_string
+impl SharedObject {
+    // loads the library and checks that it exports the functions of its ABI version
+    pub fn load(lib_path: &std::path::Path, legacy_abi: bool) -> Result<SharedObject, Error> {
+        let library: Library = {
+            let _guard = LIBRARY_LOAD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            unsafe { Library::new(lib_path) }?
+        };
+        type AbiVersionFunc = unsafe extern "C" fn() -> u32;
+        let abi_version: u32 = match unsafe { library.get::<AbiVersionFunc>(b"analyzer_abi_version") } {
+            Ok(func) => unsafe { func() },
+            Err(_) if legacy_abi => 0,
+            Err(_) => return Err(Error::SoAbiMissing()),
+        };
+        if abi_version > SO_ABI_VERSION {
+            return Err(Error::SoAbiUnsupported(abi_version));
+        }
+        unsafe { library.get::<*const libc::c_void>(b"analyzer_main")? };
+        if abi_version >= 1 {
+            unsafe { library.get::<*const libc::c_void>(b"analyzer_free")? };
+        }
+        Ok(SharedObject { library, abi_version })
+    }
+
+    // calls `analyzer_main` with the JSON argument
+    pub fn call(&self, input: &str) -> Result<String, Error> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match self.abi_version {
+            0 => self.call_v0(input),
+            _ => self.call_v1(input),
+        })).map_err(|_| Error::SoPanicError())?
+    }
+
+    // the output is copied before it is handed back to `analyzer_free`, whatever the return code. a null output is
+    // not handed back.
+    fn call_v1(&self, input: &str) -> Result<String, Error> {
+        type AnalyzerMainFunc = unsafe extern "C" fn(*const u8, usize, *mut OutputData) -> i32;
+        type AnalyzerFreeFunc = unsafe extern "C" fn(OutputData);
+        let func: Symbol<AnalyzerMainFunc> = unsafe { self.library.get(b"analyzer_main")? };
+        let free: Symbol<AnalyzerFreeFunc> = unsafe { self.library.get(b"analyzer_free")? };
+
+        let mut output_data: OutputData = OutputData { data: std::ptr::null_mut(), len: 0 };
+        let code: i32 = unsafe { func(input.as_ptr(), input.len(), &mut output_data) };
+        // an error may come without a message
+        let output: Option<Vec<u8>> = if output_data.data.is_null() {
+            None
+        } else {
+            let output: Vec<u8> = unsafe { std::slice::from_raw_parts(output_data.data, output_data.len) }.to_vec();
+            unsafe { free(output_data) };
+            Some(output)
         };
 
-        Ok(json_string)
-    }).map_err(|_| Error::SoPanicError())?
+        if code != 0 {
+            return Err(Error::SoFailed(code, String::from_utf8_lossy(&output.unwrap_or_default()).into_owned()));
+        }
+        let output: Vec<u8> = output.ok_or(Error::SoNullOutput())?;
+        String::from_utf8(output).map_err(|err| Error::SoInvalidUtf8(err.utf8_error()))
+    }
+
+    fn call_v0(&self, input: &str) -> Result<String, Error> {
+        type AnalyzerMainFunc = unsafe extern "C" fn(*const u8, usize) -> OutputData;
+        let func: Symbol<AnalyzerMainFunc> = unsafe { self.library.get(b"analyzer_main")? };
+
+        let output_data: OutputData = unsafe { func(input.as_ptr(), input.len()) };
+        if output_data.data.is_null() {
+            return Err(Error::SoNullOutput());
+        }
+        let output: Vec<u8> = unsafe { Vec::from_raw_parts(output_data.data, output_data.len, output_data.len) };
+        String::from_utf8(output).map_err(|err| Error::SoInvalidUtf8(err.utf8_error()))
+    }
+}
+
+// loads the library and calls `analyzer_main` with the JSON argument. used by the `faaf-so-host` helper.
+pub fn call_shared_object(lib_path: &std::path::Path, input: &str, legacy_abi: bool) -> Result<String, Error> {
+    SharedObject::load(lib_path, legacy_abi)?.call(input)
+}
+
+// runs the library in the `faaf-so-host` helper, so that limits can be enforced on it.
+fn execute_shared_object_process(script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+    let lib_path: std::path::PathBuf = script_dir.join(format!("lib{}.so", analyzer.name));
+    let mut command = so_host_command(analyzer.legacy_abi)?;
+    command.arg(lib_path);
+    let output: process::ProcessOutput = process::run(command, Some(args.to_string().into_bytes()), &analyzer.limits)?;
+    process_output_string(output)
+}
+
+fn so_host_command(legacy_abi: bool) -> Result<std::process::Command, Error> {
+    let mut command = std::process::Command::new(so_host_path()?);
+    if legacy_abi {
+        command.arg("--legacy-abi");
+    }
+    Ok(command)
+}
+
+// the helper is installed next to the faaf executable. FAAF_SO_HOST overrides it.
+fn so_host_path() -> Result<std::path::PathBuf, Error> {
+    if let Some(path) = std::env::var_os("FAAF_SO_HOST") {
+        return Ok(std::path::PathBuf::from(path));
+    }
+    Ok(std::env::current_exe()?.with_file_name("faaf-so-host"))
 }
 
 //fn execute_shared_object(script_dir: &str, script_name: &str, args: &Value) -> Result<String, Error> {
@@ -67,6 +149,7 @@ fn execute_shared_object(script_dir: &str, script_name: &str, args: &Value) -> R
 //    }).map_err(|_| Error::SoPanicError())?
 //}
 
+// with_gil serializes python analyzers running on different worker threads.
 fn execute_python(script_dir: &str, script_name: &str, args: &Value) -> Result<String, Error> {
     pyo3::Python::with_gil(|py| {
         let sys = py.import("sys")?;
@@ -84,44 +167,340 @@ fn execute_python(script_dir: &str, script_name: &str, args: &Value) -> Result<S
     })
 }
 
-fn execute_sh(script_dir: &str, script_name: &str, args: &Value) -> Result<String, Error> {
-    let script_path = format!("{}/{}.sh", script_dir, script_name);
-    let data_string = args.to_string();
-
-    let output: std::process::Output = std::process::Command::new("sh")
-        .arg(&script_path)
-        .arg(data_string)
-        .output()?;
-
-    if output.status.success() {
-        let output_str = String::from_utf8_lossy(&output.stdout).into_owned();
-        Ok(output_str)
-    } else {
-        let error_str = String::from_utf8_lossy(&output.stderr).into_owned();
-        Ok(format!( "{{ \"error\": \"{}\" }} ", error_str))
-    }
-}
-
-pub fn execute_analyzer(script_dir: &std::path::Path, analyzer_name: &str, extension: &str, args: &Value) -> Result<Value, Error> {
-    let script_dir_str = script_dir.to_str().unwrap();
-
-
-    match extension {
-        "py" => {
-            let json_string = execute_python(script_dir_str, analyzer_name, args)?;
-            let json_value: Value = serde_json::from_str(&json_string)?;
-            Ok(json_value)
-        },
-        "so" => {
-            let json_string = execute_shared_object(script_dir_str, analyzer_name, args)?;
-            let json_value: Value = serde_json::from_str(&json_string)?;
-            Ok(json_value)
-        },
-        "sh" => {
-            let json_string = execute_sh(script_dir_str, analyzer_name, args)?;
-            let json_value: Value = serde_json::from_str(&json_string)?;
-            Ok(json_value)
-        },
-        _ => return Err(Error::UndefinedExtensionError()),
+// The analyzer's prints are sent to stderr so that stdout only carries the returned JSON.
+const PYTHON_RUNNER: &str = "\
+import importlib, sys
+sys.path.append(sys.argv[1])
+stdout = sys.stdout
+sys.stdout = sys.stderr
+result = importlib.import_module(sys.argv[2]).analyzer_main(sys.stdin.read())
+stdout.write(result)
+stdout.flush()
+";
+
+// runs the script in its own python interpreter, so that limits can be enforced on it.
+fn execute_python_process(script_dir: &str, script_name: &str, args: &Value, limits: &Limits) -> Result<String, Error> {
+    let mut command = std::process::Command::new("python3");
+    command.arg("-c").arg(PYTHON_RUNNER).arg(script_dir).arg(script_name);
+    let output: process::ProcessOutput = process::run(command, Some(args.to_string().into_bytes()), limits)?;
+    process_output_string(output)
+}
+
+fn process_output_string(output: process::ProcessOutput) -> Result<String, Error> {
+    if !output.status.success() {
+        return Err(Error::ProcessFailed(output.status, String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn execute_sh(script_dir: &std::path::Path, script_name: &str, args: &Value, protocol: Protocol, limits: &Limits) -> Result<String, Error> {
+    let mut command = std::process::Command::new("sh");
+    command.arg(script_dir.join(format!("{}.sh", script_name)));
+    execute_process(command, args, protocol, limits)
+}
+
+// Runs `command` of the analyzer, or the executable named after the analyzer in the script directory with
+// `interpreter` in front of it. `{script_dir}` in `command` is replaced by the script directory.
+fn execute_exe(script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+    let argv: Vec<String> = match &analyzer.command {
+        Some(command) => command.iter().map(|part| part.replace("{script_dir}", &script_dir.to_string_lossy())).collect(),
+        None => analyzer.interpreter.iter().cloned().chain(std::iter::once(script_dir.join(&analyzer.name).to_string_lossy().into_owned())).collect(),
+    };
+    let (program, arguments): (&String, &[String]) = argv.split_first().ok_or(Error::UndefinedExtensionError())?;
+    let mut command = std::process::Command::new(program);
+    command.args(arguments);
+    execute_process(command, args, analyzer.protocol, &analyzer.limits)
+}
+
+// The JSON I/O of sh and exe analyzers: the arguments on stdin (or as the last command line argument), the output
+// on stdout. A process that fails is an error with its stderr and exit code, like a python or so analyzer run in a process.
+fn execute_process(mut command: std::process::Command, args: &Value, protocol: Protocol, limits: &Limits) -> Result<String, Error> {
+    let stdin: Option<Vec<u8>> = match protocol {
+        Protocol::Stdin => Some(args.to_string().into_bytes()),
+        Protocol::Argv => {
+            command.arg(args.to_string());
+            None
+        }
+    };
+    let output: process::ProcessOutput = process::run(command, stdin, limits)?;
+    process_output_string(output)
+}
+
+// py analyzers run in the embedded interpreter, or in python3 with isolation = "process"
+pub struct PythonRunner;
+
+impl AnalyzerRunner for PythonRunner {
+    fn run(&self, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        let script_dir_str: &str = &script_dir.to_string_lossy();
+        match analyzer.isolation {
+            Isolation::InProcess => execute_python(script_dir_str, &analyzer.name, args),
+            Isolation::Process | Isolation::Host => execute_python_process(script_dir_str, &analyzer.name, args, &analyzer.limits),
+        }
+    }
+}
+
+// so analyzers are loaded into faaf, into a faaf-so-host helper started for each file with isolation = "process",
+// or into faaf-so-host helpers kept running with isolation = "host". The libraries loaded into faaf and the
+// hosts are kept from `prepare` to `shutdown`, so each library is loaded once per run, or once per host.
+#[derive(Default)]
+pub struct SharedObjectRunner {
+    libraries: std::sync::Mutex<HashMap<std::path::PathBuf, Arc<SharedObject>>>,
+    // the idle hosts of each library. a worker takes one, or starts one when none is idle.
+    hosts: std::sync::Mutex<HashMap<std::path::PathBuf, Vec<Host>>>,
+}
+
+impl SharedObjectRunner {
+    fn library(&self, lib_path: std::path::PathBuf, analyzer: &Analyzer) -> Result<Arc<SharedObject>, Error> {
+        let mut libraries = self.libraries.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(library) = libraries.get(&lib_path) {
+            return Ok(library.clone());
+        }
+        let library: Arc<SharedObject> = Arc::new(SharedObject::load(&lib_path, analyzer.legacy_abi)?);
+        libraries.insert(lib_path, library.clone());
+        Ok(library)
+    }
+
+    fn host(&self, lib_path: &std::path::Path, analyzer: &Analyzer) -> Result<Host, Error> {
+        let idle: Option<Host> = self.hosts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_mut(lib_path).and_then(Vec::pop);
+        match idle {
+            Some(host) => Ok(host),
+            None => {
+                let mut command = so_host_command(analyzer.legacy_abi)?;
+                command.arg("--serve").arg(lib_path);
+                Host::spawn(command, &analyzer.limits)
+            }
+        }
+    }
+
+    // a host that crashed, timed out or sent something else than a response is dropped, and the next file starts a new one
+    fn call_host(&self, lib_path: std::path::PathBuf, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        let mut host: Host = self.host(&lib_path, analyzer)?;
+        let output: Result<String, Error> = host.call(&args.to_string(), analyzer.limits.timeout);
+        if host.is_running() {
+            self.hosts.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).entry(lib_path).or_default().push(host);
+        }
+        output
+    }
+}
+
+impl AnalyzerRunner for SharedObjectRunner {
+    // a library that cannot be loaded stops the run
+    fn prepare(&self, script_dir: &std::path::Path, analyzer: &Analyzer) -> Result<(), Error> {
+        let lib_path: std::path::PathBuf = script_dir.join(format!("lib{}.so", analyzer.name));
+        match analyzer.isolation {
+            Isolation::InProcess => {
+                self.library(lib_path, analyzer)?;
+            }
+            Isolation::Host => {
+                let host: Host = self.host(&lib_path, analyzer)?;
+                self.hosts.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).entry(lib_path).or_default().push(host);
+            }
+            Isolation::Process => {}
+        }
+        Ok(())
+    }
+
+    fn run(&self, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        let lib_path: std::path::PathBuf = script_dir.join(format!("lib{}.so", analyzer.name));
+        match analyzer.isolation {
+            Isolation::InProcess => self.library(lib_path, analyzer)?.call(&args.to_string()),
+            Isolation::Process => execute_shared_object_process(script_dir, analyzer, args),
+            Isolation::Host => self.call_host(lib_path, analyzer, args),
+        }
+    }
+
+    fn shutdown(&self) {
+        self.libraries.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+        self.hosts.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+}
+
+pub struct ShellRunner;
+
+impl AnalyzerRunner for ShellRunner {
+    fn run(&self, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        execute_sh(script_dir, &analyzer.name, args, analyzer.protocol, &analyzer.limits)
+    }
+}
+
+pub struct ExecutableRunner;
+
+impl AnalyzerRunner for ExecutableRunner {
+    fn run(&self, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        execute_exe(script_dir, analyzer, args)
+    }
+}
+
+// runs the analyzer with the runner of its extension and parses its output
+pub fn execute_analyzer(runners: &Runners, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<Value, Error> {
+    let json_string: String = runners.get(&analyzer.extension)?.run(script_dir, analyzer, args)?;
+
+    // an in-process analyzer can only be checked once it returned
+    if let Some(limit) = analyzer.limits.max_output_bytes {
+        if json_string.len() as u64 > limit {
+            return Err(Error::OutputLimitExceeded(limit));
+        }
+    }
+
+    let json_value: Value = serde_json::from_str(&json_string)?;
+    Ok(json_value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::Config;
+
+    fn analyzer(toml_str: &str) -> Analyzer {
+        let config: Config = toml::from_str(toml_str).unwrap();
+        config.analyzer.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_execute_sh() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("stdin.sh"), "cat").unwrap();
+        std::fs::write(dir.path().join("argv.sh"), "printf '%s' \"$1\"").unwrap();
+        std::fs::write(dir.path().join("fail.sh"), "echo 'no \"elf\" header' >&2; exit 3").unwrap();
+        let args: Value = serde_json::json!({"filename": "bin/busybox", "argument1": "a'b\"c"});
+
+        let stdin: Analyzer = analyzer("[[analyzer]]\nname = \"stdin\"\nextension = \"sh\"");
+        assert_eq!(stdin.protocol, Protocol::Stdin);
+        assert_eq!(execute_analyzer(&Runners::new(), dir.path(), &stdin, &args).unwrap(), args);
+        let argv: Analyzer = analyzer("[[analyzer]]\nname = \"argv\"\nextension = \"sh\"\nprotocol = \"argv\"");
+        assert_eq!(execute_analyzer(&Runners::new(), dir.path(), &argv, &args).unwrap(), args);
+
+        let fail: Analyzer = analyzer("[[analyzer]]\nname = \"fail\"\nextension = \"sh\"");
+        let err: Error = execute_analyzer(&Runners::new(), dir.path(), &fail, &args).unwrap_err();
+        assert_eq!(err.kind(), "process");
+        assert_eq!(err.exit_code(), Some(3));
+        assert_eq!(err.stderr().as_deref(), Some("no \"elf\" header\n"));
+
+        assert!(toml::from_str::<Config>("[[analyzer]]\nname = \"a\"\nextension = \"py\"\nprotocol = \"argv\"").is_err());
+    }
+
+    fn which_cat() -> std::path::PathBuf {
+        ["/bin/cat", "/usr/bin/cat"].iter().map(std::path::PathBuf::from).find(|path| path.exists()).unwrap()
+    }
+
+    #[test]
+    fn test_execute_exe() {
+        let dir = tempfile::tempdir().unwrap();
+        // an executable written by the test could be busy in a process forked by another test
+        std::os::unix::fs::symlink(which_cat(), dir.path().join("native")).unwrap();
+        std::fs::write(dir.path().join("interpreted"), "import sys; sys.stdout.write(sys.stdin.read())").unwrap();
+        std::fs::write(dir.path().join("tool.py"), "import json, sys; print(json.dumps({'argv': sys.argv[1:-1]}))").unwrap();
+        let args: Value = serde_json::json!({"filename": "bin/busybox"});
+
+        let native: Analyzer = analyzer("[[analyzer]]\nname = \"native\"\nextension = \"exe\"");
+        assert_eq!(execute_analyzer(&Runners::new(), dir.path(), &native, &args).unwrap(), args);
+        let interpreted: Analyzer = analyzer("[[analyzer]]\nname = \"interpreted\"\nextension = \"exe\"\ninterpreter = [\"python3\"]");
+        assert_eq!(execute_analyzer(&Runners::new(), dir.path(), &interpreted, &args).unwrap(), args);
+        let command: Analyzer = analyzer(r#"
+            [[analyzer]]
+            name = "tool"
+            extension = "exe"
+            command = ["python3", "{script_dir}/tool.py", "--json"]
+            protocol = "argv"
+        "#);
+        assert_eq!(execute_analyzer(&Runners::new(), dir.path(), &command, &args).unwrap(), serde_json::json!({"argv": ["--json"]}));
+
+        assert!(toml::from_str::<Config>("[[analyzer]]\nname = \"a\"\nextension = \"sh\"\ninterpreter = [\"bash\"]").is_err());
+        assert!(toml::from_str::<Config>("[[analyzer]]\nname = \"a\"\nextension = \"exe\"\ncommand = []").is_err());
+    }
+
+    const PLUGIN: &str = r#"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "faaf_analyzer.h"
+
+#ifndef ABI_VERSION
+#define ABI_VERSION FAAF_ANALYZER_ABI_VERSION
+#endif
+
+static int freed = 0;
+
+#ifndef NO_ABI_VERSION
+uint32_t analyzer_abi_version(void) { return ABI_VERSION; }
+#endif
+
+static void set(faaf_output *output, const char *text) {
+    output->len = strlen(text);
+    output->data = malloc(output->len);
+    memcpy(output->data, text, output->len);
+}
+
+int32_t analyzer_main(const uint8_t *input, size_t input_len, faaf_output *output) {
+    char text[64];
+    if (input_len == 6 && memcmp(input, "\"fail\"", 6) == 0) {
+        set(output, "no elf header");
+        return 7;
+    }
+    if (input_len == 6 && memcmp(input, "\"null\"", 6) == 0) {
+        return FAAF_ANALYZER_OK;
+    }
+    if (input_len == 6 && memcmp(input, "\"utf8\"", 6) == 0) {
+        set(output, "\"\xff\"");
+        return FAAF_ANALYZER_OK;
+    }
+    snprintf(text, sizeof(text), "{\"freed\": %d}", freed);
+    set(output, text);
+    return FAAF_ANALYZER_OK;
+}
+
+void analyzer_free(faaf_output output) {
+    free(output.data);
+    freed++;
+}
+"#;
+
+    // builds the plugin against include/faaf_analyzer.h
+    fn build_plugin(dir: &std::path::Path, name: &str, defines: &[&str]) {
+        let source: std::path::PathBuf = dir.join("plugin.c");
+        std::fs::write(&source, PLUGIN).unwrap();
+        let status = std::process::Command::new("cc")
+            .args(["-shared", "-fPIC", "-I"]).arg(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("include"))
+            .args(defines).arg("-o").arg(dir.join(format!("lib{}.so", name))).arg(&source)
+            .status().unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_execute_shared_object() {
+        let dir = tempfile::tempdir().unwrap();
+        build_plugin(dir.path(), "plugin", &[]);
+        build_plugin(dir.path(), "future", &["-DABI_VERSION=2"]);
+        build_plugin(dir.path(), "unversioned", &["-DNO_ABI_VERSION"]);
+        let plugin: Analyzer = analyzer("[[analyzer]]\nname = \"plugin\"\nextension = \"so\"");
+        let runners: Runners = Runners::new();
+        runners.prepare(dir.path(), [&plugin]).unwrap();
+
+        // the library is loaded once, so the count of freed outputs goes on
+        assert_eq!(execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!({})).unwrap(), serde_json::json!({"freed": 0}));
+        assert_eq!(execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!({})).unwrap(), serde_json::json!({"freed": 1}));
+
+        let err: Error = execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!("fail")).unwrap_err();
+        assert_eq!(err.kind(), "so_failed");
+        assert_eq!(err.exit_code(), Some(7));
+        assert_eq!(err.stderr().as_deref(), Some("no elf header"));
+        let err: Error = execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!("utf8")).unwrap_err();
+        assert_eq!(err.kind(), "so_utf8");
+        // a null output is not handed back to analyzer_free
+        assert_eq!(execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!("null")).unwrap_err().kind(), "so_null");
+        assert_eq!(execute_analyzer(&runners, dir.path(), &plugin, &serde_json::json!({})).unwrap(), serde_json::json!({"freed": 4}));
+
+        let future: Analyzer = analyzer("[[analyzer]]\nname = \"future\"\nextension = \"so\"");
+        assert!(matches!(runners.prepare(dir.path(), [&future]), Err(Error::SoAbiUnsupported(2))));
+        // the version 0 ABI frees the output with the allocator of rust, so it has to be asked for
+        let unversioned: Analyzer = analyzer("[[analyzer]]\nname = \"unversioned\"\nextension = \"so\"");
+        assert!(matches!(runners.prepare(dir.path(), [&unversioned]), Err(Error::SoAbiMissing())));
+        assert!(analyzer("[[analyzer]]\nname = \"unversioned\"\nextension = \"so\"\nlegacy_abi = true").legacy_abi);
+        assert!(toml::from_str::<Config>("[[analyzer]]\nname = \"a\"\nextension = \"py\"\nlegacy_abi = true").is_err());
+        let missing: Analyzer = analyzer("[[analyzer]]\nname = \"missing\"\nextension = \"so\"");
+        assert!(matches!(runners.prepare(dir.path(), [&missing]), Err(Error::SoError(_))));
+        runners.shutdown();
     }
 }
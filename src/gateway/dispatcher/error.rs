@@ -9,6 +9,26 @@ pub enum Error {
     SoError(libloading::Error),
     ShError(std::io::Error),
     SoPanicError(),
+    Timeout(std::time::Duration),
+    OutputLimitExceeded(u64),
+    ProcessFailed(std::process::ExitStatus, String),
+    // the `emit` key of the output is not a list of files that can be analyzed
+    EmitError(String),
+    // no runner is registered for the extension of an analyzer
+    UnknownRunner(String),
+    // `analyzer_abi_version` of a library returned a version this faaf does not know
+    SoAbiUnsupported(u32),
+    // the library has no `analyzer_abi_version` and the analyzer does not set legacy_abi
+    SoAbiMissing(),
+    // `analyzer_main` of a library succeeded without an output
+    SoNullOutput(),
+    // `analyzer_main` of a library returned this code and error message
+    SoFailed(i32, String),
+    SoInvalidUtf8(std::str::Utf8Error),
+    // the faaf-so-host process of an analyzer with isolation = "host" died: its exit code or signal, and its stderr
+    SoHostCrashed(Option<i32>, Option<i32>, String),
+    // the faaf-so-host process reported an error other than a return code of the library
+    SoHostFailed(String),
 }
 
 impl fmt::Display for Error {
@@ -20,6 +40,81 @@ impl fmt::Display for Error {
             Error::SoError(err) => write!(f, "so error {}", err),
             Error::SoPanicError() => write!(f, "so panic error"),
             Error::ShError(err) => write!(f, "sh error {}", err),
+            Error::Timeout(timeout) => write!(f, "analyzer timed out after {:?}", timeout),
+            Error::OutputLimitExceeded(limit) => write!(f, "analyzer output exceeded {} bytes", limit),
+            Error::ProcessFailed(status, _) => write!(f, "analyzer process failed: {}", status),
+            Error::EmitError(reason) => write!(f, "emit error {}", reason),
+            Error::UnknownRunner(extension) => write!(f, "no runner for the extension `{}`", extension),
+            Error::SoAbiUnsupported(version) => write!(f, "so ABI version {} is not supported", version),
+            Error::SoAbiMissing() => write!(f, "so library does not export analyzer_abi_version (legacy_abi = true calls it with the deprecated version 0 ABI)"),
+            Error::SoNullOutput() => write!(f, "so analyzer returned a null output"),
+            Error::SoFailed(code, _) => write!(f, "so analyzer returned {}", code),
+            Error::SoInvalidUtf8(err) => write!(f, "so output is not UTF-8: {}", err),
+            Error::SoHostCrashed(code, signal, _) => match (code, signal) {
+                (_, Some(signal)) => write!(f, "so analyzer host was killed by signal {}", signal),
+                (Some(code), None) => write!(f, "so analyzer host exited with status {}", code),
+                (None, None) => write!(f, "so analyzer host exited"),
+            },
+            Error::SoHostFailed(message) => write!(f, "so analyzer host error: {}", message),
+        }
+    }
+}
+
+impl Error {
+    // short name of the error stored in the `kind` column of the `analyzer_error` table
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::UndefinedExtensionError() => "undefined_extension",
+            Error::PythonError(_) => "python",
+            Error::JsonError(_) => "json",
+            Error::SoError(_) => "so",
+            Error::SoPanicError() => "so_panic",
+            Error::ShError(_) => "sh",
+            Error::Timeout(_) => "timeout",
+            Error::OutputLimitExceeded(_) => "output_limit",
+            Error::ProcessFailed(_, _) => "process",
+            Error::EmitError(_) => "emit",
+            Error::UnknownRunner(_) => "undefined_extension",
+            Error::SoAbiUnsupported(_) => "so",
+            Error::SoAbiMissing() => "so",
+            Error::SoNullOutput() => "so_null",
+            Error::SoFailed(_, _) => "so_failed",
+            Error::SoInvalidUtf8(_) => "so_utf8",
+            Error::SoHostCrashed(_, _, _) => "so_crash",
+            Error::SoHostFailed(_) => "so_host",
+        }
+    }
+
+    // what the analyzer wrote to stderr, the traceback of a python exception, or the error message of a so analyzer
+    pub fn stderr(&self) -> Option<String> {
+        match self {
+            Error::PythonError(err) => pyo3::Python::with_gil(|py| {
+                err.traceback(py).and_then(|traceback| traceback.format().ok())
+            }),
+            Error::ProcessFailed(_, stderr) => Some(stderr.clone()),
+            Error::SoFailed(_, message) => Some(message.clone()),
+            Error::SoHostCrashed(_, _, stderr) => Some(stderr.clone()),
+            _ => None,
+        }
+    }
+
+    // the return code of a so analyzer in faaf. None when the process was killed by a signal, or when
+    // the analyzer failed in another way.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Error::ProcessFailed(status, _) => status.code(),
+            Error::SoFailed(code, _) => Some(*code),
+            Error::SoHostCrashed(code, _, _) => *code,
+            _ => None,
+        }
+    }
+
+    // the signal that killed the process of the analyzer
+    pub fn signal(&self) -> Option<i32> {
+        match self {
+            Error::ProcessFailed(status, _) => std::os::unix::process::ExitStatusExt::signal(status),
+            Error::SoHostCrashed(_, signal, _) => *signal,
+            _ => None,
         }
     }
 }
@@ -33,6 +128,18 @@ impl std::error::Error for Error {
             Error::SoError(err) => Some(err),
             Error::SoPanicError() =>  None,
             Error::ShError(err) =>  Some(err),
+            Error::Timeout(_) => None,
+            Error::OutputLimitExceeded(_) => None,
+            Error::ProcessFailed(_, _) => None,
+            Error::EmitError(_) => None,
+            Error::UnknownRunner(_) => None,
+            Error::SoAbiUnsupported(_) => None,
+            Error::SoAbiMissing() => None,
+            Error::SoNullOutput() => None,
+            Error::SoFailed(_, _) => None,
+            Error::SoInvalidUtf8(err) => Some(err),
+            Error::SoHostCrashed(_, _, _) => None,
+            Error::SoHostFailed(_) => None,
         }
     }
 }
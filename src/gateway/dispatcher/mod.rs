@@ -1,2 +1,5 @@
 pub mod dispatcher;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod process;
+pub mod runner;
+pub mod so_host;
\ No newline at end of file
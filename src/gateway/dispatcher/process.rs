@@ -0,0 +1,216 @@
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::config::analyzer::Limits;
+use crate::gateway::dispatcher::error::Error;
+extern crate libc;
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+// how long the pipes of an analyzer that exited are still read, when a process it started outside of its
+// process group keeps them open
+const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+// the end of the stderr of an analyzer that is kept. stderr does not count toward max_output_bytes.
+pub const MAX_STDERR_BYTES: usize = 64 * 1024;
+
+pub struct ProcessOutput {
+    pub status: std::process::ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+// Runs an analyzer in a child process under the limits of the analyzer.
+// The child gets its own process group, so a timeout also kills whatever the analyzer started.
+pub fn run(mut command: std::process::Command, stdin: Option<Vec<u8>>, limits: &Limits) -> Result<ProcessOutput, Error> {
+    command
+        .stdin(if stdin.is_some() { std::process::Stdio::piped() } else { std::process::Stdio::null() })
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+
+    isolate(&mut command, limits.max_memory);
+
+    let mut child: std::process::Child = command.spawn()?;
+    let pid: libc::pid_t = child.id() as libc::pid_t;
+    let exceeded: std::sync::Arc<AtomicBool> = std::sync::Arc::new(AtomicBool::new(false));
+
+    // the writer is not waited for: the analyzer may exit without reading all of its input
+    if let (Some(mut pipe), Some(data)) = (child.stdin.take(), stdin) {
+        std::thread::spawn(move || {
+            let _ = pipe.write_all(&data);
+        });
+    }
+    let stdout: std::sync::Arc<std::sync::Mutex<Vec<u8>>> = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let stderr: std::sync::Arc<std::sync::Mutex<Vec<u8>>> = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let (done_sender, done) = std::sync::mpsc::channel::<()>();
+    let mut readers: usize = 0;
+    if let Some(pipe) = child.stdout.take() {
+        let (stdout, exceeded, done_sender) = (std::sync::Arc::clone(&stdout), std::sync::Arc::clone(&exceeded), done_sender.clone());
+        let limit: Option<u64> = limits.max_output_bytes;
+        std::thread::spawn(move || {
+            read_capped(pipe, limit, pid, &exceeded, &stdout);
+            let _ = done_sender.send(());
+        });
+        readers += 1;
+    }
+    if let Some(pipe) = child.stderr.take() {
+        let (stderr, done_sender) = (std::sync::Arc::clone(&stderr), done_sender.clone());
+        std::thread::spawn(move || {
+            read_tail(pipe, &stderr);
+            let _ = done_sender.send(());
+        });
+        readers += 1;
+    }
+
+    let deadline: Option<std::time::Instant> = limits.timeout.map(|timeout| std::time::Instant::now() + timeout);
+    let mut timed_out: bool = false;
+    let status: std::process::ExitStatus = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            kill_group(pid);
+            timed_out = true;
+            break child.wait()?;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    // what the analyzer left running in its process group
+    kill_group(pid);
+    let drain_deadline: std::time::Instant = std::time::Instant::now() + DRAIN_TIMEOUT;
+    for _ in 0..readers {
+        if done.recv_timeout(drain_deadline.saturating_duration_since(std::time::Instant::now())).is_err() {
+            break;
+        }
+    }
+    let stdout: Vec<u8> = std::mem::take(&mut *stdout.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    let stderr: Vec<u8> = std::mem::take(&mut *stderr.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+
+    if timed_out {
+        return Err(Error::Timeout(limits.timeout.unwrap_or_default()));
+    }
+    if exceeded.load(Ordering::SeqCst) {
+        return Err(Error::OutputLimitExceeded(limits.max_output_bytes.unwrap_or_default()));
+    }
+
+    Ok(ProcessOutput { status, stdout, stderr })
+}
+
+// Puts the child in its own process group, so that kill_group also kills whatever it started, and limits
+// its address space to `max_memory` bytes.
+pub fn isolate(command: &mut std::process::Command, max_memory: Option<u64>) {
+    // only async-signal-safe calls are made between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if let Some(bytes) = max_memory {
+                let limit = libc::rlimit { rlim_cur: bytes as libc::rlim_t, rlim_max: bytes as libc::rlim_t };
+                if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+// reads a pipe until EOF into `buffer`. once more than `limit` bytes arrive, the process group is killed.
+fn read_capped<R: Read>(mut pipe: R, limit: Option<u64>, pid: libc::pid_t, exceeded: &AtomicBool, buffer: &std::sync::Mutex<Vec<u8>>) {
+    let mut chunk = [0u8; 8192];
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                let mut buffer = buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if limit.is_some_and(|limit| (buffer.len() + n) as u64 > limit) {
+                    exceeded.store(true, Ordering::SeqCst);
+                    kill_group(pid);
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..n]);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+// reads a pipe until EOF, keeping the last MAX_STDERR_BYTES bytes in `tail`
+pub fn read_tail<R: Read>(mut pipe: R, tail: &std::sync::Mutex<Vec<u8>>) {
+    let mut chunk = [0u8; 8192];
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                let mut tail = tail.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                tail.extend_from_slice(&chunk[..n]);
+                let excess: usize = tail.len().saturating_sub(MAX_STDERR_BYTES);
+                tail.drain(..excess);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+pub fn kill_group(pid: libc::pid_t) {
+    unsafe {
+        libc::kill(-pid, libc::SIGKILL);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> std::process::Command {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn test_run_reads_stdin() {
+        let output = run(sh("cat"), Some(b"{\"a\": 1}".to_vec()), &Limits::default()).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"{\"a\": 1}");
+    }
+
+    #[test]
+    fn test_run_timeout_kills_children() {
+        let limits = Limits { timeout: Some(std::time::Duration::from_millis(100)), ..Default::default() };
+        let start = std::time::Instant::now();
+        let result = run(sh("sleep 10 & sleep 10"), None, &limits);
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_run_output_limit() {
+        let limits = Limits { max_output_bytes: Some(16), ..Default::default() };
+        let result = run(sh("yes"), None, &limits);
+        assert!(matches!(result, Err(Error::OutputLimitExceeded(16))));
+
+        // stderr has its own cap and does not fail the analyzer
+        let output = run(sh("head -c 100000 /dev/zero >&2; echo '{}'"), None, &limits).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"{}\n");
+        assert_eq!(output.stderr.len(), MAX_STDERR_BYTES);
+    }
+
+    #[test]
+    fn test_run_escaped_child() {
+        // a process in its own session keeps stdout open after the analyzer exited
+        let start = std::time::Instant::now();
+        let output = run(sh("setsid sleep 10 & echo '{}'"), None, &Limits::default()).unwrap();
+        assert_eq!(output.stdout, b"{}\n");
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        let limits = Limits { timeout: Some(std::time::Duration::from_millis(100)), ..Default::default() };
+        let start = std::time::Instant::now();
+        assert!(matches!(run(sh("setsid sleep 10 & sleep 10"), None, &limits), Err(Error::Timeout(_))));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,122 @@
+use serde_json::Value;
+use crate::config::analyzer::Analyzer;
+use crate::gateway::dispatcher::dispatcher::{ExecutableRunner, PythonRunner, SharedObjectRunner, ShellRunner};
+use crate::gateway::dispatcher::error::Error;
+
+
+// Runs the analyzers of one kind, the `extension` of their [[analyzer]] section. Runners are shared by the
+// worker threads, so `run` can be called for several files at the same time.
+pub trait AnalyzerRunner: Send + Sync {
+    // called once for every analyzer of the kind before the first file, e.g. to check that its script exists.
+    // an error stops the run before anything is analyzed.
+    fn prepare(&self, _script_dir: &std::path::Path, _analyzer: &Analyzer) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // the output JSON of the analyzer for the arguments of one file
+    fn run(&self, script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error>;
+
+    // called once after the last file, even when the run failed
+    fn shutdown(&self) {}
+}
+
+// A closure is a runner for analyzers written in rust by a program embedding faaf:
+// runners.register("native", Box::new(|analyzer: &Analyzer, args: &Value| Ok(json!({"name": analyzer.name}))))
+impl<F> AnalyzerRunner for F
+where
+    F: Fn(&Analyzer, &Value) -> Result<Value, Error> + Send + Sync,
+{
+    fn run(&self, _script_dir: &std::path::Path, analyzer: &Analyzer, args: &Value) -> Result<String, Error> {
+        Ok(self(analyzer, args)?.to_string())
+    }
+}
+
+// the runners by kind: py, so, sh and exe are built in, and a registered runner replaces the one of its kind
+pub struct Runners {
+    runners: std::collections::HashMap<String, Box<dyn AnalyzerRunner>>,
+}
+
+impl Runners {
+    pub fn new() -> Self {
+        let mut runners: Runners = Runners { runners: std::collections::HashMap::new() };
+        runners.register("py", Box::new(PythonRunner));
+        runners.register("so", Box::new(SharedObjectRunner::default()));
+        runners.register("sh", Box::new(ShellRunner));
+        runners.register("exe", Box::new(ExecutableRunner));
+        runners
+    }
+
+    pub fn register(&mut self, kind: &str, runner: Box<dyn AnalyzerRunner>) {
+        self.runners.insert(kind.to_string(), runner);
+    }
+
+    pub fn get(&self, kind: &str) -> Result<&dyn AnalyzerRunner, Error> {
+        self.runners.get(kind).map(|runner| runner.as_ref()).ok_or_else(|| Error::UnknownRunner(kind.to_string()))
+    }
+
+    pub fn prepare<'c>(&self, script_dir: &std::path::Path, analyzers: impl IntoIterator<Item = &'c Analyzer>) -> Result<(), Error> {
+        for analyzer in analyzers {
+            self.get(&analyzer.extension)?.prepare(script_dir, analyzer)?;
+        }
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        for runner in self.runners.values() {
+            runner.shutdown();
+        }
+    }
+}
+
+impl Default for Runners {
+    fn default() -> Self {
+        Runners::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::Config;
+    use crate::gateway::dispatcher::dispatcher::execute_analyzer;
+
+    struct Counter {
+        prepared: std::sync::atomic::AtomicUsize,
+    }
+
+    impl AnalyzerRunner for Counter {
+        fn prepare(&self, _script_dir: &std::path::Path, _analyzer: &Analyzer) -> Result<(), Error> {
+            self.prepared.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn run(&self, _script_dir: &std::path::Path, _analyzer: &Analyzer, _args: &Value) -> Result<String, Error> {
+            Ok(self.prepared.load(std::sync::atomic::Ordering::SeqCst).to_string())
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let config: Config = toml::from_str(r#"
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+
+            [[analyzer]]
+            name = "counter"
+            extension = "sh"
+        "#).unwrap();
+        let script_dir: &std::path::Path = std::path::Path::new(".");
+        let mut runners: Runners = Runners::new();
+        assert!(matches!(runners.prepare(script_dir, &config.analyzer), Err(Error::UnknownRunner(_))));
+
+        runners.register("rust", Box::new(|analyzer: &Analyzer, args: &Value| Ok(serde_json::json!({"name": analyzer.name, "file": args["filename"]}))));
+        runners.register("sh", Box::new(Counter { prepared: std::sync::atomic::AtomicUsize::new(0) }));
+        runners.prepare(script_dir, &config.analyzer).unwrap();
+
+        let args: Value = serde_json::json!({"filename": "bin/busybox"});
+        assert_eq!(execute_analyzer(&runners, script_dir, &config.analyzer[0], &args).unwrap(), serde_json::json!({"name": "native", "file": "bin/busybox"}));
+        assert_eq!(execute_analyzer(&runners, script_dir, &config.analyzer[1], &args).unwrap(), serde_json::json!(1));
+    }
+}
@@ -0,0 +1,295 @@
+use std::io::{BufRead, Read, Write};
+use std::os::fd::FromRawFd;
+use serde_json::{json, Value};
+use crate::config::analyzer::Limits;
+use crate::gateway::dispatcher::dispatcher::SharedObject;
+use crate::gateway::dispatcher::error::Error;
+use crate::gateway::dispatcher::process;
+extern crate libc;
+
+// A `faaf-so-host --serve` process keeping one library loaded for `isolation = "host"`. The protocol is one
+// JSON object per line: the host sends {"ready": true} once the library is loaded, then answers each line of
+// arguments with {"output": "..."} or {"error": "...", "message": "...", "code": ...}. A crash of the library
+// only kills the host, which is started again for the next file. So is any other line, since the responses
+// of the host and the files would not match anymore.
+pub struct Host {
+    child: std::process::Child,
+    stdin: Option<std::process::ChildStdin>,
+    // None for a line longer than the limit
+    responses: std::sync::mpsc::Receiver<Option<String>>,
+    max_output_bytes: Option<u64>,
+    stderr: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+    stderr_reader: Option<std::thread::JoinHandle<()>>,
+    exited: bool,
+}
+
+impl Host {
+    // starts the host and waits until its library is loaded
+    pub fn spawn(mut command: std::process::Command, limits: &Limits) -> Result<Host, Error> {
+        command
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        process::isolate(&mut command, limits.max_memory);
+        let mut child: std::process::Child = command.spawn()?;
+
+        let (sender, responses) = std::sync::mpsc::channel::<Option<String>>();
+        let max_line_bytes: Option<u64> = limits.max_output_bytes.map(max_line_bytes);
+        if let Some(stdout) = child.stdout.take() {
+            std::thread::spawn(move || read_lines(std::io::BufReader::new(stdout), max_line_bytes, &sender));
+        }
+        let stderr: std::sync::Arc<std::sync::Mutex<Vec<u8>>> = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stderr_reader = child.stderr.take().map(|pipe| {
+            let stderr = std::sync::Arc::clone(&stderr);
+            std::thread::spawn(move || process::read_tail(pipe, &stderr))
+        });
+
+        let stdin: Option<std::process::ChildStdin> = child.stdin.take();
+        let mut host: Host = Host { child, stdin, responses, max_output_bytes: limits.max_output_bytes, stderr, stderr_reader, exited: false };
+        let line: String = host.receive(limits.timeout)?;
+        match serde_json::from_str::<Value>(&line) {
+            Ok(ready) if ready["ready"] == json!(true) => Ok(host),
+            Ok(response) if response["error"].is_string() => {
+                host.stop();
+                Err(response_error(&response))
+            }
+            _ => {
+                host.stop();
+                Err(unexpected_response(&line))
+            }
+        }
+    }
+
+    // Sends the arguments of one file and waits for the output. After a crash, a timeout or a line that is
+    // not a response, the host is stopped and not running anymore.
+    pub fn call(&mut self, input: &str, timeout: Option<std::time::Duration>) -> Result<String, Error> {
+        // a host that died is noticed when its stdout is closed
+        if let Some(stdin) = self.stdin.as_mut() {
+            let _ = writeln!(stdin, "{}", input).and_then(|_| stdin.flush());
+        }
+        let line: String = self.receive(timeout)?;
+        match serde_json::from_str::<Value>(&line) {
+            Ok(response) => match (response["output"].as_str(), response["error"].is_string()) {
+                (Some(output), _) => Ok(output.to_string()),
+                (None, true) => Err(response_error(&response)),
+                (None, false) => {
+                    self.stop();
+                    Err(unexpected_response(&line))
+                }
+            },
+            Err(_) => {
+                self.stop();
+                Err(unexpected_response(&line))
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.exited
+    }
+
+    fn receive(&mut self, timeout: Option<std::time::Duration>) -> Result<String, Error> {
+        let received = match timeout {
+            Some(timeout) => self.responses.recv_timeout(timeout),
+            None => self.responses.recv().map_err(|_| std::sync::mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Some(line)) => Ok(line),
+            Ok(None) => {
+                self.stop();
+                Err(Error::OutputLimitExceeded(self.max_output_bytes.unwrap_or_default()))
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                self.stop();
+                Err(Error::Timeout(timeout.unwrap_or_default()))
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                let status: std::process::ExitStatus = self.stop();
+                let stderr: Vec<u8> = std::mem::take(&mut *self.stderr.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+                let signal: Option<i32> = std::os::unix::process::ExitStatusExt::signal(&status);
+                Err(Error::SoHostCrashed(status.code(), signal, String::from_utf8_lossy(&stderr).into_owned()))
+            }
+        }
+    }
+
+    // kills the host and what it started, and waits for the end of its stderr
+    fn stop(&mut self) -> std::process::ExitStatus {
+        self.exited = true;
+        self.stdin = None;
+        process::kill_group(self.child.id() as libc::pid_t);
+        let status: std::process::ExitStatus = match self.child.wait() {
+            Ok(status) => status,
+            Err(_) => std::os::unix::process::ExitStatusExt::from_raw(libc::SIGKILL),
+        };
+        if let Some(reader) = self.stderr_reader.take() {
+            let _ = reader.join();
+        }
+        status
+    }
+}
+
+impl Drop for Host {
+    // a running host exits when its stdin is closed
+    fn drop(&mut self) {
+        if !self.exited {
+            self.stdin = None;
+            let _ = self.child.wait();
+        }
+    }
+}
+
+// The output is escaped in the response line, where a byte takes at most 6 (\u001f), and execute_analyzer
+// checks the exact limit once it is decoded.
+fn max_line_bytes(max_output_bytes: u64) -> u64 {
+    max_output_bytes.saturating_mul(6).saturating_add(64)
+}
+
+// sends the lines of the host until its stdout is closed, or None for a line longer than `limit`
+fn read_lines<R: BufRead>(mut stdout: R, limit: Option<u64>, sender: &std::sync::mpsc::Sender<Option<String>>) {
+    let limit: u64 = limit.unwrap_or(u64::MAX);
+    loop {
+        let mut line: Vec<u8> = Vec::new();
+        match (&mut stdout).take(limit.saturating_add(1)).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        } else if line.len() as u64 > limit {
+            let _ = sender.send(None);
+            break;
+        }
+        if sender.send(Some(String::from_utf8_lossy(&line).into_owned())).is_err() {
+            break;
+        }
+    }
+}
+
+fn response_error(response: &Value) -> Error {
+    let message: String = response["message"].as_str().unwrap_or_default().to_string();
+    match response["code"].as_i64() {
+        Some(code) => Error::SoFailed(code as i32, message),
+        None => Error::SoHostFailed(message),
+    }
+}
+
+fn unexpected_response(line: &str) -> Error {
+    let start: String = line.chars().take(200).collect();
+    Error::SoHostFailed(format!("unexpected line from the host: {}", start))
+}
+
+fn error_response(err: &Error) -> Value {
+    match err {
+        Error::SoFailed(code, message) => json!({"error": err.kind(), "message": message, "code": code}),
+        _ => json!({"error": err.kind(), "message": err.to_string()}),
+    }
+}
+
+// The loop of `faaf-so-host --serve`. The library may print to stdout, so the responses are written to a copy
+// of fd 1, and fd 1 is redirected to stderr before the library is loaded.
+pub fn serve(lib_path: &std::path::Path, legacy_abi: bool) -> Result<(), Error> {
+    let protocol: std::fs::File = unsafe {
+        let fd: libc::c_int = libc::fcntl(1, libc::F_DUPFD_CLOEXEC, 3);
+        if fd < 0 || libc::dup2(2, 1) < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        std::fs::File::from_raw_fd(fd)
+    };
+    serve_lines(lib_path, legacy_abi, std::io::stdin().lock(), std::io::BufWriter::new(protocol))
+}
+
+// loads the library, then calls it for every line of `input` until the end of it. an error loading the
+// library is sent before it is returned.
+fn serve_lines(lib_path: &std::path::Path, legacy_abi: bool, input: impl BufRead, mut output: impl Write) -> Result<(), Error> {
+    let library: SharedObject = match SharedObject::load(lib_path, legacy_abi) {
+        Ok(library) => library,
+        Err(err) => {
+            writeln!(output, "{}", error_response(&err))?;
+            output.flush()?;
+            return Err(err);
+        }
+    };
+    writeln!(output, "{}", json!({"ready": true}))?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let response: Value = match library.call(&line?) {
+            Ok(result) => json!({"output": result}),
+            Err(err) => error_response(&err),
+        };
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // stands in for faaf-so-host: crashes on "segv", fails on "fail", answers the other lines
+    const FAKE_HOST: &str = r#"
+echo '{"ready": true}'
+while read -r line; do
+    case "$line" in
+        '"segv"') echo 'parsing header' >&2; kill -SEGV $$ ;;
+        '"fail"') echo '{"error": "so_failed", "message": "no elf header", "code": 7}' ;;
+        '"hang"') sleep 10 ;;
+        '"noise"') echo 'loading signatures'; echo '{"output": "{}"}' ;;
+        '"large"') printf '{"output": "%0200d"}\n' 0 ;;
+        *) echo '{"output": "{\"ok\": true}"}' ;;
+    esac
+done
+"#;
+
+    fn fake_host() -> std::process::Command {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(FAKE_HOST);
+        command
+    }
+
+    #[test]
+    fn test_host_crash() {
+        let mut host: Host = Host::spawn(fake_host(), &Limits::default()).unwrap();
+        assert_eq!(host.call("{}", None).unwrap(), "{\"ok\": true}");
+        let err: Error = host.call("\"fail\"", None).unwrap_err();
+        assert_eq!((err.kind(), err.exit_code(), err.stderr().as_deref()), ("so_failed", Some(7), Some("no elf header")));
+        assert!(host.is_running());
+        assert_eq!(host.call("{}", None).unwrap(), "{\"ok\": true}");
+
+        let err: Error = host.call("\"segv\"", None).unwrap_err();
+        assert_eq!(err.kind(), "so_crash");
+        assert!(matches!(&err, Error::SoHostCrashed(None, Some(libc::SIGSEGV), _)));
+        assert_eq!(err.signal(), Some(libc::SIGSEGV));
+        assert_eq!(err.stderr().as_deref(), Some("parsing header\n"));
+        assert!(!host.is_running());
+        assert_eq!(host.call("{}", None).unwrap_err().kind(), "so_crash");
+
+        let mut host: Host = Host::spawn(fake_host(), &Limits::default()).unwrap();
+        let start = std::time::Instant::now();
+        assert!(matches!(host.call("\"hang\"", Some(std::time::Duration::from_millis(100))), Err(Error::Timeout(_))));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(!host.is_running());
+
+        // the response of the file would be read for the next one
+        let mut host: Host = Host::spawn(fake_host(), &Limits::default()).unwrap();
+        assert_eq!(host.call("\"noise\"", None).unwrap_err().kind(), "so_host");
+        assert!(!host.is_running());
+
+        let limits: Limits = Limits { max_output_bytes: Some(8), ..Default::default() };
+        let mut host: Host = Host::spawn(fake_host(), &limits).unwrap();
+        assert!(matches!(host.call("\"large\"", None), Err(Error::OutputLimitExceeded(8))));
+        assert!(!host.is_running());
+    }
+
+    #[test]
+    fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        assert!(serve_lines(&dir.path().join("libmissing.so"), false, &b""[..], &mut output).is_err());
+        let response: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(response["error"], json!("so"));
+        assert_eq!(response_error(&response).kind(), "so_host");
+    }
+}
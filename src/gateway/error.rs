@@ -2,6 +2,7 @@ use nom::Err;
 use std::fmt;
 use crate::config::error::Error as ConfigError;
 use crate::database::error::Error as DatabaseError;
+use crate::extract::error::Error as ExtractError;
 
 use super::dispatcher;
 
@@ -11,7 +12,11 @@ pub enum Error {
     DatabaseError(DatabaseError),
     IoError(std::io::Error),
     DiffCondAndCondStmt(),
-    DispathcerError(dispatcher::error::Error)
+    DispathcerError(dispatcher::error::Error),
+    WorkerPoolClosed(),
+    UnknownAnalyzer(String),
+    DatabaseNotEmpty(),
+    ExtractError(ExtractError),
 }
 
 impl fmt::Display for Error {
@@ -22,6 +27,10 @@ impl fmt::Display for Error {
             Error::IoError(err) => write!(f, "IO error: {}", err),
             Error::DiffCondAndCondStmt() => write!(f, "difference conditions struct and condition stmt"),
             Error::DispathcerError(err) => write!(f, "dispather error {} ", err),
+            Error::WorkerPoolClosed() => write!(f, "worker pool closed before all jobs finished"),
+            Error::UnknownAnalyzer(name) => write!(f, "analyzer `{}` is not in the config file", name),
+            Error::DatabaseNotEmpty() => write!(f, "the database already contains results. use --resume or --rerun to continue it"),
+            Error::ExtractError(err) => write!(f, "extract error: {}", err),
         }
     }
 }
@@ -34,6 +43,10 @@ impl std::error::Error for Error {
             Error::IoError(err) => Some(err),
             Error::DiffCondAndCondStmt() => None,
             Error::DispathcerError(err) => Some(err),
+            Error::WorkerPoolClosed() => None,
+            Error::UnknownAnalyzer(_) => None,
+            Error::DatabaseNotEmpty() => None,
+            Error::ExtractError(err) => Some(err),
         }
     }
 }
@@ -44,6 +57,12 @@ impl From<ConfigError> for Error {
     }
 }
 
+impl From<ExtractError> for Error {
+    fn from(err: ExtractError) -> Self {
+        Error::ExtractError(err)
+    }
+}
+
 impl From<DatabaseError> for Error {
     fn from(err: DatabaseError) -> Self {
         Error::DatabaseError(err)
@@ -1,64 +1,693 @@
+use crate::config::analyzer::Scope;
 use crate::config::config;
-use crate::gateway;
+use crate::config::filter::FileFilter;
+use crate::gateway::dispatcher;
+use crate::gateway::dispatcher::runner::Runners;
 use crate::gateway::error::Error;
+use crate::gateway::worker;
+use crate::database::cache;
 use crate::database::database;
+use crate::gateway::metadata;
+use crate::gateway::traverse;
+use crate::gateway::traverse::Entry;
+use crate::extract::extractor::{Budget, Extractor, Extractors};
+use crate::extract::image;
 
 
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
-static RESULT_ID: Lazy<Mutex<i64>> = Lazy::new(|| Mutex::new(1));
-
-fn analyze_callback(
-    base: &std::path::Path, 
-    relative_path: &std::path::Path, 
-    absolute_path: &std::path::Path, 
-    script_dir: &std::path::Path,
-    config: &config::Config,
-    insert_stmt: &mut database::InsertAnalyerStatement, 
-    select_stmt: &mut database::SelectAnalyzerStatement,
-) -> Result<(), Error> {
-    insert_stmt.insert_path(&relative_path)?;
-    let mut result_id_guard = RESULT_ID.lock().unwrap();
-    let result_id = *result_id_guard;
-    *result_id_guard += 1;
-    for analyzer in &config.analyzer {
-        {
-            let (arg_stmt, cond_stmt) = select_stmt.get_stmt(&analyzer.name)?;
+pub struct Options {
+    // number of analyzer jobs run at the same time
+    pub jobs: usize,
+    // abort the run and discard the transaction on the first analyzer error
+    pub fail_fast: bool,
+    // keep the results of an existing database and only run what is missing
+    pub resume: bool,
+    // recompute these analyzers and their dependents on an existing database
+    pub rerun: Vec<String>,
+    // results shared between runs, keyed by the content of the file
+    pub cache_database: Option<std::path::PathBuf>,
+    // follow symlinks, resolved inside the firmware root directory
+    pub follow_symlinks: bool,
+    // given on the command line, applied together with the filter of the config
+    pub filter: FileFilter,
+    // how deep archives found among the files are unpacked and analyzed. 0 does not unpack them.
+    pub extract_depth: usize,
+    // bytes the extraction of the image and of the archives in it may write, against zip bombs
+    pub max_extract_size: u64,
+    // how deep files written by analyzers are analyzed: a file emitted from an emitted file is 2 deep
+    pub emit_depth: usize,
+    // how analyzers are run, by extension. a program embedding faaf can register its own runners here.
+    pub runners: std::sync::Arc<Runners>,
+}
+
+pub const DEFAULT_MAX_EXTRACT_SIZE: u64 = 4 << 30;
+pub const DEFAULT_EMIT_DEPTH: usize = 4;
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { jobs: 1, fail_fast: false, resume: false, rerun: Vec::new(), cache_database: None, follow_symlinks: false, filter: FileFilter::default(),
+                  extract_depth: 0, max_extract_size: DEFAULT_MAX_EXTRACT_SIZE, emit_depth: DEFAULT_EMIT_DEPTH,
+                  runners: std::sync::Arc::new(Runners::new()) }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AnalyzerSummary {
+    pub name: String,
+    pub success: u64,
+    pub failed: u64,
+    pub timed_out: u64,
+    pub skipped: u64,
+    // successes taken from the result cache
+    pub cached: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub files: u64,
+    pub analyzers: Vec<AnalyzerSummary>,
+}
+
+impl Summary {
+    fn new(config: &config::Config) -> Self {
+        Summary {
+            files: 0,
+            analyzers: config.analyzer.iter().map(|analyzer| AnalyzerSummary { name: analyzer.name.clone(), ..Default::default() }).collect(),
+        }
+    }
+
+    // only the analyzers of the stage are counted: the others had their own task
+    fn add(&mut self, config: &config::Config, stage: Scope, task: &FileTask) {
+        if stage == Scope::File {
+            self.files += 1;
+        }
+        for ((summary, status), analyzer) in self.analyzers.iter_mut().zip(&task.status).zip(&config.analyzer) {
+            if analyzer.scope != stage {
+                continue;
+            }
+            match status {
+                AnalyzerStatus::Success => summary.success += 1,
+                AnalyzerStatus::Failed => summary.failed += 1,
+                AnalyzerStatus::TimedOut => summary.timed_out += 1,
+                AnalyzerStatus::Skipped => summary.skipped += 1,
+                AnalyzerStatus::Pending | AnalyzerStatus::Running => {}
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "files: {}", self.files)?;
+        let width: usize = self.analyzers.iter().map(|analyzer| analyzer.name.len()).max().unwrap_or(0);
+        for analyzer in &self.analyzers {
+            writeln!(f, "{:width$}  success: {} (cached: {})  failed: {}  timed out: {}  skipped: {}", analyzer.name, analyzer.success, analyzer.cached, analyzer.failed, analyzer.timed_out, analyzer.skipped, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+// outcome of one analyzer for one file. dependents of an analyzer that did not succeed are skipped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnalyzerStatus {
+    Pending,
+    Running,
+    Success,
+    Failed,
+    TimedOut,
+    Skipped,
+}
+
+// where an entry comes from: the firmware root directory, an archive found among the files, or an analyzer
+#[derive(Clone, Copy)]
+struct Nesting {
+    origin: database::FileOrigin,
+    // number of archives the entry is in
+    depth: usize,
+    // number of analyzers the entry was emitted by, one after the other
+    emitted: usize,
+}
+
+// the metadata of a file being collected
+type Pending = std::sync::mpsc::Receiver<database::FileMetadata>;
+
+// a file whose analyzers are still pending or running
+struct FileTask {
+    entry: Entry,
+    metadata: database::FileMetadata,
+    nesting: Nesting,
+    status: Vec<AnalyzerStatus>,
+    // where the result of a running analyzer goes in the cache
+    cache_keys: Vec<Option<cache::CacheKey>>,
+    // the pass over the files, see DependencyGraph::pass
+    pass: usize,
+}
+
+impl FileTask {
+    fn new(entry: Entry, metadata: database::FileMetadata, nesting: Nesting, analyzer_count: usize) -> Self {
+        FileTask {
+            entry,
+            metadata,
+            nesting,
+            status: vec![AnalyzerStatus::Pending; analyzer_count],
+            cache_keys: (0..analyzer_count).map(|_| None).collect(),
+            pass: 0,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        !self.status.iter().any(|status| matches!(status, AnalyzerStatus::Pending | AnalyzerStatus::Running))
+    }
+
+    fn is_running(&self) -> bool {
+        self.status.contains(&AnalyzerStatus::Running)
+    }
+}
+
+// Runs the analyzers of every file. Lives on the thread owning the transaction: conditions and arguments are
+// read and results are written here, so workers never touch the database.
+struct Scheduler<'s, 'a, 'b> {
+    base: &'s std::path::Path,
+    config: &'b config::Config,
+    options: &'s Options,
+    insert_stmt: database::InsertAnalyerStatement<'a, 'b>,
+    select_stmt: database::SelectAnalyzerStatement<'a, 'b>,
+    // only when resuming on an existing database
+    resume_stmt: Option<database::ResumeStatement<'a, 'b>>,
+    // analyzers allowed to run. with --rerun, the others only keep their previous results.
+    runnable: Vec<bool>,
+    // the analyzers named by --rerun: they may have changed without a new version, so their cached results are
+    // replaced and never read
+    rerun: Vec<bool>,
+    // the analyzers that emitted files in the run being resumed. the copies of these files are gone, so such an
+    // analyzer runs again on a file when another analyzer has yet to run on what it emitted from it.
+    reemit: Vec<bool>,
+    cache: Option<&'s cache::Cache>,
+    // hashes the files and runs libmagic on them
+    collector: &'s metadata::Collector,
+    // the innermost image the files of the firmware root directory were extracted from
+    image_id: Option<i64>,
+    extractors: &'s Extractors,
+    budget: Budget,
+    // where archives found among the files are unpacked and emitted files are copied, created on the first one
+    workspace: Option<tempfile::TempDir>,
+    // files emitted by analyzers, by result id of the file they come from and analyzer index
+    emitted: std::collections::BTreeMap<(i64, usize), Vec<(Entry, Nesting)>>,
+    // the scope of the analyzers run now
+    stage: Scope,
+    // outcome of the image analyzers, seen by every file
+    image_status: Vec<AnalyzerStatus>,
+    // run by the directory analyzers once every file is done
+    directories: Vec<(i64, FileTask)>,
+    // files with analyzers of a later pass, run once every file is through the current one
+    deferred: Vec<(i64, FileTask)>,
+    pool: &'s worker::WorkerPool,
+    summary: Summary,
+}
+
+impl<'s, 'a, 'b> Scheduler<'s, 'a, 'b> {
+    // files filtered out for every analyzer are not recorded, and their content is never read
+    fn admitted(&self, entry: &Entry) -> bool {
+        let inode: database::FileMetadata = metadata::stat(&entry.absolute_path);
+        self.config.filter.matches(&entry.relative_path, &inode) && self.options.filter.matches(&entry.relative_path, &inode)
+    }
+
+    // the result id and the metadata of a file recorded by the run being resumed
+    fn find_resumed(&mut self, entry: &Entry) -> Result<Option<(i64, database::FileMetadata)>, Error> {
+        match self.resume_stmt.as_mut() {
+            Some(resume_stmt) => Ok(resume_stmt.find_path(&entry.relative_path)?),
+            None => Ok(None),
+        }
+    }
+
+    // Starts collecting the metadata of the next files on the collector threads, so that the scheduler does not
+    // wait for hashing and libmagic. `pending` is None for the files with nothing to collect.
+    fn prefetch(&mut self, entries: &std::collections::VecDeque<(Entry, Nesting)>, pending: &mut std::collections::HashMap<std::path::PathBuf, Option<Pending>>, ahead: usize) -> Result<(), Error> {
+        for (entry, _) in entries.iter().take(ahead) {
+            if pending.contains_key(&entry.relative_path) {
+                continue;
+            }
+            let collect: bool = self.admitted(entry) && self.find_resumed(entry)?.is_none();
+            pending.insert(entry.relative_path.clone(), Some(self.collector.submit(&entry.absolute_path)).filter(|_| collect));
+        }
+        Ok(())
+    }
+
+    fn workspace(&mut self) -> Result<std::path::PathBuf, std::io::Error> {
+        if self.workspace.is_none() {
+            self.workspace = Some(tempfile::Builder::new().prefix("faaf-nested-").tempdir()?);
+        }
+        Ok(self.workspace.as_ref().map(|workspace| workspace.path().to_path_buf()).unwrap_or_default())
+    }
+
+    // Unpacks an archive found among the files and returns its files, named `archive!/file`.
+    // An archive that can not be unpacked is recorded in the image table with the error, and analyzed as a file only.
+    fn unpack(&mut self, entry: &Entry, result_id: i64, sha256: Option<&str>, nesting: &Nesting) -> Result<Vec<(Entry, Nesting)>, Error> {
+        let extractors: &Extractors = self.extractors;
+        let extractor: &dyn Extractor = match extractors.detect(&entry.absolute_path) {
+            Ok(Some(extractor)) => extractor,
+            _ => return Ok(Vec::new()),
+        };
+        let destination: std::path::PathBuf = self.workspace()?.join(result_id.to_string());
+        std::fs::create_dir(&destination)?;
+
+        let path: String = entry.relative_path.to_string_lossy().into_owned();
+        // a failed extraction, extracted files that can not be walked and a partial output that can not be removed
+        // are recorded as the error of the image, and the run goes on
+        let follow_symlinks: bool = self.options.follow_symlinks;
+        let extracted: Result<std::collections::VecDeque<Entry>, String> = extractor.extract(&entry.absolute_path, &destination, &mut self.budget)
+            .map_err(|err| err.to_string())
+            .and_then(|()| {
+                std::fs::canonicalize(&destination)
+                    .and_then(|root| traverse::traverse(&root, follow_symlinks))
+                    .map_err(|err| format!("can not read the extracted files: {}", err))
+            });
+        let error: Option<String> = match &extracted {
+            Ok(_) => None,
+            Err(error) => match std::fs::remove_dir_all(&destination) {
+                Ok(()) => Some(error.clone()),
+                Err(err) => Some(format!("{}; can not remove the extracted files: {}", error, err)),
+            },
+        };
+        let image_id: i64 = self.insert_stmt.insert_image(&database::ImageRecord {
+            parent_id: nesting.origin.image_id,
+            result_id: Some(result_id),
+            path: &path,
+            origin: extractor.name(),
+            sha256,
+            error: error.as_deref(),
+        })?;
+        let children: std::collections::VecDeque<Entry> = match extracted {
+            Ok(children) => children,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let archive: std::path::PathBuf = std::path::PathBuf::from(format!("{}!", path));
+        let child_nesting: Nesting = Nesting {
+            origin: database::FileOrigin { image_id: Some(image_id), parent_id: Some(result_id) },
+            depth: nesting.depth + 1,
+            emitted: nesting.emitted,
+        };
+        Ok(children.into_iter().map(|child| (Entry { relative_path: archive.join(&child.relative_path), ..child }, child_nesting)).collect())
+    }
+
+    // Removes the `emit` key of an analyzer output and copies the files it lists to the workspace, as the analyzer
+    // may delete them. They are named `file!analyzer/name`. Beyond --emit-depth, the key is removed and ignored.
+    fn emit(&mut self, value: &mut serde_json::Value, result_id: i64, analyzer_index: usize, task: &FileTask) -> Result<Vec<(Entry, Nesting)>, dispatcher::error::Error> {
+        let files: Vec<(std::path::PathBuf, String)> = match value.as_object_mut().and_then(|obj| obj.remove("emit")) {
+            Some(emit) => parse_emit(&emit).map_err(dispatcher::error::Error::EmitError)?,
+            None => return Ok(Vec::new()),
+        };
+        // every file analyzer has run when the directory analyzers and the last image analyzers run
+        if task.nesting.emitted >= self.options.emit_depth || self.stage > Scope::File {
+            return Ok(Vec::new());
+        }
+
+        let config: &config::Config = self.config;
+        let analyzer_name: &str = &config.analyzer[analyzer_index].name;
+        let emit_error = |err: &dyn std::fmt::Display| dispatcher::error::Error::EmitError(err.to_string());
+        let destination: std::path::PathBuf = self.workspace().map_err(|err| emit_error(&err))?.join("emit").join(result_id.to_string()).join(analyzer_name);
+        std::fs::create_dir_all(&destination).map_err(|err| emit_error(&err))?;
+        let destination: std::path::PathBuf = std::fs::canonicalize(&destination).map_err(|err| emit_error(&err))?;
+
+        let parent: std::path::PathBuf = std::path::PathBuf::from(format!("{}!{}", task.entry.relative_path.display(), analyzer_name));
+        let nesting: Nesting = Nesting {
+            // an image analyzer has no result row: its files are named `!analyzer/name` and have no parent
+            origin: database::FileOrigin { image_id: task.nesting.origin.image_id, parent_id: Some(result_id).filter(|_| self.stage == Scope::File) },
+            depth: task.nesting.depth,
+            emitted: task.nesting.emitted + 1,
+        };
+        let mut entries: Vec<(Entry, Nesting)> = Vec::new();
+        for (path, name) in files {
+            if !std::fs::metadata(&path).is_ok_and(|metadata| metadata.is_file()) {
+                return Err(emit_error(&format!("`{}` is not a regular file", path.display())));
+            }
+            let target: std::path::PathBuf = destination.join(&name);
+            let reader: std::fs::File = std::fs::File::open(&path).map_err(|err| emit_error(&err))?;
+            let mut writer: std::fs::File = std::fs::File::create(&target).map_err(|err| emit_error(&err))?;
+            self.budget.copy(reader, &mut writer).map_err(|err| emit_error(&err))?;
+            entries.push((Entry { relative_path: parent.join(&name), absolute_path: target, link_target: None, resolved_target: None }, nesting));
+        }
+        Ok(entries)
+    }
+
+    // Submits every analyzer of the file whose dependencies have finished.
+    fn schedule(&mut self, result_id: i64, task: &mut FileTask) -> Result<(), Error> {
+        let config: &config::Config = self.config;
+        for &index in config.graph.order() {
+            if task.status[index] != AnalyzerStatus::Pending {
+                continue;
+            }
+            let analyzer = &config.analyzer[index];
+            if analyzer.scope != self.stage {
+                task.status[index] = self.outside_status(index);
+                continue;
+            }
+            let dependencies: &[usize] = config.graph.dependencies(index);
+            if dependencies.iter().any(|dep| matches!(task.status[*dep], AnalyzerStatus::Pending | AnalyzerStatus::Running)) {
+                continue;
+            }
+            if self.stage == Scope::File && config.graph.pass(index) > task.pass {
+                continue;
+            }
+
+            let resumed: bool = match self.resume_stmt.as_mut() {
+                Some(resume_stmt) => resume_stmt.has_result(&analyzer.name, result_id)?,
+                None => false,
+            };
+            let reemit: bool = resumed && self.reemit[index] && self.misses_emitted(index, task)?;
+            if resumed && !reemit {
+                task.status[index] = AnalyzerStatus::Success;
+                continue;
+            }
+            if let (true, Some(resume_stmt)) = (reemit, self.resume_stmt.as_mut()) {
+                resume_stmt.forget_result(&analyzer.name, result_id)?;
+            }
+            let image: bool = matches!(self.stage, Scope::ImageBefore | Scope::ImageAfter);
+            if !(self.runnable[index] || reemit) || (!image && !analyzer.filter.matches(&task.entry.relative_path, &task.metadata)) {
+                task.status[index] = AnalyzerStatus::Skipped;
+                continue;
+            }
+            // a dependency that did not succeed has no data for this file
+            if dependencies.iter().any(|dep| task.status[*dep] != AnalyzerStatus::Success) {
+                task.status[index] = AnalyzerStatus::Skipped;
+                continue;
+            }
+
+            let (arg_stmt, cond_stmt) = self.select_stmt.get_stmt(&analyzer.name)?;
             match (analyzer.conditions.as_ref(), cond_stmt) {
                 (Some(conditions), Some(stmt)) => {
                     stmt.set_placeholder(result_id)?;
-                    if stmt.is_match_condition(&conditions)? == true {
-                        arg_stmt.set_placeholder(result_id)?;
-                        let mut args: serde_json::Value = arg_stmt.get_argument()?;
-                        if let Some(obj) = args.as_object_mut() {
-                            obj.insert("relative_path".to_string(), serde_json::json!(base.display().to_string()));
-                            obj.insert("absolute_path".to_string(), serde_json::json!(absolute_path.display().to_string()));
-                        }
-                        let result: serde_json::Value = gateway::dispatcher::dispatcher::execute_analyzer(script_dir, &analyzer.name, &analyzer.extension, &args)?;
-                        insert_stmt.insert_analyzer(&analyzer.name, result_id, result)?;
-                    }
-                    else {
+                    if !stmt.is_match_condition(conditions)? {
+                        task.status[index] = AnalyzerStatus::Skipped;
                         continue;
                     }
+                }
+                (None, None) => {}
+                _ => return Err(Error::DiffCondAndCondStmt()),
+            }
+
+            arg_stmt.set_placeholder(result_id)?;
+            let mut args: serde_json::Value = arg_stmt.get_argument()?;
+            if let Some(obj) = args.as_object_mut() {
+                obj.insert("relative_path".to_string(), serde_json::json!(self.base.display().to_string()));
+                // a symlink that is not followed is opened through its target inside the firmware, never the link itself
+                let absolute_path: Option<&std::path::Path> = if task.metadata.file_type.as_deref() == Some("symlink") {
+                    task.entry.resolved_target.as_deref()
+                } else {
+                    Some(task.entry.absolute_path.as_path())
+                };
+                obj.insert("absolute_path".to_string(), serde_json::json!(absolute_path.map(|path| path.display().to_string())));
+            }
+
+            if let (Some(cache), Some(sha256)) = (self.cache, task.metadata.sha256.as_ref()) {
+                let key: cache::CacheKey = cache::CacheKey {
+                    file_sha256: sha256.clone(),
+                    analyzer: analyzer.name.clone(),
+                    version: analyzer.version.clone().unwrap_or_default(),
+                    arguments_sha256: arguments_sha256(&args),
+                };
+                let cached: Option<serde_json::Value> = if self.rerun[index] { None } else { cache.get(&key)? };
+                if let Some(value) = cached {
+                    self.insert_stmt.insert_analyzer(&analyzer.name, result_id, value)?;
+                    self.summary.analyzers[index].cached += 1;
+                    task.status[index] = AnalyzerStatus::Success;
+                    continue;
+                }
+                task.cache_keys[index] = Some(key);
+            }
+
+            self.pool.submit(worker::Job {
+                result_id,
+                analyzer_index: index,
+                args,
+            });
+            task.status[index] = AnalyzerStatus::Running;
+        }
+        Ok(())
+    }
 
+    // Whether a file analyzer allowed to run has no result for one of the files emitted from the task by the analyzer
+    // `index` in the run being resumed, while its filters and conditions match the file.
+    fn misses_emitted(&mut self, index: usize, task: &FileTask) -> Result<bool, Error> {
+        let config: &config::Config = self.config;
+        let prefix: String = format!("{}!{}/", task.entry.relative_path.display(), config.analyzer[index].name);
+        let resume_stmt: &mut database::ResumeStatement = match self.resume_stmt.as_mut() {
+            Some(resume_stmt) => resume_stmt,
+            None => return Ok(false),
+        };
+        for (result_id, path, file_metadata) in resume_stmt.find_emitted(&prefix)? {
+            for (other, analyzer) in config.analyzer.iter().enumerate() {
+                if analyzer.scope != Scope::File || !self.runnable[other] || !analyzer.filter.matches(&path, &file_metadata) {
+                    continue;
+                }
+                if resume_stmt.has_result(&analyzer.name, result_id)? {
+                    continue;
                 }
-                (None, None) => {
-                    arg_stmt.set_placeholder(result_id)?;
-                    let mut args: serde_json::Value = arg_stmt.get_argument()?;
-                    if let Some(obj) = args.as_object_mut() {
-                        obj.insert("relative_path".to_string(), serde_json::json!(base.display().to_string()));
-                        obj.insert("absolute_path".to_string(), serde_json::json!(absolute_path.display().to_string()));
+                if let (Some(conditions), (_, Some(cond_stmt))) = (analyzer.conditions.as_ref(), self.select_stmt.get_stmt(&analyzer.name)?) {
+                    cond_stmt.set_placeholder(result_id)?;
+                    if !cond_stmt.is_match_condition(conditions)? {
+                        continue;
                     }
-                    let result: serde_json::Value = gateway::dispatcher::dispatcher::execute_analyzer(script_dir, &analyzer.name, &analyzer.extension, &args)?;
-                    insert_stmt.insert_analyzer(&analyzer.name, result_id, result)?;
                 }
-                _ => return Err(Error::DiffCondAndCondStmt()),
+                return Ok(true);
             }
-        } 
+        }
+        Ok(false)
+    }
+
+    // What a task of the current stage sees of an analyzer of another scope. An image analyzer has a single outcome,
+    // and the analyzers of an earlier stage have run on every file: a dependency on them only orders the stages.
+    fn outside_status(&self, index: usize) -> AnalyzerStatus {
+        match self.config.analyzer[index].scope {
+            Scope::ImageBefore | Scope::ImageAfter => self.image_status[index],
+            scope if scope < self.stage => AnalyzerStatus::Success,
+            _ => AnalyzerStatus::Skipped,
+        }
     }
-    
 
-    Ok(())
+    fn finish_job(&mut self, done: worker::JobResult, task: &mut FileTask) -> Result<AnalyzerStatus, Error> {
+        let config: &config::Config = self.config;
+        let analyzer_name: &str = &config.analyzer[done.analyzer_index].name;
+        let mut cache_key: Option<cache::CacheKey> = task.cache_keys[done.analyzer_index].take();
+        let result: Result<(serde_json::Value, Vec<(Entry, Nesting)>), dispatcher::error::Error> = match done.result {
+            Ok(mut value) => {
+                // the emitted files are gone after the run, so a cached result could not emit them again
+                if value.get("emit").is_some() {
+                    cache_key = None;
+                }
+                self.emit(&mut value, done.result_id, done.analyzer_index, task).map(|files| (value, files))
+            }
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok((value, files)) => {
+                if let (Some(cache), Some(key)) = (self.cache, cache_key) {
+                    cache.put(&key, &value)?;
+                }
+                self.insert_stmt.insert_analyzer(analyzer_name, done.result_id, value)?;
+                if !files.is_empty() {
+                    self.emitted.insert((done.result_id, done.analyzer_index), files);
+                }
+                Ok(AnalyzerStatus::Success)
+            }
+            Err(err) if self.options.fail_fast => Err(err.into()),
+            Err(err) => {
+                let stderr: Option<String> = err.stderr();
+                self.insert_stmt.insert_error(&database::AnalyzerErrorRecord {
+                    result_id: done.result_id,
+                    analyzer: analyzer_name,
+                    kind: err.kind(),
+                    message: &err.to_string(),
+                    stderr: stderr.as_deref(),
+                    exit_code: err.exit_code(),
+                    signal: err.signal(),
+                })?;
+                match err {
+                    dispatcher::error::Error::Timeout(_) => Ok(AnalyzerStatus::TimedOut),
+                    _ => Ok(AnalyzerStatus::Failed),
+                }
+            }
+        }
+    }
+
+    // Runs the scopes one after the other: the image analyzers of the `before` stage, the files,
+    // the directories and the image analyzers of the `after` stage.
+    fn run(&mut self, entries: std::collections::VecDeque<Entry>) -> Result<(), Error> {
+        if self.has_scope(Scope::ImageBefore) {
+            let task: FileTask = self.image_task();
+            self.run_stage(Scope::ImageBefore, vec![(database::IMAGE_RESULT_ID, task)])?;
+        }
+        self.run_files(entries)?;
+        // a subdirectory has a larger result id than its directory, so it comes first and its results can be read
+        let mut directories: Vec<(i64, FileTask)> = std::mem::take(&mut self.directories);
+        directories.sort_by_key(|(result_id, _)| std::cmp::Reverse(*result_id));
+        self.run_stage(Scope::Directory, directories)?;
+        if self.has_scope(Scope::ImageAfter) {
+            let task: FileTask = self.image_task();
+            self.run_stage(Scope::ImageAfter, vec![(database::IMAGE_RESULT_ID, task)])?;
+        }
+        Ok(())
+    }
+
+    fn has_scope(&self, scope: Scope) -> bool {
+        self.config.analyzer.iter().any(|analyzer| analyzer.scope == scope)
+    }
+
+    // the image analyzers run on the firmware root directory, which has no result row
+    fn image_task(&self) -> FileTask {
+        let entry: Entry = Entry { relative_path: std::path::PathBuf::new(), absolute_path: self.base.to_path_buf(), link_target: None, resolved_target: None };
+        let nesting: Nesting = Nesting { origin: database::FileOrigin { image_id: self.image_id, parent_id: None }, depth: 0, emitted: 0 };
+        FileTask::new(entry, database::FileMetadata::default(), nesting, self.config.analyzer.len())
+    }
+
+    // runs the analyzers of `stage` on the given result ids
+    fn run_stage(&mut self, stage: Scope, tasks: Vec<(i64, FileTask)>) -> Result<(), Error> {
+        self.stage = stage;
+        let window: usize = self.options.jobs.max(1) * 2;
+        let mut in_flight: std::collections::HashMap<i64, FileTask> = std::collections::HashMap::new();
+        let mut tasks = tasks.into_iter();
+        loop {
+            while in_flight.len() < window {
+                match tasks.next() {
+                    Some((result_id, task)) => self.start(result_id, task, &mut in_flight)?,
+                    None => break,
+                }
+            }
+            if in_flight.is_empty() {
+                return Ok(());
+            }
+            self.complete(&mut in_flight)?;
+        }
+    }
+
+    fn start(&mut self, result_id: i64, mut task: FileTask, in_flight: &mut std::collections::HashMap<i64, FileTask>) -> Result<(), Error> {
+        self.schedule(result_id, &mut task)?;
+        if task.is_finished() {
+            self.finished(&task);
+        }
+        else if task.is_running() {
+            in_flight.insert(result_id, task);
+        }
+        else {
+            task.pass += 1;
+            self.deferred.push((result_id, task));
+        }
+        Ok(())
+    }
+
+    // waits for one analyzer and schedules what depends on it
+    fn complete(&mut self, in_flight: &mut std::collections::HashMap<i64, FileTask>) -> Result<(), Error> {
+        let done: worker::JobResult = self.pool.recv().ok_or(Error::WorkerPoolClosed())?;
+        let result_id: i64 = done.result_id;
+        let analyzer_index: usize = done.analyzer_index;
+        if let Some(mut task) = in_flight.remove(&result_id) {
+            let status: AnalyzerStatus = self.finish_job(done, &mut task)?;
+            task.status[analyzer_index] = status;
+            self.start(result_id, task, in_flight)?;
+        }
+        Ok(())
+    }
+
+    fn finished(&mut self, task: &FileTask) {
+        let config: &config::Config = self.config;
+        if matches!(self.stage, Scope::ImageBefore | Scope::ImageAfter) {
+            for (index, analyzer) in config.analyzer.iter().enumerate() {
+                if analyzer.scope == self.stage {
+                    self.image_status[index] = task.status[index];
+                }
+            }
+        }
+        self.summary.add(config, self.stage, task);
+    }
+
+    // Feeds files to the worker pool, keeping at most a few files per worker in flight.
+    // Result ids are assigned here in traversal order, so they do not depend on the number of jobs.
+    fn run_files(&mut self, entries: std::collections::VecDeque<Entry>) -> Result<(), Error> {
+        self.stage = Scope::File;
+        let directory_analyzers: bool = self.has_scope(Scope::Directory);
+        let top: Nesting = Nesting { origin: database::FileOrigin { image_id: self.image_id, parent_id: None }, depth: 0, emitted: 0 };
+        let mut entries: std::collections::VecDeque<(Entry, Nesting)> = entries.into_iter().map(|entry| (entry, top)).collect();
+        let window: usize = self.options.jobs.max(1) * 2;
+        let mut in_flight: std::collections::HashMap<i64, FileTask> = std::collections::HashMap::new();
+        let mut resumed: std::collections::VecDeque<(i64, FileTask)> = std::collections::VecDeque::new();
+        let mut pending: std::collections::HashMap<std::path::PathBuf, Option<Pending>> = std::collections::HashMap::new();
+
+        loop {
+            while in_flight.len() < window {
+                if let Some((result_id, task)) = resumed.pop_front() {
+                    self.start(result_id, task, &mut in_flight)?;
+                    continue;
+                }
+                self.prefetch(&entries, &mut pending, window)?;
+                let (entry, nesting): (Entry, Nesting) = match entries.pop_front() {
+                    Some(next) => next,
+                    None => break,
+                };
+                let prefetched: Option<Pending> = pending.remove(&entry.relative_path).flatten();
+                if !self.admitted(&entry) {
+                    continue;
+                }
+                // a file recorded by the run being resumed keeps its result id and its metadata
+                let (result_id, file_metadata): (i64, database::FileMetadata) = match self.find_resumed(&entry)? {
+                    Some(resumed) => resumed,
+                    None => {
+                        let prefetched: Pending = prefetched.unwrap_or_else(|| self.collector.submit(&entry.absolute_path));
+                        // a file that can not be read is still recorded, only without its metadata
+                        let mut file_metadata: database::FileMetadata = prefetched.recv().unwrap_or_else(|_| metadata::stat(&entry.absolute_path));
+                        // a followed symlink has the metadata of its target
+                        if entry.link_target.is_some() {
+                            file_metadata.link_target = entry.link_target.clone();
+                        }
+                        (self.insert_stmt.insert_path(&entry.relative_path, &file_metadata, &nesting.origin)?, file_metadata)
+                    }
+                };
+                // the files of an archive are analyzed right after it, so result ids still follow a depth first order
+                if nesting.depth < self.options.extract_depth && file_metadata.file_type.as_deref() == Some("regular") {
+                    let children: Vec<(Entry, Nesting)> = self.unpack(&entry, result_id, file_metadata.sha256.as_deref(), &nesting)?;
+                    for child in children.into_iter().rev() {
+                        entries.push_front(child);
+                    }
+                }
+                if directory_analyzers && file_metadata.file_type.as_deref() == Some("directory") {
+                    self.directories.push((result_id, FileTask::new(entry.clone(), file_metadata.clone(), nesting, self.config.analyzer.len())));
+                }
+                let task: FileTask = FileTask::new(entry, file_metadata, nesting, self.config.analyzer.len());
+                self.start(result_id, task, &mut in_flight)?;
+            }
+
+            if in_flight.is_empty() {
+                // emitted files come once every queued file is done, by result id of the file they come from,
+                // so that their result ids do not depend on which analyzer finished first either
+                if !self.emitted.is_empty() {
+                    for (_, files) in std::mem::take(&mut self.emitted) {
+                        entries.extend(files);
+                    }
+                    continue;
+                }
+                // then the next pass, on the files with analyzers reading other files
+                let pass: usize = match self.deferred.iter().map(|(_, task)| task.pass).min() {
+                    Some(pass) => pass,
+                    None => break,
+                };
+                let mut next: Vec<(i64, FileTask)> = Vec::new();
+                for (result_id, task) in std::mem::take(&mut self.deferred) {
+                    if task.pass == pass {
+                        next.push((result_id, task));
+                    }
+                    else {
+                        self.deferred.push((result_id, task));
+                    }
+                }
+                next.sort_by_key(|(result_id, _)| *result_id);
+                resumed.extend(next);
+                continue;
+            }
+
+            self.complete(&mut in_flight)?;
+        }
+
+        Ok(())
+    }
 }
 
 pub fn analyze(
@@ -66,61 +695,209 @@ pub fn analyze(
     script_directory: &std::path::Path, 
     config_file: &std::path::Path,
     database_file: &std::path::Path,
-) -> Result<(), Error> 
+    options: &Options,
+) -> Result<Summary, Error> 
 {
 
     let canonical_path = std::fs::canonicalize(firmware_root_directory)?;
-    let abs_path: &std::path::Path = canonical_path.as_path();
-    let config: config::Config = config::Config::load(config_file)?;
+    let config: std::sync::Arc<config::Config> = std::sync::Arc::new(config::Config::load(config_file)?);
+    let extractors: Extractors = Extractors::new(&config.extractor);
+    let budget: Budget = Budget::new(options.max_extract_size);
+    let firmware: Firmware = Firmware { root: &canonical_path, layers: &[], budget };
+    run_analysis(firmware, &extractors, script_directory, config, database_file, options)
+}
+
+// Unpacks a firmware image (tar, gzip, xz, zip, cpio or an [[extractor]] of the config) into a temporary directory
+// and analyzes its files. The temporary directory is removed at the end.
+pub fn analyze_image(
+    firmware_image: &std::path::Path,
+    script_directory: &std::path::Path,
+    config_file: &std::path::Path,
+    database_file: &std::path::Path,
+    options: &Options,
+) -> Result<Summary, Error>
+{
+    let config: std::sync::Arc<config::Config> = std::sync::Arc::new(config::Config::load(config_file)?);
+    let extractors: Extractors = Extractors::new(&config.extractor);
+    let mut budget: Budget = Budget::new(options.max_extract_size);
+    let extracted: image::ExtractedImage = image::extract_image(&extractors, &std::fs::canonicalize(firmware_image)?, &mut budget)?;
+    let canonical_path = std::fs::canonicalize(&extracted.root)?;
+    let firmware: Firmware = Firmware { root: &canonical_path, layers: &extracted.layers, budget };
+    run_analysis(firmware, &extractors, script_directory, config, database_file, options)
+}
+
+// the firmware root directory to analyze
+struct Firmware<'f> {
+    root: &'f std::path::Path,
+    // the images unpacked to make `root`, recorded in the image table
+    layers: &'f [image::Layer],
+    // what is left for the archives found among the files
+    budget: Budget,
+}
+
+fn run_analysis(
+    firmware: Firmware,
+    extractors: &Extractors,
+    script_directory: &std::path::Path,
+    config: std::sync::Arc<config::Config>,
+    database_file: &std::path::Path,
+    options: &Options,
+) -> Result<Summary, Error>
+{
+    let resume: bool = options.resume || !options.rerun.is_empty();
+
+    let mut roots: Vec<usize> = Vec::new();
+    for name in &options.rerun {
+        match config.analyzer.iter().position(|analyzer| &analyzer.name == name) {
+            Some(index) => roots.push(index),
+            None => return Err(Error::UnknownAnalyzer(name.clone())),
+        }
+    }
+    let runnable: Vec<bool> = if options.rerun.is_empty() {
+        vec![true; config.analyzer.len()]
+    }
+    else {
+        config.graph.with_dependents(&roots)
+    };
+    let rerun: Vec<bool> = (0..config.analyzer.len()).map(|index| roots.contains(&index)).collect();
+
+    let entries: std::collections::VecDeque<Entry> = traverse::traverse(firmware.root, options.follow_symlinks)?;
+
+    let cache: Option<cache::Cache> = match &options.cache_database {
+        Some(path) => Some(cache::Cache::open(path)?),
+        None => None,
+    };
+
+    let collector: metadata::Collector = metadata::Collector::new(options.jobs);
+
+    let summary: Summary;
     let mut db: database::Database = database::Database::open(database_file)?;
     {
         db.create_result_table()?;
+        db.create_analyzer_error_table()?;
+        db.create_image_table()?;
         db.create_analyzer_table(&config)?;
+        if !resume && db.has_results()? {
+            return Err(Error::DatabaseNotEmpty());
+        }
+        let mut reemit: Vec<bool> = vec![false; config.analyzer.len()];
+        if resume {
+            for (index, analyzer) in config.analyzer.iter().enumerate() {
+                reemit[index] = db.has_emitted(&analyzer.name)?;
+            }
+        }
+
+        let analyzers = config.analyzer.iter().enumerate().filter(|(index, _)| runnable[*index] || reemit[*index]).map(|(_, analyzer)| analyzer);
+        options.runners.prepare(script_directory, analyzers)?;
+        // after the workers are joined, also when the run fails
+        let _shutdown: RunnersShutdown = RunnersShutdown(&options.runners);
+
         let transaction: database::Transaction = database::Transaction::start_transaction(&mut db)?;
         {
+            if resume {
+                for (analyzer, runnable) in config.analyzer.iter().zip(&runnable) {
+                    if !*runnable {
+                        continue;
+                    }
+                    // failures are retried, and --rerun recomputes the results
+                    transaction.delete_analyzer_errors(&analyzer.name)?;
+                    if !options.rerun.is_empty() {
+                        transaction.delete_analyzer_results(&analyzer.name)?;
+                    }
+                }
+            }
             let mut insert_stmt: database::InsertAnalyerStatement = transaction.insert_stmt(&config)?;
-            let mut select_stmt: database::SelectAnalyzerStatement = transaction.select_stmt(&config)?;
-            traverse_dir(abs_path, abs_path, script_directory, &config, &mut insert_stmt, &mut select_stmt, &analyze_callback)?;
+            let mut image_id: Option<i64> = None;
+            for layer in firmware.layers {
+                image_id = Some(insert_stmt.insert_image(&database::ImageRecord {
+                    parent_id: image_id,
+                    result_id: None,
+                    path: &layer.path,
+                    origin: &layer.origin,
+                    sha256: layer.sha256.as_deref(),
+                    error: None,
+                })?);
+            }
+            let pool: worker::WorkerPool = worker::WorkerPool::new(options.jobs, script_directory, &config, &options.runners);
+            let mut scheduler = Scheduler {
+                base: firmware.root,
+                config: &config,
+                options,
+                insert_stmt,
+                select_stmt: transaction.select_stmt(&config)?,
+                resume_stmt: if resume { Some(transaction.resume_stmt(&config)?) } else { None },
+                runnable,
+                rerun,
+                reemit,
+                cache: cache.as_ref(),
+                collector: &collector,
+                image_id,
+                extractors,
+                budget: firmware.budget,
+                workspace: None,
+                emitted: std::collections::BTreeMap::new(),
+                stage: Scope::File,
+                image_status: vec![AnalyzerStatus::Skipped; config.analyzer.len()],
+                directories: Vec::new(),
+                deferred: Vec::new(),
+                pool: &pool,
+                summary: Summary::new(&config),
+            };
+            scheduler.run(entries)?;
+            summary = scheduler.summary;
         }
         transaction.end_transaction()?;
     }
-    Ok(())
-}
-
-fn traverse_dir<F>(
-    base: &std::path::Path,
-    current: &std::path::Path,
-    script_dir: &std::path::Path,
-    config: &config::Config,
-    insert_stmt: &mut database::InsertAnalyerStatement,
-    select_stmt: &mut database::SelectAnalyzerStatement,
-    callback: &F,
-) -> Result<(), Error>
-where
-    F: Fn(
-        &std::path::Path, 
-        &std::path::Path, 
-        &std::path::Path, 
-        &std::path::Path, 
-        &config::Config,
-        &mut database::InsertAnalyerStatement, 
-        &mut database::SelectAnalyzerStatement
-    ) -> Result<(), Error>,
-{
-    for entry in std::fs::read_dir(current)? {
-        let entry = entry?;
-        let path = entry.path();
-        let relative_path = path.strip_prefix(base).unwrap_or(&path);
-
-        callback(base, relative_path, &path, script_dir, config, insert_stmt, select_stmt)?;
+    if let Some(cache) = cache {
+        cache.commit()?;
+    }
+    Ok(summary)
+}
 
-        if path.is_dir() {
-            traverse_dir(base, &path, script_dir, config, insert_stmt, select_stmt, callback)?;
+// `emit` is a list of {"path": ..., "name": ...}. The name defaults to the file name of the path, and must be a
+// single file name so that the copy stays in the directory of the analyzer.
+fn parse_emit(emit: &serde_json::Value) -> Result<Vec<(std::path::PathBuf, String)>, String> {
+    let items: &Vec<serde_json::Value> = emit.as_array().ok_or_else(|| format!("`emit` must be a list, got {}", emit))?;
+    let mut files: Vec<(std::path::PathBuf, String)> = Vec::new();
+    for item in items {
+        let path: std::path::PathBuf = match item.get("path").and_then(|path| path.as_str()) {
+            Some(path) => std::path::PathBuf::from(path),
+            None => return Err(format!("no path in {}", item)),
+        };
+        let name: String = match item.get("name") {
+            Some(name) => name.as_str().ok_or_else(|| format!("the name must be a string in {}", item))?.to_string(),
+            None => path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(format!("invalid name `{}`", name));
         }
+        if files.iter().any(|(_, other)| *other == name) {
+            return Err(format!("the name `{}` is emitted twice", name));
+        }
+        files.push((path, name));
+    }
+    Ok(files)
+}
+
+struct RunnersShutdown<'r>(&'r Runners);
+
+impl Drop for RunnersShutdown<'_> {
+    fn drop(&mut self) {
+        self.0.shutdown();
     }
-    Ok(())
 }
 
+// path dependent arguments are left out, so that the same file at another path hits the cache
+fn arguments_sha256(args: &serde_json::Value) -> String {
+    let mut args: serde_json::Value = args.clone();
+    if let Some(obj) = args.as_object_mut() {
+        for key in ["filename", "relative_path", "absolute_path"] {
+            obj.remove(key);
+        }
+    }
+    // object keys are serialized in sorted order
+    metadata::sha256_bytes(args.to_string().as_bytes())
+}
 
 #[cfg(test)]
 mod tests {
@@ -137,10 +914,447 @@ mod tests {
 
         fs::create_dir_all(firmware_root_directory)?;
 
-        let result = analyze(firmware_root_directory, script_directory, config_file, database_file);
+        let result = analyze(firmware_root_directory, script_directory, config_file, database_file, &Options::default());
         println!("{:?}", result);
         //assert!(ouesult.is_ok());
 
         Ok(())
     }
+
+    // a firmware root directory, a config and a result database in a temporary directory, analyzed with the
+    // analyzers of the kind "rust" run by a closure
+    struct Fixture {
+        workspace: tempfile::TempDir,
+        root: std::path::PathBuf,
+    }
+
+    impl Fixture {
+        fn new(files: &[(&str, &[u8])]) -> Fixture {
+            let workspace: tempfile::TempDir = tempfile::tempdir().unwrap();
+            let root: std::path::PathBuf = workspace.path().join("root");
+            fs::create_dir_all(&root).unwrap();
+            for (path, data) in files {
+                let path: std::path::PathBuf = root.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, data).unwrap();
+            }
+            Fixture { workspace, root }
+        }
+
+        fn database_file(&self) -> std::path::PathBuf {
+            self.workspace.path().join("result.db")
+        }
+
+        fn analyze<F>(&self, config: &str, options: Options, runner: F) -> Result<Summary, Error>
+        where
+            F: Fn(&crate::config::analyzer::Analyzer, &serde_json::Value) -> Result<serde_json::Value, dispatcher::error::Error> + Send + Sync + 'static,
+        {
+            let config_file: std::path::PathBuf = self.workspace.path().join("config.toml");
+            fs::write(&config_file, config).unwrap();
+            let mut runners: Runners = Runners::new();
+            runners.register("rust", Box::new(runner));
+            let options: Options = Options { runners: std::sync::Arc::new(runners), ..options };
+            analyze(&self.root, self.workspace.path(), &config_file, &self.database_file(), &options)
+        }
+
+        // the rows of a query selecting a single JSON column, e.g. json_array(path, json(value))
+        fn query(&self, sql: &str) -> Vec<serde_json::Value> {
+            let conn: rusqlite::Connection = rusqlite::Connection::open(self.database_file()).unwrap();
+            let rows: Vec<String> = conn.prepare(sql).unwrap().query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap();
+            rows.iter().map(|row| serde_json::from_str(row).unwrap()).collect()
+        }
+    }
+
+    // a file whose output divides by zero or cannot be compared does not stop the run: the value is missing
+    #[test]
+    fn test_analyze_division_by_zero() {
+        let fixture: Fixture = Fixture::new(&[("zero", b"0"), ("two", b"2")]);
+        let summary: Summary = fixture.analyze(r#"
+            [[analyzer]]
+            name = "source"
+            extension = "rust"
+
+            [[analyzer]]
+            name = "ratio"
+            extension = "rust"
+            dependencies = ["source"]
+            conditions = '100 / source.divisor > 10'
+
+            [[analyzer]]
+            name = "quotient"
+            extension = "rust"
+            dependencies = ["source"]
+            arguments = ['100 / source.divisor']
+
+            [[analyzer]]
+            name = "mismatch"
+            extension = "rust"
+            dependencies = ["source"]
+            conditions = 'source.name > 1 or source.divisor == 0'
+        "#, Options::default(), |analyzer, args| {
+            match analyzer.name.as_str() {
+                "source" if args["filename"] == "zero" => Ok(serde_json::json!({"divisor": 0, "name": "zero"})),
+                "source" => Ok(serde_json::json!({"divisor": 2, "name": "two"})),
+                _ => Ok(serde_json::json!({"quotient": args["argument1"]})),
+            }
+        }).unwrap();
+        assert!(summary.analyzers.iter().all(|analyzer| analyzer.failed == 0));
+
+        assert_eq!(fixture.query("SELECT json_quote(result.path) FROM ratio JOIN result ON result.id = ratio.result_id"), vec![serde_json::json!("two")]);
+        assert_eq!(fixture.query("SELECT json_quote(result.path) FROM mismatch JOIN result ON result.id = mismatch.result_id"), vec![serde_json::json!("zero")]);
+        assert_eq!(fixture.query("SELECT json_array(result.path, json(quotient.value)) FROM quotient JOIN result ON result.id = quotient.result_id ORDER BY result.path"), vec![
+            serde_json::json!(["two", {"quotient": 50}]),
+            serde_json::json!(["zero", {"quotient": null}]),
+        ]);
+    }
+
+    // path[...] reads the result of a file that comes later in the traversal, whatever the number of jobs
+    #[test]
+    fn test_analyze_later_file() {
+        for jobs in [1, 4] {
+            let fixture: Fixture = Fixture::new(&[("a_bin", b"bin"), ("libc.so.6", b"lib")]);
+            fixture.analyze(r#"
+                [[analyzer]]
+                name = "linked"
+                extension = "rust"
+                conditions = 'path["libc.so.6"].checksec.relro == "full"'
+                arguments = ['path["libc.so.6"].checksec.relro', 'path[*].checksec.relro']
+
+                [[analyzer]]
+                name = "checksec"
+                extension = "rust"
+            "#, Options { jobs, ..Options::default() }, |analyzer, args| {
+                match analyzer.name.as_str() {
+                    "checksec" => Ok(serde_json::json!({"relro": "full"})),
+                    _ => Ok(serde_json::json!({"relro": args["argument1"], "all": args["argument2"]})),
+                }
+            }).unwrap();
+
+            let value: serde_json::Value = serde_json::json!({"relro": "full", "all": ["full", "full"]});
+            assert_eq!(fixture.query("SELECT json_array(result.path, json(linked.value)) FROM linked JOIN result ON result.id = linked.result_id ORDER BY result.path"), vec![
+                serde_json::json!(["a_bin", value]),
+                serde_json::json!(["libc.so.6", value]),
+            ]);
+        }
+    }
+
+    // --rerun computes the analyzer again instead of reading its old result from the cache, and caches the new one
+    #[test]
+    fn test_rerun_skips_cache() {
+        let fixture: Fixture = Fixture::new(&[("bin", b"elf")]);
+        let config: &str = r#"
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+        "#;
+        let cache_database: Option<std::path::PathBuf> = Some(fixture.workspace.path().join("cache.db"));
+        fixture.analyze(config, Options { cache_database: cache_database.clone(), ..Options::default() }, |_, _| Ok(serde_json::json!({"version": 1}))).unwrap();
+
+        let rerun: Options = Options { cache_database: cache_database.clone(), rerun: vec!["native".to_string()], ..Options::default() };
+        let summary: Summary = fixture.analyze(config, rerun, |_, _| Ok(serde_json::json!({"version": 2}))).unwrap();
+        assert_eq!((summary.analyzers[0].success, summary.analyzers[0].cached), (1, 0));
+        assert_eq!(fixture.query("SELECT json(value) FROM native"), vec![serde_json::json!({"version": 2})]);
+
+        // the same file in another database gets the new result from the cache
+        fs::remove_file(fixture.database_file()).unwrap();
+        let summary: Summary = fixture.analyze(config, Options { cache_database, ..Options::default() }, |_, _| Ok(serde_json::json!({"version": 3}))).unwrap();
+        assert_eq!(summary.analyzers[0].cached, 1);
+        assert_eq!(fixture.query("SELECT json(value) FROM native"), vec![serde_json::json!({"version": 2})]);
+    }
+
+    // --resume reuses the row of a file with the metadata of the first run, without reading the file again
+    #[test]
+    fn test_resume_keeps_metadata() {
+        let fixture: Fixture = Fixture::new(&[("bin", b"elf"), ("etc", b"conf")]);
+        let config: &str = r#"
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+        "#;
+        fixture.analyze(config, Options { jobs: 4, ..Options::default() }, |_, _| Ok(serde_json::json!({}))).unwrap();
+        let first: Vec<serde_json::Value> = fixture.query("SELECT json_array(id, path, sha256, mime) FROM result ORDER BY id");
+        assert_eq!(first[0][2], serde_json::json!(metadata::sha256_bytes(b"elf")));
+
+        fs::write(fixture.root.join("bin"), b"changed").unwrap();
+        let config: String = format!("{}\n[[analyzer]]\nname = \"added\"\nextension = \"rust\"\n", config);
+        let summary: Summary = fixture.analyze(&config, Options { jobs: 4, resume: true, ..Options::default() }, |_, _| Ok(serde_json::json!({}))).unwrap();
+        assert_eq!(summary.analyzers[1].success, 2);
+        assert_eq!(fixture.query("SELECT json_array(id, path, sha256, mime) FROM result ORDER BY id"), first);
+    }
+
+    // a symlink that is not followed gives its analyzers its target inside the firmware, never a file of the host
+    #[test]
+    fn test_symlink_absolute_path() {
+        let fixture: Fixture = Fixture::new(&[("lib/ld-2.31.so", b"elf")]);
+        std::os::unix::fs::symlink("/lib/ld-2.31.so", fixture.root.join("lib/ld.so")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", fixture.root.join("passwd")).unwrap();
+        fixture.analyze(r#"
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+            file_types = ["symlink"]
+        "#, Options::default(), |_, args| Ok(serde_json::json!({"absolute_path": args["absolute_path"]}))).unwrap();
+
+        let target: std::path::PathBuf = fixture.root.canonicalize().unwrap().join("lib/ld-2.31.so");
+        assert_eq!(fixture.query("SELECT json_array(result.path, json(native.value)) FROM native JOIN result ON result.id = native.result_id ORDER BY result.path"), vec![
+            serde_json::json!(["lib/ld.so", {"absolute_path": target.display().to_string()}]),
+            serde_json::json!(["passwd", {"absolute_path": null}]),
+        ]);
+    }
+
+    // the image analyzers of the before stage, the files, the directories from the deepest one and the image analyzers
+    // of the after stage run one after the other. files read the results of the image analyzers like their own.
+    #[test]
+    fn test_analyze_stages() {
+        let fixture: Fixture = Fixture::new(&[("usr/bin/sh", b"elf"), ("usr/lib/libc.so", b"elf")]);
+        let root: std::path::PathBuf = fixture.root.canonicalize().unwrap();
+        let log: std::sync::Arc<std::sync::Mutex<Vec<String>>> = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let runner_log: std::sync::Arc<std::sync::Mutex<Vec<String>>> = log.clone();
+        let summary: Summary = fixture.analyze(r#"
+            [[analyzer]]
+            name = "report"
+            extension = "rust"
+            scope = "image"
+            stage = "after"
+
+            [[analyzer]]
+            name = "tree"
+            extension = "rust"
+            scope = "directory"
+
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+            dependencies = ["os"]
+            file_types = ["regular"]
+            conditions = 'os.id == "openwrt"'
+            arguments = ['os.id']
+
+            [[analyzer]]
+            name = "debian"
+            extension = "rust"
+            dependencies = ["os"]
+            conditions = 'os.id == "debian"'
+
+            [[analyzer]]
+            name = "needs_broken"
+            extension = "rust"
+            dependencies = ["broken"]
+
+            [[analyzer]]
+            name = "os"
+            extension = "rust"
+            scope = "image"
+            stage = "before"
+
+            [[analyzer]]
+            name = "broken"
+            extension = "rust"
+            scope = "image"
+            stage = "before"
+        "#, Options::default(), move |analyzer, args| {
+            let path: std::path::PathBuf = std::path::PathBuf::from(args["absolute_path"].as_str().unwrap());
+            runner_log.lock().unwrap().push(format!("{} /{}", analyzer.name, path.strip_prefix(&root).unwrap().display()));
+            match analyzer.name.as_str() {
+                "os" => Ok(serde_json::json!({"id": "openwrt"})),
+                "broken" => Err(dispatcher::error::Error::SoFailed(1, "broken".to_string())),
+                _ => Ok(serde_json::json!({"os": args["argument1"]})),
+            }
+        }).unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec![
+            "os /", "broken /",
+            "native /usr/bin/sh", "native /usr/lib/libc.so",
+            "tree /usr/lib", "tree /usr/bin", "tree /usr",
+            "report /",
+        ]);
+        let statuses: Vec<(&str, u64, u64, u64)> = summary.analyzers.iter()
+            .map(|analyzer| (analyzer.name.as_str(), analyzer.success, analyzer.failed, analyzer.skipped))
+            .collect();
+        assert_eq!(statuses, vec![
+            ("report", 1, 0, 0),
+            ("tree", 3, 0, 0),
+            ("native", 2, 0, 3),
+            ("debian", 0, 0, 5),
+            ("needs_broken", 0, 0, 5),
+            ("os", 1, 0, 0),
+            ("broken", 0, 1, 0),
+        ]);
+        assert_eq!(fixture.query("SELECT json_array(result_id, json(value)) FROM os"), vec![serde_json::json!([database::IMAGE_RESULT_ID, {"id": "openwrt"}])]);
+        assert_eq!(fixture.query("SELECT json_array(result.path, json(native.value)) FROM native JOIN result ON result.id = native.result_id ORDER BY result.id"), vec![
+            serde_json::json!(["usr/bin/sh", {"os": "openwrt"}]),
+            serde_json::json!(["usr/lib/libc.so", {"os": "openwrt"}]),
+        ]);
+    }
+
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    // the files of an archive are analyzed as `archive!/path`, linked to the archive and its image row, and
+    // --extract-depth stops at the archives they contain
+    #[test]
+    fn test_analyze_archive() {
+        let inner: Vec<u8> = tar(&[("etc/passwd", b"root")]);
+        let update: Vec<u8> = tar(&[("bin/busybox", b"elf"), ("inner.tar", &inner)]);
+        let fixture: Fixture = Fixture::new(&[("update.tar", &update)]);
+        let summary: Summary = fixture.analyze(r#"
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+            file_types = ["regular"]
+        "#, Options { extract_depth: 1, ..Options::default() }, |_, _| Ok(serde_json::json!({}))).unwrap();
+        assert_eq!(summary.analyzers[0].success, 3);
+
+        assert_eq!(fixture.query("SELECT json_array(id, result_id, parent_id, path, origin, error) FROM image"), vec![
+            serde_json::json!([1, 1, null, "update.tar", "tar", null]),
+        ]);
+        assert_eq!(fixture.query("SELECT json_array(result.path, parent.path, result.image_id) FROM result LEFT JOIN result AS parent ON parent.id = result.parent_id WHERE result.file_type = 'regular' ORDER BY result.id"), vec![
+            serde_json::json!(["update.tar", null, null]),
+            serde_json::json!(["update.tar!/bin/busybox", "update.tar", 1]),
+            serde_json::json!(["update.tar!/inner.tar", "update.tar", 1]),
+        ]);
+    }
+
+    // an archive whose extracted files are gone is recorded with its error, and the other files are analyzed
+    #[test]
+    fn test_analyze_broken_extraction() {
+        let fixture: Fixture = Fixture::new(&[("fw.bin", b"FWIMG"), ("readme", b"text")]);
+        let summary: Summary = fixture.analyze(r#"
+            [[extractor]]
+            name = "vanishing"
+            command = ["rm", "-r", "{output}"]
+            signature = "4657494d47"
+
+            [[analyzer]]
+            name = "native"
+            extension = "rust"
+        "#, Options { extract_depth: 1, ..Options::default() }, |_, _| Ok(serde_json::json!({}))).unwrap();
+        assert_eq!(summary.analyzers[0].success, 2);
+
+        let images: Vec<serde_json::Value> = fixture.query("SELECT json_array(origin, error) FROM image");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0][0], "vanishing");
+        let error: &str = images[0][1].as_str().unwrap();
+        assert!(error.contains("; can not remove the extracted files: "), "{}", error);
+    }
+
+    const CARVE_CONFIG: &str = r#"
+        [[analyzer]]
+        name = "carve"
+        extension = "rust"
+
+        [[analyzer]]
+        name = "native"
+        extension = "rust"
+        dependencies = ["carve"]
+    "#;
+
+    // `carve` emits `source` as `part` from every file, the other analyzers read the file they are given
+    fn carving(source: std::path::PathBuf) -> impl Fn(&crate::config::analyzer::Analyzer, &serde_json::Value) -> Result<serde_json::Value, dispatcher::error::Error> + Send + Sync + 'static {
+        move |analyzer, args| match analyzer.name.as_str() {
+            "carve" => Ok(serde_json::json!({"emit": [{"path": source.display().to_string(), "name": "part"}]})),
+            _ => Ok(serde_json::json!({"read": args["absolute_path"].as_str().map(|path| fs::read(path).unwrap().len())})),
+        }
+    }
+
+    // an emitted file is recorded under the file it comes from and analyzed by every analyzer, up to --emit-depth,
+    // and its copy is charged to --max-extract-size
+    #[test]
+    fn test_analyze_emitted() {
+        let fixture: Fixture = Fixture::new(&[("firmware.bin", b"firmware")]);
+        let source: std::path::PathBuf = fixture.workspace.path().join("carved");
+        fs::write(&source, b"part").unwrap();
+        let summary: Summary = fixture.analyze(CARVE_CONFIG, Options { emit_depth: 2, ..Options::default() }, carving(source.clone())).unwrap();
+        assert_eq!(summary.files, 3);
+        assert_eq!(fixture.query("SELECT json_array(result.id, result.path, result.parent_id, result.sha256) FROM result ORDER BY id"), vec![
+            serde_json::json!([1, "firmware.bin", null, metadata::sha256_bytes(b"firmware")]),
+            serde_json::json!([2, "firmware.bin!carve/part", 1, metadata::sha256_bytes(b"part")]),
+            serde_json::json!([3, "firmware.bin!carve/part!carve/part", 2, metadata::sha256_bytes(b"part")]),
+        ]);
+        // the emit key is not stored, and the analyzers of an emitted file read its copy
+        assert_eq!(fixture.query("SELECT json_array(result_id, json(value)) FROM carve ORDER BY result_id"), vec![
+            serde_json::json!([1, {}]),
+            serde_json::json!([2, {}]),
+            serde_json::json!([3, {}]),
+        ]);
+        assert_eq!(fixture.query("SELECT json_array(result_id, json(value)) FROM native ORDER BY result_id"), vec![
+            serde_json::json!([1, {"read": 8}]),
+            serde_json::json!([2, {"read": 4}]),
+            serde_json::json!([3, {"read": 4}]),
+        ]);
+
+        // the second copy is beyond the budget: the analyzer emitting it fails
+        let fixture: Fixture = Fixture::new(&[("firmware.bin", b"firmware")]);
+        let options: Options = Options { emit_depth: 2, max_extract_size: 6, ..Options::default() };
+        let summary: Summary = fixture.analyze(CARVE_CONFIG, options, carving(source)).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(fixture.query("SELECT json_array(result_id, analyzer, kind) FROM analyzer_error"), vec![serde_json::json!([2, "carve", "emit"])]);
+        assert_eq!(fixture.query("SELECT json_quote(path) FROM result ORDER BY id"), vec![
+            serde_json::json!("firmware.bin"),
+            serde_json::json!("firmware.bin!carve/part"),
+        ]);
+    }
+
+    // the copies of emitted files are gone after a run: --rerun and --resume run the analyzer emitting them again when
+    // another analyzer has to run on them, and the emitted files keep their result ids
+    #[test]
+    fn test_resume_emitted() {
+        let fixture: Fixture = Fixture::new(&[("firmware.bin", b"firmware")]);
+        let source: std::path::PathBuf = fixture.workspace.path().join("carved");
+        fs::write(&source, b"part").unwrap();
+        fixture.analyze(CARVE_CONFIG, Options { emit_depth: 1, ..Options::default() }, carving(source.clone())).unwrap();
+        let files: Vec<serde_json::Value> = fixture.query("SELECT json_array(id, path, parent_id) FROM result ORDER BY id");
+        assert_eq!(files, vec![serde_json::json!([1, "firmware.bin", null]), serde_json::json!([2, "firmware.bin!carve/part", 1])]);
+
+        let options: Options = Options { emit_depth: 1, rerun: vec!["native".to_string()], ..Options::default() };
+        fixture.analyze(CARVE_CONFIG, options, carving(source.clone())).unwrap();
+        assert_eq!(fixture.query("SELECT json_array(id, path, parent_id) FROM result ORDER BY id"), files);
+        assert_eq!(fixture.query("SELECT json_array(result_id, json(value)) FROM native ORDER BY result_id"), vec![
+            serde_json::json!([1, {"read": 8}]),
+            serde_json::json!([2, {"read": 4}]),
+        ]);
+        assert_eq!(fixture.query("SELECT json_quote(result_id) FROM carve ORDER BY result_id"), vec![serde_json::json!(1), serde_json::json!(2)]);
+
+        let config: String = format!("{}\n[[analyzer]]\nname = \"added\"\nextension = \"rust\"\n", CARVE_CONFIG);
+        fixture.analyze(&config, Options { emit_depth: 1, resume: true, ..Options::default() }, carving(source.clone())).unwrap();
+        assert_eq!(fixture.query("SELECT json_array(id, path, parent_id) FROM result ORDER BY id"), files);
+        assert_eq!(fixture.query("SELECT json_array(result_id, json(value)) FROM added ORDER BY result_id"), vec![
+            serde_json::json!([1, {"read": 8}]),
+            serde_json::json!([2, {"read": 4}]),
+        ]);
+        assert_eq!(fixture.query("SELECT json_quote(result_id) FROM carve ORDER BY result_id"), vec![serde_json::json!(1), serde_json::json!(2)]);
+
+        // nothing is missing any more: the emitted file is not revisited
+        fs::remove_file(&source).unwrap();
+        let summary: Summary = fixture.analyze(&config, Options { emit_depth: 1, resume: true, ..Options::default() }, carving(source)).unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(fixture.query("SELECT json_quote(count(*)) FROM analyzer_error"), vec![serde_json::json!(0)]);
+    }
+
+    #[test]
+    fn test_parse_emit() {
+        let emit: serde_json::Value = serde_json::json!([
+            {"path": "/tmp/out/section.bin"},
+            {"path": "/tmp/out/1", "name": "config.dec"},
+        ]);
+        assert_eq!(parse_emit(&emit).unwrap(), vec![
+            (std::path::PathBuf::from("/tmp/out/section.bin"), "section.bin".to_string()),
+            (std::path::PathBuf::from("/tmp/out/1"), "config.dec".to_string()),
+        ]);
+
+        assert!(parse_emit(&serde_json::json!({"path": "/tmp/a"})).is_err());
+        assert!(parse_emit(&serde_json::json!([{"name": "a"}])).is_err());
+        assert!(parse_emit(&serde_json::json!([{"path": "/tmp/a", "name": "../a"}])).is_err());
+        assert!(parse_emit(&serde_json::json!([{"path": "/"}])).is_err());
+        assert!(parse_emit(&serde_json::json!([{"path": "/tmp/a"}, {"path": "/var/a"}])).is_err());
+    }
 }
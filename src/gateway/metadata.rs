@@ -0,0 +1,197 @@
+use sha2::Digest;
+use std::io::Read;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use crate::database::database::FileMetadata;
+
+
+// hex encoded SHA-256 of the content of a regular file
+pub fn sha256_file(path: &std::path::Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n: usize = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn sha256_bytes(data: &[u8]) -> String {
+    hex::encode(sha2::Sha256::digest(data))
+}
+
+// None when libmagic or its database can not be loaded. the mime column then stays empty.
+pub fn open_magic() -> Option<magic::Cookie> {
+    let cookie: magic::Cookie = magic::Cookie::open(magic::CookieFlags::MIME_TYPE).ok()?;
+    cookie.load::<&str>(&[]).ok()?;
+    Some(cookie)
+}
+
+// The metadata of the file itself, not of the target of a symlink.
+// What can not be read is left empty instead of failing the whole file.
+pub fn collect(path: &std::path::Path, magic: Option<&magic::Cookie>) -> FileMetadata {
+    let mut file_metadata: FileMetadata = stat(path);
+    if file_metadata.file_type.as_deref() == Some("regular") {
+        file_metadata.sha256 = sha256_file(path).ok();
+    }
+    file_metadata.mime = magic.and_then(|cookie| cookie.file(path).ok());
+    file_metadata
+}
+
+// Collects the metadata of files on threads of its own, as hashing and libmagic read the whole file. Every thread
+// has its own libmagic cookie, which can not be shared between threads.
+pub struct Collector {
+    sender: Option<std::sync::mpsc::Sender<(std::path::PathBuf, std::sync::mpsc::Sender<FileMetadata>)>>,
+    handles: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl Collector {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel::<(std::path::PathBuf, std::sync::mpsc::Sender<FileMetadata>)>();
+        let receiver = std::sync::Arc::new(std::sync::Mutex::new(receiver));
+        let mut handles: Vec<std::thread::JoinHandle<()>> = Vec::new();
+        for _ in 0..threads.max(1) {
+            let receiver = std::sync::Arc::clone(&receiver);
+            handles.push(std::thread::spawn(move || {
+                let magic: Option<magic::Cookie> = open_magic();
+                loop {
+                    let next = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let (path, reply) = match next {
+                        Ok(next) => next,
+                        Err(_) => return,
+                    };
+                    let _ = reply.send(collect(&path, magic.as_ref()));
+                }
+            }));
+        }
+        Collector { sender: Some(sender), handles }
+    }
+
+    // starts collecting the metadata of a file, which the receiver gets once it is done
+    pub fn submit(&self, path: &std::path::Path) -> std::sync::mpsc::Receiver<FileMetadata> {
+        let (reply, receiver) = std::sync::mpsc::channel::<FileMetadata>();
+        if let Some(sender) = &self.sender {
+            let _ = sender.send((path.to_path_buf(), reply));
+        }
+        receiver
+    }
+}
+
+impl Drop for Collector {
+    fn drop(&mut self) {
+        self.sender.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+// what the inode tells, without reading the content: no sha256 and no mime
+pub fn stat(path: &std::path::Path) -> FileMetadata {
+    let file_metadata: std::fs::Metadata = match std::fs::symlink_metadata(path) {
+        Ok(file_metadata) => file_metadata,
+        Err(_) => return FileMetadata::default(),
+    };
+    let file_type: std::fs::FileType = file_metadata.file_type();
+    FileMetadata {
+        size: i64::try_from(file_metadata.len()).ok(),
+        mode: Some(i64::from(file_metadata.mode() & 0o7777)),
+        uid: Some(i64::from(file_metadata.uid())),
+        gid: Some(i64::from(file_metadata.gid())),
+        file_type: Some(file_type_name(&file_type).to_string()),
+        link_target: if file_type.is_symlink() {
+            std::fs::read_link(path).ok().map(|target| target.to_string_lossy().into_owned())
+        } else {
+            None
+        },
+        mtime: Some(file_metadata.mtime()),
+        sha256: None,
+        mime: None,
+    }
+}
+
+fn file_type_name(file_type: &std::fs::FileType) -> &'static str {
+    if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_block_device() {
+        "block_device"
+    } else if file_type.is_char_device() {
+        "char_device"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else {
+        "regular"
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(file.path()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(sha256_bytes(b"abc"), sha256_file(file.path()).unwrap());
+    }
+
+    #[test]
+    fn test_collect() {
+        let dir = tempfile::tempdir().unwrap();
+        let file: std::path::PathBuf = dir.path().join("hello.txt");
+        std::fs::write(&file, b"abc").unwrap();
+        std::fs::set_permissions(&file, std::os::unix::fs::PermissionsExt::from_mode(0o4755)).unwrap();
+        let link: std::path::PathBuf = dir.path().join("link");
+        std::os::unix::fs::symlink("hello.txt", &link).unwrap();
+
+        let magic: Option<magic::Cookie> = open_magic();
+        let metadata: FileMetadata = collect(&file, magic.as_ref());
+        assert_eq!(metadata.size, Some(3));
+        assert_eq!(metadata.mode, Some(0o4755));
+        assert_eq!(metadata.file_type.as_deref(), Some("regular"));
+        assert_eq!(metadata.sha256, Some(sha256_bytes(b"abc")));
+        if magic.is_some() {
+            assert_eq!(metadata.mime.as_deref(), Some("text/plain"));
+        }
+
+        let metadata: FileMetadata = collect(&link, magic.as_ref());
+        assert_eq!(metadata.file_type.as_deref(), Some("symlink"));
+        assert_eq!(metadata.link_target.as_deref(), Some("hello.txt"));
+        assert_eq!(metadata.sha256, None);
+
+        let metadata: FileMetadata = collect(dir.path(), None);
+        assert_eq!(metadata.file_type.as_deref(), Some("directory"));
+        assert_eq!(metadata.mime, None);
+
+        assert_eq!(collect(&dir.path().join("missing"), None), FileMetadata::default());
+    }
+
+    #[test]
+    fn test_collector() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<std::path::PathBuf> = (0..8).map(|index| dir.path().join(index.to_string())).collect();
+        for path in &paths {
+            std::fs::write(path, path.to_string_lossy().as_bytes()).unwrap();
+        }
+        let collector: Collector = Collector::new(3);
+        let pending: Vec<std::sync::mpsc::Receiver<FileMetadata>> = paths.iter().map(|path| collector.submit(path)).collect();
+        for (path, pending) in paths.iter().zip(pending) {
+            assert_eq!(pending.recv().unwrap().sha256, Some(sha256_bytes(path.to_string_lossy().as_bytes())));
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod dispatcher;
 pub mod gateway;
-pub mod error;
\ No newline at end of file
+pub mod worker;
+pub mod error;
+pub mod metadata;
+pub mod traverse;
\ No newline at end of file
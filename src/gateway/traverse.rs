@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+
+// symlinks followed while resolving one path, as in the linux kernel (MAXSYMLINKS)
+const MAX_SYMLINK_HOPS: usize = 40;
+
+// a file found while traversing the firmware root directory
+#[derive(Clone)]
+pub struct Entry {
+    pub relative_path: PathBuf,
+    // where the content is read from. always inside the firmware root directory.
+    pub absolute_path: PathBuf,
+    // what a followed symlink points to. None when the entry is not a followed symlink.
+    pub link_target: Option<String>,
+    // for a symlink recorded as it is, its target resolved inside the root directory it was found in.
+    // None for other entries, and when the target does not exist there.
+    pub resolved_target: Option<PathBuf>,
+}
+
+// Collects every entry under `root` depth first, sorted by name so the order is the same on every run.
+// Symlinks are recorded as they are. With `follow_symlinks`, a symlink is also resolved inside `root` and
+// recorded with the content of its target, and the directory it points to is traversed under the path of the link.
+pub fn traverse(root: &Path, follow_symlinks: bool) -> Result<VecDeque<Entry>, std::io::Error> {
+    let mut entries: VecDeque<Entry> = VecDeque::new();
+    let mut links: Vec<PathBuf> = Vec::new();
+    walk(root, root, Path::new(""), follow_symlinks, &mut links, &mut entries)?;
+    Ok(entries)
+}
+
+// `links` holds the directories of the symlinks followed to reach `dir`
+fn walk(
+    root: &Path,
+    dir: &Path,
+    logical: &Path,
+    follow_symlinks: bool,
+    links: &mut Vec<PathBuf>,
+    entries: &mut VecDeque<Entry>,
+) -> Result<(), std::io::Error>
+{
+    // symlinks are never followed by walkdir itself: it would resolve them on the analysis host
+    let walker = walkdir::WalkDir::new(dir).min_depth(1).follow_links(false).sort_by_file_name();
+    for dir_entry in walker {
+        let dir_entry: walkdir::DirEntry = dir_entry?;
+        let path: &Path = dir_entry.path();
+        let relative_path: PathBuf = logical.join(path.strip_prefix(dir).unwrap_or(path));
+
+        if !dir_entry.path_is_symlink() {
+            entries.push_back(Entry { relative_path, absolute_path: path.to_path_buf(), link_target: None, resolved_target: None });
+            continue;
+        }
+
+        // a link that is not followed, a dangling symlink and a chain of symlinks that never ends are recorded as a symlink
+        let target: PathBuf = match resolve_in_root(root, path) {
+            Some(target) if follow_symlinks => target,
+            resolved_target => {
+                entries.push_back(Entry { relative_path, absolute_path: path.to_path_buf(), link_target: None, resolved_target });
+                continue;
+            }
+        };
+        let link_target: Option<String> = std::fs::read_link(path).ok().map(|target| target.to_string_lossy().into_owned());
+        let is_dir: bool = target.is_dir();
+        entries.push_back(Entry { relative_path: relative_path.clone(), absolute_path: target.clone(), link_target, resolved_target: None });
+        if !is_dir {
+            continue;
+        }
+
+        // A directory containing the link, or one of the links followed before, would be traversed forever.
+        // The link is kept, but not traversed again.
+        let link_dir: &Path = path.parent().unwrap_or(root);
+        if link_dir.starts_with(&target) || links.iter().any(|link| link.starts_with(&target)) {
+            continue;
+        }
+        links.push(link_dir.to_path_buf());
+        walk(root, &target, &relative_path, follow_symlinks, links, entries)?;
+        links.pop();
+    }
+    Ok(())
+}
+
+// Resolves `path`, a path inside `root`, as if `root` were `/` (like chroot). Absolute symlink targets start at `root`
+// and `..` never leaves it, so the result is always inside `root`. None when a file does not exist or there are too
+// many symlinks (a loop).
+pub fn resolve_in_root(root: &Path, path: &Path) -> Option<PathBuf> {
+    let relative: &Path = path.strip_prefix(root).unwrap_or(path);
+    let mut pending: VecDeque<OsString> = relative.components().map(|component| component.as_os_str().to_os_string()).collect();
+    let mut resolved: PathBuf = root.to_path_buf();
+    let mut hops: usize = 0;
+
+    while let Some(name) = pending.pop_front() {
+        match Path::new(&name).components().next() {
+            Some(Component::RootDir) | Some(Component::Prefix(_)) => resolved = root.to_path_buf(),
+            Some(Component::CurDir) | None => {},
+            Some(Component::ParentDir) => {
+                if resolved != root {
+                    resolved.pop();
+                }
+            }
+            Some(Component::Normal(_)) => {
+                let candidate: PathBuf = resolved.join(&name);
+                let file_metadata: std::fs::Metadata = std::fs::symlink_metadata(&candidate).ok()?;
+                if !file_metadata.file_type().is_symlink() {
+                    resolved = candidate;
+                    continue;
+                }
+                hops += 1;
+                if hops > MAX_SYMLINK_HOPS {
+                    return None;
+                }
+                let target: PathBuf = std::fs::read_link(&candidate).ok()?;
+                for component in target.components().rev() {
+                    pending.push_front(component.as_os_str().to_os_string());
+                }
+            }
+        }
+    }
+    Some(resolved)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn relative_paths(entries: &VecDeque<Entry>) -> Vec<String> {
+        entries.iter().map(|entry| entry.relative_path.display().to_string()).collect()
+    }
+
+    #[test]
+    fn test_traverse() {
+        let dir = tempfile::tempdir().unwrap();
+        let root: PathBuf = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        std::fs::create_dir_all(root.join("usr")).unwrap();
+        std::fs::write(root.join("lib/ld-2.31.so"), b"ld").unwrap();
+        symlink("/lib/ld-2.31.so", root.join("lib/ld.so")).unwrap();
+        symlink("../lib", root.join("usr/lib")).unwrap();
+        // a loop and a link to the host
+        symlink("/", root.join("usr/root")).unwrap();
+        symlink("/etc/passwd", root.join("passwd")).unwrap();
+
+        let entries: VecDeque<Entry> = traverse(&root, false).unwrap();
+        assert_eq!(relative_paths(&entries), vec!["lib", "lib/ld-2.31.so", "lib/ld.so", "passwd", "usr", "usr/lib", "usr/root"]);
+        assert!(entries.iter().all(|entry| entry.absolute_path == root.join(&entry.relative_path) && entry.link_target.is_none()));
+
+        let entries: VecDeque<Entry> = traverse(&root, true).unwrap();
+        assert_eq!(relative_paths(&entries), vec![
+            "lib", "lib/ld-2.31.so", "lib/ld.so", "passwd", "usr",
+            "usr/lib", "usr/lib/ld-2.31.so", "usr/lib/ld.so", "usr/root",
+        ]);
+        let ld: &Entry = &entries[2];
+        assert_eq!(ld.absolute_path, root.join("lib/ld-2.31.so"));
+        assert_eq!(ld.link_target.as_deref(), Some("/lib/ld-2.31.so"));
+        // /etc/passwd does not exist in the firmware
+        assert_eq!(entries[3].absolute_path, root.join("passwd"));
+        assert_eq!(entries[3].link_target, None);
+        // the root contains the link, so it is not traversed
+        assert_eq!(entries[8].absolute_path, root);
+    }
+
+    #[test]
+    fn test_resolve_in_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root: PathBuf = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("bin")).unwrap();
+        std::fs::write(root.join("bin/busybox"), b"").unwrap();
+        symlink("busybox", root.join("bin/sh")).unwrap();
+        symlink("../../../../bin/sh", root.join("bin/ash")).unwrap();
+        symlink("loop_b", root.join("bin/loop_a")).unwrap();
+        symlink("loop_a", root.join("bin/loop_b")).unwrap();
+
+        assert_eq!(resolve_in_root(&root, &root.join("bin/sh")), Some(root.join("bin/busybox")));
+        assert_eq!(resolve_in_root(&root, &root.join("bin/ash")), Some(root.join("bin/busybox")));
+        assert_eq!(resolve_in_root(&root, &root.join("bin/loop_a")), None);
+        assert_eq!(resolve_in_root(&root, &root.join("bin/missing")), None);
+    }
+}
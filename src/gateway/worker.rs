@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use crate::config::config::Config;
+use crate::gateway::dispatcher;
+use crate::gateway::dispatcher::runner::Runners;
+
+
+// A job only carries what the analyzer needs to run; the database is never touched by workers.
+pub struct Job {
+    pub result_id: i64,
+    pub analyzer_index: usize,
+    pub args: serde_json::Value,
+}
+
+pub struct JobResult {
+    pub result_id: i64,
+    pub analyzer_index: usize,
+    pub result: Result<serde_json::Value, dispatcher::error::Error>,
+}
+
+pub struct WorkerPool {
+    sender: Option<mpsc::Sender<Job>>,
+    receiver: mpsc::Receiver<JobResult>,
+    stop: Arc<AtomicBool>,
+    handles: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(jobs: usize, script_dir: &std::path::Path, config: &Arc<Config>, runners: &Arc<Runners>) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, result_receiver) = mpsc::channel::<JobResult>();
+        let job_receiver: Arc<Mutex<mpsc::Receiver<Job>>> = Arc::new(Mutex::new(job_receiver));
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+        let mut handles = Vec::new();
+        for _ in 0..jobs.max(1) {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let stop = Arc::clone(&stop);
+            let script_dir: std::path::PathBuf = script_dir.to_path_buf();
+            let config: Arc<Config> = Arc::clone(config);
+            let runners: Arc<Runners> = Arc::clone(runners);
+            handles.push(std::thread::spawn(move || {
+                worker_loop(&script_dir, &config, &runners, &job_receiver, &result_sender, &stop)
+            }));
+        }
+
+        WorkerPool {
+            sender: Some(job_sender),
+            receiver: result_receiver,
+            stop,
+            handles,
+        }
+    }
+
+    pub fn submit(&self, job: Job) {
+        if let Some(sender) = &self.sender {
+            // workers only exit after the sender is dropped, so this can not fail while the pool is alive
+            let _ = sender.send(job);
+        }
+    }
+
+    // blocks until a worker finishes a job
+    pub fn recv(&self) -> Option<JobResult> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // jobs still queued when the pool is dropped (e.g. after an error) are not run
+        self.stop.store(true, Ordering::SeqCst);
+        self.sender.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn worker_loop(
+    script_dir: &std::path::Path,
+    config: &Config,
+    runners: &Runners,
+    job_receiver: &Mutex<mpsc::Receiver<Job>>,
+    result_sender: &mpsc::Sender<JobResult>,
+    stop: &AtomicBool,
+) {
+    loop {
+        let job: Job = {
+            let receiver = match job_receiver.lock() {
+                Ok(receiver) => receiver,
+                Err(_) => return,
+            };
+            match receiver.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            }
+        };
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let analyzer = &config.analyzer[job.analyzer_index];
+        let result = dispatcher::dispatcher::execute_analyzer(runners, script_dir, analyzer, &job.args);
+        let sent = result_sender.send(JobResult {
+            result_id: job.result_id,
+            analyzer_index: job.analyzer_index,
+            result,
+        });
+        if sent.is_err() {
+            return;
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod config;
 pub mod gateway;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod extract;
\ No newline at end of file
@@ -5,8 +5,12 @@ use clap::Parser;
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Firmware root directory
-    #[arg(short, long)]
-    firmware_root_dir: PathBuf,
+    #[arg(short, long, required_unless_present = "firmware_image", conflicts_with = "firmware_image")]
+    firmware_root_dir: Option<PathBuf>,
+
+    /// Firmware image (tar, gzip, xz, zip, cpio, ...), unpacked into a temporary directory instead of --firmware-root-dir
+    #[arg(short = 'i', long)]
+    firmware_image: Option<PathBuf>,
 
     /// Analyzer directory
     #[arg(short, long)]
@@ -19,13 +23,63 @@ struct Args {
     /// Output database file(sqlite)
     #[arg(short, long)]
     database_file: PathBuf,
+
+    /// Number of analyzers run in parallel
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: u16,
+
+    /// Stop at the first analyzer error instead of recording it and continuing
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Continue an existing database: keep its results and only run the missing analyzers
+    #[arg(long)]
+    resume: bool,
+
+    /// Recompute an analyzer and its dependents on an existing database
+    #[arg(long, value_name = "ANALYZER")]
+    rerun: Vec<String>,
+
+    /// Result cache shared between runs (sqlite), keyed by the SHA-256 of each file
+    #[arg(long, value_name = "CACHE_DATABASE_FILE")]
+    cache_database: Option<PathBuf>,
+
+    /// Follow symlinks, resolving absolute targets inside the firmware root directory
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Only analyze the files matching this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Do not analyze the files matching this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Do not analyze the files larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_file_size: Option<u64>,
+
+    /// Only analyze the files of this type: regular, directory, symlink, block_device, char_device, fifo or socket (repeatable)
+    #[arg(long, value_name = "FILE_TYPE")]
+    file_type: Vec<String>,
+
+    /// Unpack the archives found among the files and analyze their files, up to this many archives deep
+    #[arg(long, value_name = "DEPTH", default_value_t = 0)]
+    extract_depth: usize,
+
+    /// Bytes the extraction of the image and of the archives in it may write
+    #[arg(long, value_name = "BYTES", default_value_t = faaf::gateway::gateway::DEFAULT_MAX_EXTRACT_SIZE)]
+    max_extract_size: u64,
+
+    /// Analyze the files written by analyzers (the `emit` key of their output), up to this many files deep
+    #[arg(long, value_name = "DEPTH", default_value_t = faaf::gateway::gateway::DEFAULT_EMIT_DEPTH)]
+    emit_depth: usize,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let firmware_root_directory = args.firmware_root_dir.canonicalize().expect("Failed to convert to absolute path");
-
     if !args.script_directory.is_dir() {
         panic!("Script Directory is not a valid directory");
     }
@@ -38,6 +92,39 @@ fn main() {
         panic!("Database File path exists and is not a file");
     }
 
-    let result = faaf::gateway::gateway::analyze(&firmware_root_directory, &args.script_directory, &args.config_file, &args.database_file);
-    println!("{:?}", result)
+    let file_types: Option<Vec<String>> = if args.file_type.is_empty() { None } else { Some(args.file_type) };
+    let filter = faaf::config::filter::FileFilter::new(&args.include, &args.exclude, args.max_file_size, file_types)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let options = faaf::gateway::gateway::Options {
+        jobs: args.jobs as usize,
+        fail_fast: args.fail_fast,
+        resume: args.resume,
+        rerun: args.rerun,
+        cache_database: args.cache_database,
+        follow_symlinks: args.follow_symlinks,
+        filter,
+        extract_depth: args.extract_depth,
+        max_extract_size: args.max_extract_size,
+        emit_depth: args.emit_depth,
+        runners: std::sync::Arc::new(faaf::gateway::dispatcher::runner::Runners::new()),
+    };
+
+    let result = match (&args.firmware_root_dir, &args.firmware_image) {
+        (_, Some(firmware_image)) => {
+            if !firmware_image.is_file() {
+                panic!("Firmware Image is not a valid file");
+            }
+            faaf::gateway::gateway::analyze_image(firmware_image, &args.script_directory, &args.config_file, &args.database_file, &options)
+        }
+        (Some(firmware_root_dir), None) => {
+            let firmware_root_directory = firmware_root_dir.canonicalize().expect("Failed to convert to absolute path");
+            faaf::gateway::gateway::analyze(&firmware_root_directory, &args.script_directory, &args.config_file, &args.database_file, &options)
+        }
+        (None, None) => unreachable!("clap requires one of them"),
+    };
+    match result {
+        Ok(summary) => print!("{}", summary),
+        Err(err) => println!("{:?}", err),
+    }
 }
\ No newline at end of file